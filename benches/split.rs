@@ -15,6 +15,50 @@ fn bench_bsp(b: &mut test::Bencher) {
     let view = vec3(0.0, 0.0, 1.0);
     b.iter(|| {
         let p = polys.clone();
-        splitter.solve(&p, view);
+        splitter.solve(p.iter(), view);
     });
 }
+
+#[bench]
+fn bench_bsp_large(b: &mut test::Bencher) {
+    let polys = Arc::new(make_grid(12));
+    let mut splitter = BspSplitter::new();
+    let view = vec3(0.0, 0.0, 1.0);
+    b.iter(|| {
+        let p = polys.clone();
+        splitter.solve(p.iter(), view);
+    });
+}
+
+#[cfg(feature = "bench-scenes")]
+fn bench_scene(b: &mut test::Bencher, polys: Vec<plane_split::Polygon<usize>>) {
+    let mut splitter = BspSplitter::new();
+    let view = vec3(0.0, 0.0, -1.0);
+    b.iter(|| {
+        splitter.solve(&polys, view);
+    });
+}
+
+#[cfg(feature = "bench-scenes")]
+#[bench]
+fn bench_carousel(b: &mut test::Bencher) {
+    bench_scene(b, plane_split::scenes::carousel(24, 500.0, 0.3));
+}
+
+#[cfg(feature = "bench-scenes")]
+#[bench]
+fn bench_card_stack(b: &mut test::Bencher) {
+    bench_scene(b, plane_split::scenes::card_stack(1, 20, 0.5));
+}
+
+#[cfg(feature = "bench-scenes")]
+#[bench]
+fn bench_cube_map(b: &mut test::Bencher) {
+    bench_scene(b, plane_split::scenes::cube_map(1000.0, 4));
+}
+
+#[cfg(feature = "bench-scenes")]
+#[bench]
+fn bench_layer_pile(b: &mut test::Bencher) {
+    bench_scene(b, plane_split::scenes::layer_pile(1, 50, 0.5, 0.001));
+}
@@ -0,0 +1,19 @@
+//! Checks that the heuristics agree on the sorted output of random polygons.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use euclid::default::Vector3D;
+use plane_split::{BspSplitter, Heuristic, Polygon};
+
+fuzz_target!(|polygons: Vec<Polygon<u16>>| {
+    let view = Vector3D::new(0.0, 0.0, -1.0);
+    for &heuristic in &[Heuristic::FirstPolygon, Heuristic::MinSplits] {
+        let mut splitter = BspSplitter::with_heuristic(heuristic);
+        let sorted = splitter.solve(&polygons, view);
+        assert!(sorted.iter().all(|p| p.is_valid()));
+        if splitter.split_errors().is_empty() {
+            splitter.validate(view).unwrap();
+        }
+    }
+});
@@ -0,0 +1,24 @@
+//! Sorts random near-degenerate polygons and checks the output.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use euclid::default::Vector3D;
+use plane_split::{verify_order, BspSplitter, Polygon};
+
+fuzz_target!(|input: (Vec<Polygon<u16>>, (i8, i8, i8))| {
+    let (polygons, (x, y, z)) = input;
+    let view = Vector3D::new(x as f64, y as f64, z as f64);
+    if view.square_length() == 0.0 {
+        return;
+    }
+
+    let mut splitter = BspSplitter::new();
+    let sorted = splitter.solve(&polygons, view).to_vec();
+    if !splitter.split_errors().is_empty() {
+        // polygons that couldn't be split are not expected to be ordered
+        return;
+    }
+    splitter.validate(view).unwrap();
+    verify_order(&sorted, view).unwrap();
+});
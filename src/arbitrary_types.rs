@@ -0,0 +1,44 @@
+use crate::{Plane, Polygon};
+
+use alloc::vec::Vec;
+use arbitrary::{Arbitrary, Error, Result, Unstructured};
+use euclid::default::{Point3D, Vector3D};
+
+/// Scale of the small offsets applied to the polygons, which make
+/// them nearly coplanar or nearly touching instead of exactly so.
+const JITTER: f64 = 1e-7;
+
+/// Produces convex planar polygons with the coordinates taken mostly from
+/// a small set of values, so that coplanar, nearly coplanar, and touching
+/// polygons are common, as these are the cases most likely to go wrong.
+impl<'a, A> Arbitrary<'a> for Polygon<A, f64>
+where
+    A: Arbitrary<'a> + Clone,
+{
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut value = || -> Result<f64> {
+            let base = i8::arbitrary(u)? as f64;
+            let jitter = i8::arbitrary(u)? as f64 * JITTER;
+            Ok(base + jitter)
+        };
+        let center = Point3D::new(value()?, value()?, value()?);
+        let normal = Vector3D::new(value()?, value()?, value()?);
+        let plane = Plane::from_point_normal(center, normal).ok_or(Error::IncorrectFormat)?;
+        let (u_axis, v_axis) = plane.basis();
+
+        let radius = u8::arbitrary(u)? as f64 + 1.0;
+        let count = u.int_in_range(3..=6)?;
+        // points on a circle, in the order of their angles, make a convex polygon
+        let mut angles = Vec::with_capacity(count);
+        for _ in 0..count {
+            angles.push(u16::arbitrary(u)?);
+        }
+        angles.sort_unstable();
+        angles.dedup();
+        let points = angles.iter().map(|&angle| {
+            let angle = angle as f64 / 65536.0 * core::f64::consts::TAU;
+            center + (u_axis * angle.cos() + v_axis * angle.sin()) * radius
+        });
+        Polygon::from_points(points, A::arbitrary(u)?).ok_or(Error::IncorrectFormat)
+    }
+}
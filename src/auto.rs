@@ -0,0 +1,169 @@
+use crate::{naive::topological_sort, BspSplitter, EpsilonConfig, Polygon, Splitter};
+
+use euclid::{approxeq::ApproxEq, Box3D, UnknownUnit, Vector3D};
+use num_traits::Float;
+
+use alloc::{vec, vec::Vec};
+use core::{cmp::Ordering, fmt};
+
+/// A splitter choosing the algorithm based on the added polygons.
+///
+/// If the bounding boxes of the polygons don't overlap, none of them
+/// can intersect another, and they are sorted without splitting: the ones
+/// overlapping on the screen by the side of the axis separating their
+/// bounding boxes, and the rest by the depth of their centroids along the view.
+/// Otherwise, the polygons are sorted by the inner `BspSplitter`.
+///
+/// The depth sort is meant for a handful of separate layers, such as
+/// the planes stacked along the view, where it's much cheaper than
+/// building a tree.
+pub struct AutoSplitter<A: Clone, T = f64, U = UnknownUnit> {
+    polygons: Vec<Polygon<A, T, U>>,
+    bounds: Vec<Box3D<T, U>>,
+    result: Vec<Polygon<A, T, U>>,
+    inner: BspSplitter<A, T, U>,
+    epsilons: EpsilonConfig<T>,
+    used_bsp: bool,
+}
+
+impl<A, T, U> AutoSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    /// Create a new automatic splitter.
+    pub fn new() -> Self {
+        Self::with_epsilons(EpsilonConfig::default())
+    }
+
+    /// Create a new automatic splitter, with the thresholds
+    /// used by the inner BSP splitter.
+    pub fn with_epsilons(epsilons: EpsilonConfig<T>) -> Self {
+        AutoSplitter {
+            polygons: Vec::new(),
+            bounds: Vec::new(),
+            result: Vec::new(),
+            inner: BspSplitter::with_epsilons(epsilons),
+            epsilons,
+            used_bsp: false,
+        }
+    }
+}
+
+impl<A, T, U> AutoSplitter<A, T, U>
+where
+    A: Clone,
+{
+    /// Get the underlying BSP splitter.
+    pub fn inner(&self) -> &BspSplitter<A, T, U> {
+        &self.inner
+    }
+
+    /// Get the underlying BSP splitter for configuration.
+    pub fn inner_mut(&mut self) -> &mut BspSplitter<A, T, U> {
+        &mut self.inner
+    }
+
+    /// Check if the last sort was done by the BSP splitter.
+    pub fn used_bsp(&self) -> bool {
+        self.used_bsp
+    }
+}
+
+impl<A, T, U> Default for AutoSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A, T, U> Splitter<A, T, U> for AutoSplitter<A, T, U>
+where
+    A: Clone + fmt::Debug + Default,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn reset(&mut self) {
+        self.polygons.clear();
+        self.bounds.clear();
+        self.result.clear();
+        self.inner.reset();
+        self.used_bsp = false;
+    }
+
+    fn add(&mut self, polygon: Polygon<A, T, U>) {
+        self.bounds.push(polygon.bounding_box());
+        self.polygons.push(polygon);
+    }
+
+    fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        let bounds = &self.bounds;
+        self.used_bsp =
+            (0..bounds.len()).any(|i| bounds[i + 1..].iter().any(|b| b.intersects(&bounds[i])));
+
+        if self.used_bsp {
+            log::debug!("\tBounds overlap, sorting with the BSP tree");
+            self.inner.reset();
+            for poly in &self.polygons {
+                self.inner.add(poly.clone());
+            }
+            return self.inner.sort(view);
+        }
+
+        // the view points towards the viewer, so the farthest come first
+        let polygons = &self.polygons;
+        let depths: Vec<T> = polygons
+            .iter()
+            .map(|p| p.centroid().to_vector().dot(view))
+            .collect();
+        let mut by_depth: Vec<usize> = (0..polygons.len()).collect();
+        by_depth.sort_by(|&a, &b| depths[a].partial_cmp(&depths[b]).unwrap_or(Ordering::Equal));
+
+        // the centroids don't tell the order of the polygons overlapping on
+        // the screen, but the axis separating their bounding boxes does
+        let epsilon = self.epsilons.coplanarity;
+        let mut after: Vec<Vec<usize>> = vec![Vec::new(); by_depth.len()];
+        for i in 0..by_depth.len() {
+            for j in i + 1..by_depth.len() {
+                let (a, b) = (by_depth[i], by_depth[j]);
+                if !polygons[a].overlaps_along(&polygons[b], &view, epsilon) {
+                    continue;
+                }
+                match is_nearer(&bounds[a], &bounds[b], &view) {
+                    Some(true) => after[j].push(i),
+                    Some(false) | None => after[i].push(j),
+                }
+            }
+        }
+
+        let (order, _) = topological_sort(&after, true);
+        self.result.clear();
+        self.result
+            .extend(order.into_iter().map(|i| polygons[by_depth[i]].clone()));
+        &self.result
+    }
+}
+
+/// Tell if the box `a` is nearer to the viewer than the box `b`, judging by
+/// an axis separating them that isn't perpendicular to the view.
+/// Returns `None` if there is no such axis.
+fn is_nearer<T: Float, U>(a: &Box3D<T, U>, b: &Box3D<T, U>, view: &Vector3D<T, U>) -> Option<bool> {
+    let axes = [
+        (a.min.x, a.max.x, b.min.x, b.max.x, view.x),
+        (a.min.y, a.max.y, b.min.y, b.max.y, view.y),
+        (a.min.z, a.max.z, b.min.z, b.max.z, view.z),
+    ];
+    axes.iter()
+        .filter(|axis| axis.4 != T::zero())
+        .find_map(|&(a_min, a_max, b_min, b_max, dir)| {
+            if a_max <= b_min {
+                Some(dir < T::zero())
+            } else if b_max <= a_min {
+                Some(dir > T::zero())
+            } else {
+                None
+            }
+        })
+}
@@ -1,187 +1,2627 @@
-use crate::{Plane, PlaneCut, Polygon};
+use crate::{
+    polygon::Cut, validate::validate_by, BspNode, ClipRegion, EpsilonConfig, Frustum, Plane,
+    Polygon, SplitError, Splitter, ValidationError,
+};
 
-use euclid::default::{Point3D, Vector3D};
+use euclid::{approxeq::ApproxEq, Box3D, Point3D, UnknownUnit, Vector3D};
+use num_traits::Float;
 use smallvec::SmallVec;
 
-use std::fmt;
+use alloc::{borrow::Cow, boxed::Box, collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use core::{borrow::Borrow, cmp::Ordering, fmt, ops::Range};
+
+/// An index of a polygon fragment stored in the splitter.
+///
+/// It stays valid until the splitter is reset.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct FragmentId(u32);
+
+impl FragmentId {
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A key of a polygon fragment that tells where it came from:
+/// the index of the source polygon, in the order they were added,
+/// and the sequence number of the fragment among the ones of that source.
+///
+/// It stays valid until the splitter is reset, so it can be used
+/// to associate resources with the fragments within a frame.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FragmentKey {
+    /// Index of the source polygon. The index of a removed polygon
+    /// is given to the next one added.
+    pub source: u32,
+    /// Sequence number of the fragment within the source polygon.
+    pub split: u32,
+}
+
+/// An identifier of a plane that splits polygons, which is
+/// the plane of a BSP tree node.
+///
+/// It stays valid until the splitter is reset.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct PlaneId(u32);
+
+/// The origin of a polygon fragment.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FragmentInfo<A> {
+    /// Anchor of the polygon that was added to the splitter.
+    pub source_anchor: A,
+    /// Planes that cut the polygon on the way to this fragment,
+    /// in the order of the cuts.
+    pub cut_planes: SmallVec<[PlaneId; 2]>,
+}
+
+type Cuts = SmallVec<[PlaneId; 2]>;
+
+/// The source of a fragment on its way down the tree,
+/// and the planes that cut it so far.
+#[derive(Clone, Debug, Default)]
+struct Lineage {
+    source: u32,
+    cuts: Cuts,
+}
+
+impl Lineage {
+    fn new(source: u32) -> Self {
+        Lineage {
+            source,
+            cuts: Cuts::new(),
+        }
+    }
+}
+
+/// The effect that adding a polygon would have on the tree,
+/// as reported by `BspSplitter::probe`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProbeResult {
+    /// The polygon would be added whole, on its own plane.
+    NoCut,
+    /// The polygon would be cut this many times.
+    WouldSplit(usize),
+    /// The polygon would be added whole, on the plane of an existing node.
+    Coplanar,
+}
+
+/// A phase boundary of the work done by `BspSplitter`, reported to the `Profiler`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfileEvent {
+    /// Inserting polygons into the tree has started.
+    InsertStart,
+    /// Inserting polygons into the tree has finished.
+    InsertEnd,
+    /// A polygon was cut in two by a node plane.
+    Cut,
+    /// Traversing the tree to sort the fragments has started.
+    SortStart,
+    /// Traversing the tree to sort the fragments has finished.
+    SortEnd,
+}
+
+/// Receiver of the phase boundaries of `BspSplitter`, which can be used
+/// to measure the time spent on plane splitting, or to count the cuts.
+pub trait Profiler {
+    /// Handle the event, as it happens.
+    fn event(&mut self, event: ProfileEvent);
+}
+
+/// The first polygon hit by a ray.
+pub struct Hit<A, T = f64, U = UnknownUnit> {
+    /// Anchor of the polygon that was hit.
+    pub anchor: A,
+    /// The point where the ray hits the polygon.
+    pub point: Point3D<T, U>,
+    /// Distance from the origin to the point, in the units of the ray direction.
+    pub t: T,
+}
+
+impl<A: Clone, T: Copy, U> Clone for Hit<A, T, U> {
+    fn clone(&self) -> Self {
+        Hit {
+            anchor: self.anchor.clone(),
+            point: self.point,
+            t: self.t,
+        }
+    }
+}
+
+impl<A: Copy, T: Copy, U> Copy for Hit<A, T, U> {}
+
+impl<A: fmt::Debug, T: fmt::Debug, U> fmt::Debug for Hit<A, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Hit")
+            .field("anchor", &self.anchor)
+            .field("point", &self.point)
+            .field("t", &self.t)
+            .finish()
+    }
+}
+
+impl<A: PartialEq, T: PartialEq, U> PartialEq for Hit<A, T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.anchor == other.anchor && self.point == other.point && self.t == other.t
+    }
+}
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct PolygonIdx(usize);
+pub struct NodeIdx(u32);
+
+impl NodeIdx {
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// A step of the tree traversal.
+enum Visit {
+    /// Choose the order of the node children.
+    Node(NodeIdx),
+    /// Process the values of the node.
+    Values(NodeIdx),
+}
+
+/// The order of the sorted polygons.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SortOrder {
+    /// The farthest polygons come first, as needed for blending.
+    #[default]
+    BackToFront,
+    /// The nearest polygons come first, to benefit from early depth rejection.
+    FrontToBack,
+}
+
+/// Additional options for sorting the polygons.
+pub struct SortOptions<'a, A, T = f64> {
+    /// Drop the fragments that are completely hidden behind
+    /// a single opaque fragment that is drawn after them.
+    pub cull_occluded: bool,
+    /// Tell if the polygons with the given anchor are opaque.
+    pub opaque: &'a dyn Fn(&A) -> bool,
+    /// Skip the fragments facing away from the view while traversing
+    /// the tree, i.e. the ones whose plane normal points away from the viewer.
+    pub cull_backfaces: bool,
+    /// Tell the sides of the polygons with the given anchor that can be seen.
+    /// Only the single-sided ones are dropped by `cull_backfaces`.
+    /// By default, every anchor is `Sidedness::default()`, i.e. single-sided.
+    pub sidedness: &'a dyn Fn(&A) -> Sidedness,
+    /// The order of the resulting polygons.
+    pub order: SortOrder,
+    /// Snap the vertices of the fragments to a grid of this size, welding
+    /// the ones closer than that together, so that the same scene produces
+    /// exactly the same fragments frame over frame. Zero disables snapping.
+    ///
+    /// The fragments that collapse into less than 3 points are dropped.
+    pub vertex_grid: T,
+    /// Insert the vertices of the neighboring fragments lying on the edges
+    /// of each fragment, so that adjacent edges match exactly and the mesh
+    /// is rendered without cracks. The fragments may get more than 4 points.
+    pub repair_t_junctions: bool,
+    /// Drop the fragments with a smaller area.
+    pub min_world_area: T,
+    /// Drop the fragments with a smaller area when projected
+    /// along the view vector and scaled by `viewport_scale`.
+    pub min_projected_area: T,
+    /// The scale from the world units to the viewport units, e.g. pixels.
+    pub viewport_scale: T,
+}
+
+impl<A, T: Float> Default for SortOptions<'_, A, T> {
+    fn default() -> Self {
+        SortOptions {
+            cull_occluded: false,
+            opaque: &|_| false,
+            cull_backfaces: false,
+            sidedness: &|_| Sidedness::default(),
+            order: SortOrder::default(),
+            vertex_grid: T::zero(),
+            repair_t_junctions: false,
+            min_world_area: T::zero(),
+            min_projected_area: T::zero(),
+            viewport_scale: T::one(),
+        }
+    }
+}
+
+// not derived, since that would require `A: Copy`
+impl<A, T: Copy> Clone for SortOptions<'_, A, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<A, T: Copy> Copy for SortOptions<'_, A, T> {}
+
+impl<A, T: fmt::Debug> fmt::Debug for SortOptions<'_, A, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SortOptions")
+            .field("cull_occluded", &self.cull_occluded)
+            .field("cull_backfaces", &self.cull_backfaces)
+            .field("order", &self.order)
+            .field("vertex_grid", &self.vertex_grid)
+            .field("repair_t_junctions", &self.repair_t_junctions)
+            .field("min_world_area", &self.min_world_area)
+            .field("min_projected_area", &self.min_projected_area)
+            .field("viewport_scale", &self.viewport_scale)
+            .finish()
+    }
+}
+
+/// The sides of a polygon that can be seen, as set by the CSS `backface-visibility`.
+///
+/// Polygons are single-sided by default, so that `SortOptions::cull_backfaces`
+/// culls every back face unless the anchor is marked as double-sided.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Sidedness {
+    /// Both sides are visible.
+    DoubleSided,
+    /// Only the front side is visible, i.e. the one the plane normal points to.
+    #[default]
+    SingleSided,
+}
+
+/// Statistics of the work done by the splitter since the last reset.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SplitStats {
+    /// Number of polygons added to the splitter.
+    pub input_polys: usize,
+    /// Number of polygons produced by the last sort.
+    pub output_polys: usize,
+    /// Number of polygons cut in two by a node plane.
+    pub cuts_performed: usize,
+    /// Length of the longest path from the root to a leaf.
+    pub tree_depth: usize,
+    /// Number of nodes in the tree.
+    pub node_count: usize,
+    /// Number of times the storage of fragments or nodes had to grow.
+    pub allocations: usize,
+}
+
+/// Strategy for choosing the splitting planes of the BSP tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Heuristic {
+    /// Insert the polygons as they are added, using the first polygon
+    /// that reaches a node as the node plane.
+    #[default]
+    FirstPolygon,
+    /// Buffer the added polygons and build the tree when sorting,
+    /// choosing the node planes that cause the fewest splits.
+    MinSplits,
+}
+
+/// Maximum number of candidate planes scored per node by `Heuristic::MinSplits`.
+const MAX_CANDIDATES: usize = 16;
+/// Cost of a split relative to the imbalance between the front and back sides.
+const SPLIT_COST: usize = 8;
+
+/// Binary Space Partitioning splitter, uses a BSP tree.
+pub struct BspSplitter<A: Clone, T = f64, U = UnknownUnit> {
+    result: Vec<Polygon<A, T, U>>,
+    result_ids: Vec<FragmentId>,
+    nodes: Vec<Node<T, U>>,
+    polygons: Vec<Polygon<A, T, U>>,
+    bounds: Vec<Box3D<T, U>>,
+    keys: Vec<FragmentKey>,
+    split_counts: Vec<u32>,
+    epsilons: EpsilonConfig<T>,
+    frustum: Option<Frustum<T, U>>,
+    sibling_order: Option<Arc<SiblingOrder<A>>>,
+    split_callback: Option<Box<SplitCallback<A, T, U>>>,
+    profiler: Option<Box<dyn Profiler + Send + Sync>>,
+    insert_stack: Vec<PendingInsert<A, T, U>>,
+    heuristic: Heuristic,
+    deterministic: bool,
+    pending: Vec<(Polygon<A, T, U>, u32)>,
+    provenance: Option<Vec<Cuts>>,
+    free_nodes: Vec<NodeIdx>,
+    spare_nodes: Vec<Node<T, U>>,
+    free_fragments: Vec<FragmentId>,
+    free_sources: Vec<u32>,
+    dropped_small: usize,
+    budget: Option<usize>,
+    budget_overflow: usize,
+    stats: SplitStats,
+    uncertain_cuts: Option<Vec<(A, A)>>,
+    split_errors: Vec<(A, SplitError)>,
+    recenter: bool,
+    origin: Option<Vector3D<T, U>>,
+    #[cfg(feature = "robust")]
+    pub(crate) exact: bool,
+}
+
+/// A fragment waiting to be inserted into the node, with its bounding box
+/// and the depth of the node.
+type PendingInsert<A, T, U> = (NodeIdx, Polygon<A, T, U>, Lineage, Box3D<T, U>, u32);
+
+type SiblingOrder<A> = dyn Fn(&A, &A) -> Ordering + Send + Sync;
+
+type SplitCallback<A, T, U> = dyn FnMut(&A, &Plane<T, U>) + Send + Sync;
+
+impl<A, T, U> BspSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    /// Create a new BSP splitter.
+    pub fn new() -> Self {
+        Self::with_epsilons(EpsilonConfig::default())
+    }
+
+    /// Create a new BSP splitter with custom thresholds for
+    /// classifying and splitting the polygons.
+    pub fn with_epsilons(epsilons: EpsilonConfig<T>) -> Self {
+        BspSplitter {
+            result: Vec::new(),
+            result_ids: Vec::new(),
+            nodes: vec![Node::new()],
+            polygons: Vec::new(),
+            bounds: Vec::new(),
+            keys: Vec::new(),
+            split_counts: Vec::new(),
+            epsilons,
+            frustum: None,
+            sibling_order: None,
+            split_callback: None,
+            profiler: None,
+            insert_stack: Vec::new(),
+            heuristic: Heuristic::default(),
+            deterministic: false,
+            pending: Vec::new(),
+            provenance: None,
+            free_nodes: Vec::new(),
+            spare_nodes: Vec::new(),
+            free_fragments: Vec::new(),
+            free_sources: Vec::new(),
+            dropped_small: 0,
+            budget: None,
+            budget_overflow: 0,
+            stats: SplitStats::default(),
+            uncertain_cuts: None,
+            split_errors: Vec::new(),
+            recenter: false,
+            origin: None,
+            #[cfg(feature = "robust")]
+            exact: false,
+        }
+    }
+
+    /// Create a new BSP splitter with the storage reserved
+    /// for the given number of polygons.
+    pub fn with_capacity(polys: usize) -> Self {
+        let mut splitter = Self::new();
+        splitter.polygons.reserve(polys);
+        splitter.keys.reserve(polys);
+        splitter.split_counts.reserve(polys);
+        splitter.nodes.reserve(polys);
+        splitter.result.reserve(polys);
+        splitter.result_ids.reserve(polys);
+        splitter
+    }
+
+    /// Create a new BSP splitter out of a tree built before with `to_tree`,
+    /// so that only the sorting needs to be done, e.g. for static scenes.
+    pub fn from_tree(mut tree: BspNode<Polygon<A, T, U>>) -> Self {
+        let mut splitter = Self::new();
+        let mut stack = vec![(&mut tree, NodeIdx(0))];
+        while let Some((node, node_idx)) = stack.pop() {
+            for poly in core::mem::take(&mut node.values) {
+                let id = FragmentId(splitter.polygons.len() as u32);
+                let source = splitter.new_source();
+                splitter.bounds.push(Box3D::from_points(poly.points.iter()));
+                splitter.polygons.push(poly);
+                splitter.keys.push(FragmentKey { source, split: 0 });
+                splitter.split_counts[source as usize] = 1;
+                splitter.nodes[node_idx.index()].values.push(id);
+            }
+            let BspNode {
+                ref mut front,
+                ref mut back,
+                ..
+            } = *node;
+            for (child, is_front) in [(front, true), (back, false)] {
+                if let Some(child) = child.as_deref_mut() {
+                    let child_idx = add_node(
+                        &mut splitter.nodes,
+                        &mut splitter.free_nodes,
+                        &mut splitter.spare_nodes,
+                        &mut splitter.stats.allocations,
+                    );
+                    let parent = &mut splitter.nodes[node_idx.index()];
+                    if is_front {
+                        parent.front = Some(child_idx);
+                    } else {
+                        parent.back = Some(child_idx);
+                    }
+                    stack.push((child, child_idx));
+                }
+            }
+        }
+        splitter.refit(NodeIdx(0));
+        splitter
+    }
+
+    /// Create a new BSP splitter with the given strategy
+    /// for choosing the splitting planes.
+    pub fn with_heuristic(heuristic: Heuristic) -> Self {
+        BspSplitter {
+            heuristic,
+            ..Self::new()
+        }
+    }
+
+    /// Allocate the index of a new source polygon,
+    /// reusing the one of a removed polygon if possible.
+    fn new_source(&mut self) -> u32 {
+        if let Some(source) = self.free_sources.pop() {
+            self.split_counts[source as usize] = 0;
+            return source;
+        }
+        self.split_counts.push(0);
+        self.split_counts.len() as u32 - 1
+    }
+
+    /// Recompute the bounding boxes of the sub-tree starting with this node
+    /// out of the bounds of the fragments in it.
+    fn refit(&mut self, node_idx: NodeIdx) {
+        let mut order = vec![node_idx];
+        let mut i = 0;
+        while let Some(&idx) = order.get(i) {
+            let node = &self.nodes[idx.index()];
+            order.extend(node.front.iter().chain(&node.back));
+            i += 1;
+        }
+        // the children come after their parents, so they are refitted first
+        for &idx in order.iter().rev() {
+            let node = &self.nodes[idx.index()];
+            let children = node
+                .front
+                .iter()
+                .chain(&node.back)
+                .filter_map(|child| self.nodes[child.index()].bounds.as_ref());
+            let bounds = node
+                .values
+                .iter()
+                .map(|id| &self.bounds[id.index()])
+                .chain(children)
+                .fold(None, |sum, b| Some(union_bounds(sum, b)));
+            self.nodes[idx.index()].bounds = bounds;
+        }
+    }
+}
+
+impl<A, T, U> BspSplitter<A, T, U>
+where
+    A: Clone,
+{
+    /// Iterate over the polygons defining the node planes.
+    #[cfg(feature = "debug-export")]
+    pub(crate) fn node_polygons(&self) -> impl Iterator<Item = &Polygon<A, T, U>> {
+        let polygons = &self.polygons;
+        self.nodes
+            .iter()
+            .filter_map(move |node| node.values.first().map(|id| &polygons[id.index()]))
+    }
+
+    /// Iterate over the nodes of the tree in the given order.
+    ///
+    /// The polygons that are added but not yet inserted into the tree,
+    /// as with `set_deterministic`, are only visible after sorting.
+    pub fn traverse(&self, order: TraversalOrder) -> Traverse<'_, A, T, U> {
+        Traverse {
+            splitter: self,
+            order,
+            stack: vec![Step::Enter(NodeIdx(0), 0)],
+        }
+    }
+
+    /// Visit the nodes of the tree, each before its sub-trees.
+    /// The sub-trees of a node are skipped if `visitor` returns false.
+    pub fn visit<F>(&self, mut visitor: F)
+    where
+        F: FnMut(&NodeView<'_, A, T, U>) -> bool,
+    {
+        let mut stack = vec![(NodeIdx(0), 0)];
+        while let Some((node_idx, depth)) = stack.pop() {
+            let node = &self.nodes[node_idx.index()];
+            if node.values.is_empty() {
+                continue;
+            }
+            let view = NodeView {
+                splitter: self,
+                node: node_idx,
+                depth,
+            };
+            if visitor(&view) {
+                stack.extend(node.front.iter().map(|&n| (n, depth + 1)));
+                stack.extend(node.back.iter().map(|&n| (n, depth + 1)));
+            }
+        }
+    }
+}
+
+/// The order of visiting the nodes of the tree.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TraversalOrder {
+    /// Each node comes before its sub-trees.
+    #[default]
+    PreOrder,
+    /// Each node comes between its back and front sub-trees.
+    InOrder,
+    /// Each node comes after its sub-trees.
+    PostOrder,
+}
+
+/// A node of the BSP tree, as seen by the traversal.
+pub struct NodeView<'a, A: Clone, T, U = UnknownUnit> {
+    splitter: &'a BspSplitter<A, T, U>,
+    node: NodeIdx,
+    depth: usize,
+}
+
+impl<'a, A: Clone, T, U> NodeView<'a, A, T, U> {
+    /// Get the identifier of the node plane.
+    pub fn id(&self) -> PlaneId {
+        PlaneId(self.node.0)
+    }
+
+    /// Get the plane of the node.
+    pub fn plane(&self) -> &'a Plane<T, U> {
+        &self.polygons().next().unwrap().plane
+    }
+
+    /// Get the number of nodes above this one, which is zero for the root.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Get the identifiers of the fragments lying on the node plane.
+    pub fn fragment_ids(&self) -> &'a [FragmentId] {
+        &self.splitter.nodes[self.node.index()].values
+    }
+
+    /// Iterate over the fragments lying on the node plane.
+    pub fn polygons(&self) -> impl Iterator<Item = &'a Polygon<A, T, U>> {
+        let polygons = &self.splitter.polygons;
+        self.fragment_ids()
+            .iter()
+            .map(move |id| &polygons[id.index()])
+    }
+}
+
+/// A step of the iterative traversal.
+enum Step {
+    /// Schedule the node and its sub-trees.
+    Enter(NodeIdx, usize),
+    /// Produce the node.
+    Emit(NodeIdx, usize),
+}
+
+/// An iterator over the nodes of the BSP tree.
+pub struct Traverse<'a, A: Clone, T, U = UnknownUnit> {
+    splitter: &'a BspSplitter<A, T, U>,
+    order: TraversalOrder,
+    stack: Vec<Step>,
+}
+
+impl<'a, A: Clone, T: Float, U> NodeView<'a, A, T, U> {
+    /// Get the bounding box of the fragments in the sub-tree of this node,
+    /// including the ones on its plane, e.g. to skip the sub-trees
+    /// outside of a region of interest in `BspSplitter::visit`.
+    pub fn bounds(&self) -> Box3D<T, U> {
+        let bounds = self.splitter.nodes[self.node.index()]
+            .bounds
+            .unwrap_or_else(Box3D::zero);
+        match self.splitter.origin {
+            Some(origin) => bounds.translate(origin),
+            None => bounds,
+        }
+    }
+}
+
+impl<'a, A: Clone, T, U> Iterator for Traverse<'a, A, T, U> {
+    type Item = NodeView<'a, A, T, U>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(step) = self.stack.pop() {
+            let (node_idx, depth) = match step {
+                Step::Emit(node, depth) => {
+                    return Some(NodeView {
+                        splitter: self.splitter,
+                        node,
+                        depth,
+                    });
+                }
+                Step::Enter(node_idx, depth) => (node_idx, depth),
+            };
+            let node = &self.splitter.nodes[node_idx.index()];
+            if node.values.is_empty() {
+                continue;
+            }
+            // the stack is processed in reverse, and the back
+            // sub-tree is visited before the front one
+            let front = node.front.map(|n| Step::Enter(n, depth + 1));
+            let back = node.back.map(|n| Step::Enter(n, depth + 1));
+            let emit = Some(Step::Emit(node_idx, depth));
+            let steps = match self.order {
+                TraversalOrder::PreOrder => [front, back, emit],
+                TraversalOrder::InOrder => [front, emit, back],
+                TraversalOrder::PostOrder => [emit, front, back],
+            };
+            self.stack.extend(IntoIterator::into_iter(steps).flatten());
+        }
+        None
+    }
+}
+
+impl<A, T, U> Default for BspSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Shows the tree indented by the depth, with a line for each node
+/// listing its plane and the identifiers of the fragments lying on it,
+/// and the front sub-tree before the back one. The polygons that are
+/// added but not yet inserted into the tree, which happens when sorting,
+/// are not shown.
+impl<A, T, U> fmt::Display for BspSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut stack = vec![(NodeIdx(0), 0, "root")];
+        while let Some((node_idx, depth, side)) = stack.pop() {
+            let indent = 2 * depth;
+            let node = &self.nodes[node_idx.index()];
+            let first = match node.values.first() {
+                Some(first) => first,
+                None => {
+                    writeln!(f, "{:indent$}{}: empty", "", side)?;
+                    continue;
+                }
+            };
+            let plane = &self.polygons[first.index()].plane;
+            // the geometry is stored relative to the origin
+            let offset = match self.origin {
+                Some(origin) => plane.offset - plane.normal.dot(origin),
+                None => plane.offset,
+            };
+            let plane = Plane {
+                normal: plane.normal,
+                offset,
+            };
+            write!(f, "{:indent$}{}: {}, fragments", "", side, plane)?;
+            for id in &node.values {
+                write!(f, " {}", id.0)?;
+            }
+            writeln!(f)?;
+            if let Some(back) = node.back {
+                stack.push((back, depth + 1, "back"));
+            }
+            if let Some(front) = node.front {
+                stack.push((front, depth + 1, "front"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<A, T, U> Clone for BspSplitter<A, T, U>
+where
+    A: Clone,
+    T: Copy,
+{
+    fn clone(&self) -> Self {
+        BspSplitter {
+            result: self.result.clone(),
+            result_ids: self.result_ids.clone(),
+            nodes: self.nodes.clone(),
+            polygons: self.polygons.clone(),
+            bounds: self.bounds.clone(),
+            keys: self.keys.clone(),
+            split_counts: self.split_counts.clone(),
+            epsilons: self.epsilons,
+            frustum: self.frustum.clone(),
+            sibling_order: self.sibling_order.clone(),
+            // the callback and the profiler may have state, which can't be shared
+            split_callback: None,
+            profiler: None,
+            insert_stack: Vec::new(),
+            heuristic: self.heuristic,
+            deterministic: self.deterministic,
+            pending: self.pending.clone(),
+            provenance: self.provenance.clone(),
+            free_nodes: self.free_nodes.clone(),
+            spare_nodes: Vec::new(),
+            free_fragments: self.free_fragments.clone(),
+            free_sources: self.free_sources.clone(),
+            dropped_small: self.dropped_small,
+            budget: self.budget,
+            budget_overflow: self.budget_overflow,
+            stats: self.stats,
+            uncertain_cuts: self.uncertain_cuts.clone(),
+            split_errors: self.split_errors.clone(),
+            recenter: self.recenter,
+            origin: self.origin,
+            #[cfg(feature = "robust")]
+            exact: self.exact,
+        }
+    }
+}
+
+/// The state of the tree saved by `BspSplitter::snapshot`.
+///
+/// Unlike a clone of the splitter, it doesn't include the configuration
+/// or the results of the last sort.
+pub struct BspSnapshot<A, T = f64, U = UnknownUnit> {
+    nodes: Vec<Node<T, U>>,
+    polygons: Vec<Polygon<A, T, U>>,
+    bounds: Vec<Box3D<T, U>>,
+    keys: Vec<FragmentKey>,
+    split_counts: Vec<u32>,
+    pending: Vec<(Polygon<A, T, U>, u32)>,
+    provenance: Option<Vec<Cuts>>,
+    free_nodes: Vec<NodeIdx>,
+    free_fragments: Vec<FragmentId>,
+    free_sources: Vec<u32>,
+    dropped_small: usize,
+    budget_overflow: usize,
+    stats: SplitStats,
+    uncertain_cuts: Option<Vec<(A, A)>>,
+    split_errors: Vec<(A, SplitError)>,
+    origin: Option<Vector3D<T, U>>,
+}
+
+impl<A: fmt::Debug, T: fmt::Debug, U> fmt::Debug for BspSnapshot<A, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BspSnapshot")
+            .field("nodes", &self.nodes)
+            .field("polygons", &self.polygons)
+            .field("bounds", &self.bounds)
+            .field("keys", &self.keys)
+            .field("split_counts", &self.split_counts)
+            .field("pending", &self.pending)
+            .field("provenance", &self.provenance)
+            .field("free_nodes", &self.free_nodes)
+            .field("free_fragments", &self.free_fragments)
+            .field("free_sources", &self.free_sources)
+            .field("dropped_small", &self.dropped_small)
+            .field("budget_overflow", &self.budget_overflow)
+            .field("stats", &self.stats)
+            .field("uncertain_cuts", &self.uncertain_cuts)
+            .field("split_errors", &self.split_errors)
+            .field("origin", &self.origin)
+            .finish()
+    }
+}
+
+impl<A, T, U> BspSplitter<A, T, U>
+where
+    A: Clone + fmt::Debug + Default,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    /// Save the state of the tree, so that more polygons can be added
+    /// and then discarded by `restore`, without rebuilding the tree.
+    pub fn snapshot(&self) -> BspSnapshot<A, T, U> {
+        BspSnapshot {
+            nodes: self.nodes.clone(),
+            polygons: self.polygons.clone(),
+            bounds: self.bounds.clone(),
+            keys: self.keys.clone(),
+            split_counts: self.split_counts.clone(),
+            pending: self.pending.clone(),
+            provenance: self.provenance.clone(),
+            free_nodes: self.free_nodes.clone(),
+            free_fragments: self.free_fragments.clone(),
+            free_sources: self.free_sources.clone(),
+            dropped_small: self.dropped_small,
+            budget_overflow: self.budget_overflow,
+            stats: self.stats,
+            uncertain_cuts: self.uncertain_cuts.clone(),
+            split_errors: self.split_errors.clone(),
+            origin: self.origin,
+        }
+    }
+
+    /// Bring the tree back to the state saved by `snapshot`.
+    ///
+    /// The storage of the splitter is reused where possible. The results
+    /// of the last sort are cleared, and the configuration is kept.
+    pub fn restore(&mut self, snapshot: &BspSnapshot<A, T, U>) {
+        self.nodes.clone_from(&snapshot.nodes);
+        self.polygons.clone_from(&snapshot.polygons);
+        self.bounds.clone_from(&snapshot.bounds);
+        self.keys.clone_from(&snapshot.keys);
+        self.split_counts.clone_from(&snapshot.split_counts);
+        self.pending.clone_from(&snapshot.pending);
+        self.provenance.clone_from(&snapshot.provenance);
+        self.free_nodes.clone_from(&snapshot.free_nodes);
+        self.free_fragments.clone_from(&snapshot.free_fragments);
+        self.free_sources.clone_from(&snapshot.free_sources);
+        self.dropped_small = snapshot.dropped_small;
+        self.budget_overflow = snapshot.budget_overflow;
+        self.stats = snapshot.stats;
+        self.uncertain_cuts.clone_from(&snapshot.uncertain_cuts);
+        self.split_errors.clone_from(&snapshot.split_errors);
+        self.origin = snapshot.origin;
+        self.result.clear();
+        self.result_ids.clear();
+    }
+
+    /// Put the splitter back in it initial state.
+    ///
+    /// Call this at the beginning of every frame when reusing the splitter.
+    /// The node and polygon storage is kept around, so a splitter that is
+    /// reused every frame stops allocating once it reaches a steady state.
+    pub fn reset(&mut self) {
+        self.polygons.clear();
+        self.bounds.clear();
+        self.keys.clear();
+        self.split_counts.clear();
+        // keep the nodes, along with their storage, for the next frame
+        for mut node in self.nodes.drain(..) {
+            node.values.clear();
+            node.front = None;
+            node.back = None;
+            node.bounds = None;
+            self.spare_nodes.push(node);
+        }
+        self.nodes.push(self.spare_nodes.pop().unwrap_or_default());
+        self.result.clear();
+        self.result_ids.clear();
+        self.pending.clear();
+        if let Some(ref mut provenance) = self.provenance {
+            provenance.clear();
+        }
+        self.free_nodes.clear();
+        self.free_fragments.clear();
+        self.free_sources.clear();
+        self.dropped_small = 0;
+        self.budget_overflow = 0;
+        self.stats = SplitStats::default();
+        if let Some(ref mut uncertain_cuts) = self.uncertain_cuts {
+            uncertain_cuts.clear();
+        }
+        self.split_errors.clear();
+        self.origin = None;
+    }
+
+    /// Build the tree out of the added polygons, and return a copy of it,
+    /// which can be cached and turned back into a splitter with `from_tree`.
+    pub fn to_tree(&mut self) -> BspNode<Polygon<A, T, U>> {
+        self.build();
+        let mut tree = BspNode::new();
+        let mut stack = vec![(&mut tree, NodeIdx(0))];
+        while let Some((out, node_idx)) = stack.pop() {
+            let node = &self.nodes[node_idx.index()];
+            out.values.extend(
+                node.values
+                    .iter()
+                    .map(|id| self.to_world(&self.polygons[id.index()])),
+            );
+            let BspNode {
+                ref mut front,
+                ref mut back,
+                ..
+            } = *out;
+            if let Some(child) = node.front {
+                stack.push((front.insert(Box::new(BspNode::new())), child));
+            }
+            if let Some(child) = node.back {
+                stack.push((back.insert(Box::new(BspNode::new())), child));
+            }
+        }
+        tree
+    }
+
+    /// Set the frustum to clip the polygons by before they are added,
+    /// so that the geometry outside of it doesn't get split.
+    pub fn set_clip_frustum(&mut self, frustum: Option<Frustum<T, U>>) {
+        self.frustum = frustum;
+    }
+
+    /// Set the order of polygons lying on the same plane, based on their anchors.
+    /// The polygons comparing as `Less` are drawn first.
+    ///
+    /// By default, such polygons are drawn in the order they were added.
+    pub fn set_sibling_order<F>(&mut self, cmp: F)
+    where
+        F: Fn(&A, &A) -> Ordering + Send + Sync + 'static,
+    {
+        self.sibling_order = Some(Arc::new(cmp));
+    }
+
+    /// Set a function to be called every time a polygon gets cut in two
+    /// while being inserted, with its anchor and the plane that cut it.
+    ///
+    /// The callback isn't carried over to the clones of the splitter.
+    pub fn on_split<F>(&mut self, callback: F)
+    where
+        F: FnMut(&A, &Plane<T, U>) + Send + Sync + 'static,
+    {
+        self.split_callback = Some(Box::new(callback));
+    }
+
+    /// Set the profiler to report the phases of the work to.
+    ///
+    /// The profiler isn't carried over to the clones of the splitter.
+    pub fn set_profiler(&mut self, profiler: Option<Box<dyn Profiler + Send + Sync>>) {
+        self.profiler = profiler;
+    }
+
+    /// Make the produced fragments independent of the order in which
+    /// the polygons are added.
+    ///
+    /// The added polygons are buffered and sorted by their plane equations
+    /// before building the tree, which happens when sorting.
+    pub fn set_deterministic(&mut self, deterministic: bool) {
+        self.deterministic = deterministic;
+    }
+
+    /// Record the planes cutting each of the fragments, which can then
+    /// be queried with `fragment_info`. The fragments stored before
+    /// the tracking is turned on are reported with no cuts.
+    pub fn set_track_provenance(&mut self, track: bool) {
+        if !track {
+            self.provenance = None;
+        } else if self.provenance.is_none() {
+            self.provenance = Some(vec![Cuts::new(); self.polygons.len()]);
+        }
+    }
+
+    /// Limit the number of fragments stored in the splitter. Once reached,
+    /// the polygons are no longer split, but put on the side of the node
+    /// planes where their centroid is instead, so the sorting degrades to an
+    /// approximate order. The number of such polygons is reported by
+    /// `budget_overflow`.
+    pub fn set_budget(&mut self, max_fragments: Option<usize>) {
+        self.budget = max_fragments;
+    }
+
+    /// Get the number of the nodes kept by `reset` for reuse.
+    pub fn pool_size(&self) -> usize {
+        self.spare_nodes.len()
+    }
+
+    /// Get the number of times a polygon wasn't split because the fragment
+    /// budget was exceeded.
+    pub fn budget_overflow(&self) -> usize {
+        self.budget_overflow
+    }
+
+    /// Check the sides of the polygons cut by the node planes with interval
+    /// arithmetic, recording the pairs of polygons for which the side can't
+    /// be determined reliably. These can be queried with `uncertain_cuts`.
+    pub fn set_check_uncertainty(&mut self, check: bool) {
+        self.uncertain_cuts = if check { Some(Vec::new()) } else { None };
+    }
+
+    /// Move the geometry close to the origin before splitting it, which
+    /// preserves the precision for scenes far from the origin, e.g. scrolled
+    /// by a large amount. The translation is chosen based on the first added
+    /// polygon, and can be queried with `origin`.
+    ///
+    /// The sorted polygons are moved back, but the ones accessed by their
+    /// identifiers are left relative to the `origin`.
+    pub fn set_recentering(&mut self, recenter: bool) {
+        self.recenter = recenter;
+    }
+
+    /// Get the point which the geometry of the splitter is relative to.
+    pub fn origin(&self) -> Point3D<T, U> {
+        self.origin
+            .map_or(Point3D::origin(), |origin| origin.to_point())
+    }
+
+    /// Add a polygon to the plane splitter.
+    ///
+    /// This is where most of the expensive computation happens.
+    pub fn add(&mut self, poly: Polygon<A, T, U>) {
+        self.add_ref(&poly, None);
+    }
+
+    /// Add a polygon to the plane splitter, clipped by the region first.
+    /// Nothing is added if the polygon is outside of the region.
+    pub fn add_clipped(&mut self, poly: Polygon<A, T, U>, region: &ClipRegion<T, U>) {
+        self.add_ref(&poly, Some(region));
+    }
+
+    /// Add a number of polygons to the plane splitter,
+    /// reserving the storage for them up front.
+    pub fn add_all<I>(&mut self, polygons: I)
+    where
+        I: IntoIterator<Item = Polygon<A, T, U>>,
+    {
+        let polygons = polygons.into_iter();
+        let (count, _) = polygons.size_hint();
+        if self.deterministic || self.heuristic == Heuristic::MinSplits {
+            self.pending.reserve(count);
+        } else {
+            let capacity = self.polygons.capacity();
+            self.polygons.reserve(count);
+            if self.polygons.capacity() != capacity {
+                self.stats.allocations += 1;
+            }
+        }
+        for poly in polygons {
+            self.add_ref(&poly, None);
+        }
+    }
+
+    fn add_ref(&mut self, poly: &Polygon<A, T, U>, region: Option<&ClipRegion<T, U>>) {
+        self.stats.input_polys += 1;
+        let source = self.new_source();
+        #[cfg(feature = "tracing")]
+        let _span = tracing::debug_span!("add", source, points = poly.points.len()).entered();
+        if let Some(poly) = self.prepare(poly, region) {
+            self.add_prepared(poly, source);
+        }
+    }
+
+    /// Clip the polygon and move it relative to the origin.
+    fn prepare(
+        &mut self,
+        poly: &Polygon<A, T, U>,
+        region: Option<&ClipRegion<T, U>>,
+    ) -> Option<Polygon<A, T, U>> {
+        let poly = match region {
+            Some(region) => poly.clip_to_region(region)?,
+            None => poly.clone(),
+        };
+        let poly = match self.frustum {
+            Some(ref frustum) => poly.clip_to_frustum(frustum)?,
+            None => poly,
+        };
+        if !self.recenter {
+            return Some(poly);
+        }
+        let origin = *self.origin.get_or_insert_with(|| {
+            let count = T::from(poly.points.len()).unwrap();
+            let sum = poly
+                .points
+                .iter()
+                .fold(Vector3D::zero(), |sum, p| sum + p.to_vector());
+            let center = sum / count;
+            // whole numbers keep the translation exact
+            Vector3D::new(center.x.round(), center.y.round(), center.z.round())
+        });
+        Some(poly.translate(-origin))
+    }
+
+    /// Move the polygon from the space relative to the origin back.
+    fn to_world(&self, poly: &Polygon<A, T, U>) -> Polygon<A, T, U> {
+        match self.origin {
+            Some(origin) => poly.translate(origin),
+            None => poly.clone(),
+        }
+    }
+
+    fn add_prepared(&mut self, poly: Polygon<A, T, U>, source: u32) {
+        if self.deterministic || self.heuristic == Heuristic::MinSplits {
+            self.pending.push((poly, source));
+        } else {
+            self.insert(NodeIdx(0), poly, Lineage::new(source));
+        }
+    }
+
+    /// Remove all the fragments whose anchor matches the `predicate`.
+    ///
+    /// Only the sub-trees whose node plane was defined solely by the removed
+    /// fragments get rebuilt, out of the remaining fragments in them,
+    /// so the rest of the tree can be reused for the next frame.
+    /// The fragments in the rebuilt sub-trees keep their identifiers,
+    /// unless some of them get split again.
+    ///
+    /// The storage of the removed fragments is reused by the ones added
+    /// later, so that the splitter doesn't grow when reused across frames.
+    pub fn remove<F>(&mut self, predicate: F)
+    where
+        F: Fn(&A) -> bool,
+    {
+        let mut sources = Vec::new();
+        self.pending.retain(|(poly, source)| {
+            let is_removed = predicate(&poly.anchor);
+            if is_removed {
+                sources.push(*source);
+            }
+            !is_removed
+        });
+
+        let polygons = &self.polygons;
+        let free_fragments = &mut self.free_fragments;
+        let free_count = free_fragments.len();
+        for node in self.nodes.iter_mut() {
+            node.values.retain(|id| {
+                let is_removed = predicate(&polygons[id.index()].anchor);
+                if is_removed {
+                    free_fragments.push(*id);
+                }
+                !is_removed
+            });
+        }
+        // all the fragments of a source share its anchor, so they are gone
+        sources.extend(
+            self.free_fragments[free_count..]
+                .iter()
+                .map(|id| self.keys[id.index()].source),
+        );
+        sources.sort_unstable();
+        sources.dedup();
+        self.free_sources.extend(sources);
+        if self.free_fragments.len() == free_count {
+            return;
+        }
+
+        let mut stack = vec![NodeIdx(0)];
+        while let Some(node_idx) = stack.pop() {
+            let node = &self.nodes[node_idx.index()];
+            if node.values.is_empty() {
+                if node.front.is_some() || node.back.is_some() {
+                    self.rebuild(node_idx);
+                }
+            } else {
+                stack.extend(node.front.iter().chain(&node.back));
+            }
+        }
+        self.refit(NodeIdx(0));
+    }
+
+    /// Replace the polygon with the given anchor by a new one.
+    ///
+    /// When the polygon isn't split, doesn't define the plane of its node,
+    /// and the new one lies in the same cell of the tree, i.e. it stays on
+    /// the same plane and doesn't cross any of the node planes above it,
+    /// the fragment is updated in place. Otherwise, this falls back to
+    /// `remove` followed by `add`.
+    ///
+    /// Returns `true` if the update happened in place.
+    pub fn update(&mut self, anchor: A, poly: Polygon<A, T, U>) -> bool
+    where
+        A: PartialEq,
+    {
+        let poly = self.prepare(&poly, None);
+        if let Some(ref poly) = poly {
+            if let Some(id) = self.locate_in_place(&anchor, poly) {
+                log::debug!("\tUpdating {:?} in place", id);
+                self.bounds[id.index()] = poly.bounding_box();
+                self.polygons[id.index()] = poly.clone();
+                self.refit(NodeIdx(0));
+                return true;
+            }
+        }
+
+        self.remove(|a| *a == anchor);
+        if let Some(poly) = poly {
+            let source = self.new_source();
+            self.add_prepared(poly, source);
+        }
+        false
+    }
+
+    /// Find the only fragment of the anchor, if the polygon can replace it
+    /// without changing the structure of the tree.
+    fn locate_in_place(&self, anchor: &A, poly: &Polygon<A, T, U>) -> Option<FragmentId>
+    where
+        A: PartialEq,
+    {
+        if self.pending.iter().any(|(p, _)| p.anchor == *anchor) {
+            return None;
+        }
+        let mut found = None;
+        for (node_idx, node) in self.nodes.iter().enumerate() {
+            for &id in &node.values {
+                if self.polygons[id.index()].anchor == *anchor {
+                    if found.is_some() {
+                        // the polygon is split
+                        return None;
+                    }
+                    found = Some((NodeIdx(node_idx as u32), id));
+                }
+            }
+        }
+        let (target, id) = found?;
+        // moving the polygon would move the plane the sub-tree is split by
+        if self.nodes[target.index()].values[0] == id {
+            return None;
+        }
+
+        let mut node_idx = NodeIdx(0);
+        loop {
+            let node = &self.nodes[node_idx.index()];
+            let mut front: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+            let mut back: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+            let next = match self.cut(node_idx, Cow::Borrowed(poly), &mut front, &mut back) {
+                Cut::Sibling(_) if node_idx == target => return Some(id),
+                Cut::Sibling(_) => return None,
+                Cut::Done | Cut::Failed(_) if node_idx == target => return None,
+                Cut::Done | Cut::Failed(_) => match (front.len(), back.len()) {
+                    (1, 0) => node.front,
+                    (0, 1) => node.back,
+                    _ => return None,
+                },
+            };
+            match next {
+                Some(next) if !self.nodes[next.index()].values.is_empty() => node_idx = next,
+                _ => return None,
+            }
+        }
+    }
+
+    /// Re-insert the fragments of the sub-tree starting with this node,
+    /// which lost its plane.
+    fn rebuild(&mut self, node_idx: NodeIdx) {
+        let mut freed = Vec::new();
+        let mut fragments = Vec::new();
+        let mut stack = vec![node_idx];
+        while let Some(idx) = stack.pop() {
+            let node = core::mem::take(&mut self.nodes[idx.index()]);
+            stack.extend(node.front.iter().chain(&node.back));
+            fragments.extend(node.values);
+            if idx != node_idx {
+                freed.push(idx);
+            }
+        }
+        log::debug!(
+            "\tRebuilding {:?} with {} fragments",
+            node_idx,
+            fragments.len()
+        );
+
+        let mut values: Vec<_> = fragments
+            .into_iter()
+            .map(|id| {
+                let mut lineage = Lineage::new(self.keys[id.index()].source);
+                if let Some(ref provenance) = self.provenance {
+                    lineage.cuts = provenance[id.index()].clone();
+                }
+                // the planes of the sub-tree are gone
+                lineage
+                    .cuts
+                    .retain(|plane| plane.0 != node_idx.0 && !freed.contains(&NodeIdx(plane.0)));
+                (id, self.polygons[id.index()].clone(), lineage)
+            })
+            .collect();
+        if self.deterministic {
+            values.sort_by(|a, b| compare_polygons(&a.1, &b.1));
+        }
+        // the fragments are stored again in the same slots, unless split
+        self.free_fragments
+            .extend(values.iter().rev().map(|&(id, _, _)| id));
+        self.free_nodes.extend(freed);
+        for (_, poly, lineage) in values {
+            self.insert(node_idx, poly, lineage);
+        }
+    }
+
+    /// Sort the added and split polygons against the view vector.
+    ///
+    /// Call this towards the end of the frame after having added all polygons.
+    pub fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        self.sort_indices(view);
+        self.collect_result()
+    }
+
+    /// Sort the added and split polygons against the view vector,
+    /// applying additional processing specified by the `options`.
+    pub fn sort_with_options(
+        &mut self,
+        view: Vector3D<T, U>,
+        options: &SortOptions<A, T>,
+    ) -> &[Polygon<A, T, U>] {
+        let mut result = core::mem::take(&mut self.result_ids);
+        result.clear();
+        let sidedness = if options.cull_backfaces {
+            Some(options.sidedness)
+        } else {
+            None
+        };
+        self.order_view(view, sidedness, &mut result);
+        self.result_ids = result;
+        self.drop_small(view, options);
+        if options.cull_occluded {
+            self.cull_occluded(view, options.opaque);
+        }
+        if options.order == SortOrder::FrontToBack {
+            // reversing the traversal is the same as visiting
+            // the far child after the node values
+            self.result_ids.reverse();
+        }
+        self.collect_result();
+        if options.vertex_grid > T::zero() {
+            snap_to_grid(&mut self.result, options.vertex_grid);
+        }
+        if options.repair_t_junctions {
+            repair_t_junctions(&mut self.result, self.epsilons.coplanarity);
+        }
+        &self.result
+    }
+
+    /// Sort the added and split polygons against the view vector, then
+    /// group the fragments of the same anchor together where it doesn't
+    /// change the result, i.e. where the fragments moved past each other
+    /// don't overlap on screen.
+    ///
+    /// Returns the ranges of each group within the fragments,
+    /// which are then available through `sorted`.
+    pub fn sort_batched(&mut self, view: Vector3D<T, U>) -> Vec<(A, Range<usize>)>
+    where
+        A: PartialEq,
+    {
+        self.sort_indices(view);
+        let ids = core::mem::take(&mut self.result_ids);
+        let polygons = &self.polygons;
+        let epsilon = self.epsilons.coplanarity;
+        let mut taken = vec![false; ids.len()];
+        let mut batched = Vec::with_capacity(ids.len());
+        let mut ranges = Vec::new();
+
+        for i in 0..ids.len() {
+            if taken[i] {
+                continue;
+            }
+            let anchor = polygons[ids[i].index()].anchor.clone();
+            let start = batched.len();
+            batched.push(ids[i]);
+            for j in i + 1..ids.len() {
+                let poly = &polygons[ids[j].index()];
+                if taken[j] || poly.anchor != anchor {
+                    continue;
+                }
+                // the fragment can only be moved before the ones it doesn't overlap
+                let can_move = (i + 1..j).all(|k| {
+                    taken[k] || !polygons[ids[k].index()].overlaps_along(poly, &view, epsilon)
+                });
+                if can_move {
+                    taken[j] = true;
+                    batched.push(ids[j]);
+                }
+            }
+            ranges.push((anchor, start..batched.len()));
+        }
+
+        self.result_ids = batched;
+        self.collect_result();
+        ranges
+    }
+
+    /// Sort the added and split polygons against the view vector, then
+    /// weld the fragments of the same anchor that share a full cut edge
+    /// back into larger convex polygons, where it doesn't change the result,
+    /// i.e. where the fragments moved past each other don't overlap on screen.
+    ///
+    /// The merged polygons are not stored in the splitter, so the identifiers
+    /// of the last sort still refer to the original fragments.
+    pub fn sort_merged(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>]
+    where
+        A: PartialEq,
+    {
+        self.sort_indices(view);
+        let epsilon = self.epsilons.coplanarity;
+        let mut result: Vec<Polygon<A, T, U>> = core::mem::take(&mut self.result);
+        result.clear();
+
+        for id in self.result_ids.iter() {
+            let mut poly = self.polygons[id.index()].clone();
+            // look back for a fragment to merge with, as long as
+            // the polygon can be moved before the ones in between
+            'merge: loop {
+                for k in (0..result.len()).rev() {
+                    let other = &result[k];
+                    if other.anchor == poly.anchor {
+                        if let Some(merged) = other.merge(&poly, epsilon) {
+                            if !result[k + 1..]
+                                .iter()
+                                .any(|p| p.overlaps_along(&merged, &view, epsilon))
+                            {
+                                log::debug!("\tMerging fragment {:?}", id);
+                                result.remove(k);
+                                poly = merged;
+                                continue 'merge;
+                            }
+                        }
+                    }
+                    if other.overlaps_along(&poly, &view, epsilon) {
+                        break;
+                    }
+                }
+                break;
+            }
+            result.push(poly);
+        }
+
+        for poly in result.iter_mut() {
+            *poly = self.to_world(poly);
+        }
+        self.result = result;
+        &self.result
+    }
+
+    /// Get the number of fragments dropped by the last `sort_with_options`
+    /// for being smaller than the area thresholds.
+    pub fn dropped_small_count(&self) -> usize {
+        self.dropped_small
+    }
+
+    /// Remove the sorted fragments that are smaller than the thresholds.
+    fn drop_small(&mut self, view: Vector3D<T, U>, options: &SortOptions<A, T>) {
+        let polygons = &self.polygons;
+        let count = self.result_ids.len();
+        let dir = view.normalize();
+        let two = T::one() + T::one();
+        let scale = options.viewport_scale * options.viewport_scale;
+        self.result_ids.retain(|id| {
+            let poly = &polygons[id.index()];
+            let area = poly.area_vector();
+            area.length() / two >= options.min_world_area
+                && area.dot(dir).abs() / two * scale >= options.min_projected_area
+        });
+        self.dropped_small = count - self.result_ids.len();
+        if self.dropped_small != 0 {
+            log::debug!("\tDropped {} small fragments", self.dropped_small);
+        }
+    }
+
+    /// Get the polygons produced by the last sort.
+    pub fn sorted(&self) -> &[Polygon<A, T, U>] {
+        &self.result
+    }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
-pub struct NodeIdx(usize);
+    /// Remove the sorted fragments that are completely hidden by
+    /// any of the following opaque fragments.
+    ///
+    /// Note: a fragment covered by a union of multiple occluders is kept.
+    fn cull_occluded(&mut self, view: Vector3D<T, U>, opaque: &dyn Fn(&A) -> bool) {
+        let polygons = &self.polygons;
+        let epsilon = self.epsilons.degeneracy;
+        let ids = &self.result_ids;
+        let visible = ids
+            .iter()
+            .enumerate()
+            .filter(|&(i, id)| {
+                let poly = &polygons[id.index()];
+                let is_hidden = ids[i + 1..].iter().any(|other_id| {
+                    let other = &polygons[other_id.index()];
+                    opaque(&other.anchor) && other.covers_along(poly, &view, epsilon)
+                });
+                if is_hidden {
+                    log::debug!("\tCulling occluded fragment {:?}", id);
+                }
+                !is_hidden
+            })
+            .map(|(_, &id)| id)
+            .collect();
+        self.result_ids = visible;
+    }
 
-/// Binary Space Partitioning splitter, uses a BSP tree.
-pub struct BspSplitter<A: Copy> {
-    result: Vec<Polygon<A>>,
-    nodes: Vec<BspNode>,
-    polygons: Vec<Polygon<A>>,
-}
+    /// Sort the added and split polygons back to front, as seen from
+    /// the specified eye position. This is needed for perspective projections,
+    /// where the view direction is different for each polygon.
+    pub fn sort_from_point(&mut self, eye: Point3D<T, U>) -> &[Polygon<A, T, U>] {
+        self.build();
+        let root = NodeIdx(0);
+        let eye = eye - self.origin().to_vector();
+        let mut result = core::mem::take(&mut self.result_ids);
+        result.clear();
+        // the eye is behind the plane, so the front side is farther away
+        self.order_by(
+            root,
+            &|poly: &Polygon<A, T, U>| poly.plane.signed_distance_to(&eye) <= T::zero(),
+            None,
+            &mut result,
+        );
+        self.result_ids = result;
 
-impl<A: Copy> BspSplitter<A> {
-    /// Create a new BSP splitter.
-    pub fn new() -> Self {
-        BspSplitter {
-            result: Vec::new(),
-            nodes: vec![BspNode::new()],
-            polygons: Vec::new(),
-        }
+        self.collect_result()
     }
-}
 
-impl<A> BspSplitter<A>
-where
-    A: Copy + fmt::Debug + Default,
-{
-    /// Put the splitter back in it initial state.
-    ///
-    /// Call this at the beginning of every frame when reusing the splitter.
-    pub fn reset(&mut self) {
-        self.polygons.clear();
-        self.nodes.clear();
-        self.nodes.push(BspNode::new());
+    fn collect_result(&mut self) -> &[Polygon<A, T, U>] {
+        let mut result = core::mem::take(&mut self.result);
+        result.clear();
+        result.extend(
+            self.result_ids
+                .iter()
+                .map(|id| self.to_world(&self.polygons[id.index()])),
+        );
+        self.result = result;
+
+        &self.result
     }
 
-    /// Add a polygon to the plane splitter.
+    /// Sort the added and split polygons against the view vector,
+    /// returning the identifiers of the fragments instead of copying them.
     ///
-    /// This is where most of the expensive computation happens.
-    pub fn add(&mut self, poly: Polygon<A>) {
-        let root = NodeIdx(0);
-        self.insert(root, &poly);
+    /// Use `fragment` to access the polygon by its identifier.
+    pub fn sort_indices(&mut self, view: Vector3D<T, U>) -> &[FragmentId] {
+        let mut result = core::mem::take(&mut self.result_ids);
+        result.clear();
+        self.sort_indices_into(view, &mut result);
+        self.result_ids = result;
+
+        &self.result_ids
     }
 
-    /// Sort the added and split polygons against the view vector.
+    /// Sort the added and split polygons against the view vector,
+    /// appending the identifiers of the fragments to `out`.
     ///
-    /// Call this towards the end of the frame after having added all polygons.
-    pub fn sort(&mut self, view: Vector3D<f64>) -> &[Polygon<A>] {
+    /// Unlike `sort_indices`, this doesn't affect the results of other sorts,
+    /// so the same tree can be sorted for multiple views.
+    pub fn sort_indices_into(&mut self, view: Vector3D<T, U>, out: &mut Vec<FragmentId>) {
+        self.order_view(view, None, out);
+    }
+
+    /// Build the tree and append the draw order along the view to `out`,
+    /// skipping the back-facing fragments of single-sided polygons
+    /// if the `sidedness` is given.
+    fn order_view(
+        &mut self,
+        view: Vector3D<T, U>,
+        sidedness: Option<&dyn Fn(&A) -> Sidedness>,
+        out: &mut Vec<FragmentId>,
+    ) {
+        #[cfg(feature = "tracing")]
+        let span = tracing::debug_span!(
+            "sort",
+            fragments = tracing::field::Empty,
+            nodes = tracing::field::Empty,
+            sorted = tracing::field::Empty,
+        )
+        .entered();
+        self.build();
+        #[cfg(feature = "tracing")]
+        {
+            span.record("fragments", self.fragment_count());
+            span.record("nodes", self.node_count());
+        }
         //debug!("\t\ttree before sorting {:?}", self.tree);
         let poly = Polygon {
-            points: [Point3D::origin(); 4],
+            points: SmallVec::new(),
             plane: Plane {
                 normal: -view, //Note: BSP `order()` is back to front
-                offset: 0.0,
+                offset: T::zero(),
             },
             anchor: A::default(),
+            edges: SmallVec::new(),
         };
 
-        let root = NodeIdx(0);
-        let mut result = std::mem::take(&mut self.result);
-        result.clear();
-        self.order(root, &poly, &mut result);
-        self.result = result;
+        #[cfg(feature = "tracing")]
+        let start = out.len();
+        self.profile(ProfileEvent::SortStart);
+        let front_first = |other: &Polygon<A, T, U>| poly.is_aligned(other);
+        self.order_by(NodeIdx(0), &front_first, sidedness, out);
+        self.profile(ProfileEvent::SortEnd);
+        #[cfg(feature = "tracing")]
+        span.record("sorted", out.len() - start);
+    }
 
-        &self.result
+    /// Sort the added and split polygons against the view vector,
+    /// appending the fragments to `out`.
+    ///
+    /// Unlike `sort`, this doesn't affect the results of other sorts,
+    /// so the same tree can be sorted for multiple views.
+    pub fn sort_into(&mut self, view: Vector3D<T, U>, out: &mut Vec<Polygon<A, T, U>>) {
+        let mut ids = Vec::new();
+        self.sort_indices_into(view, &mut ids);
+        out.extend(
+            ids.iter()
+                .map(|id| self.to_world(&self.polygons[id.index()])),
+        );
+    }
+
+    /// Tell how the polygon would be split if it was added, without adding it,
+    /// so that the caller can decide if it's worth it.
+    ///
+    /// The buffered polygons get inserted into the tree first, same as for
+    /// `raycast`. The fragment budget isn't taken into account.
+    pub fn probe(&mut self, poly: &Polygon<A, T, U>) -> ProbeResult {
+        self.build();
+        let poly = match self.frustum {
+            Some(ref frustum) => match poly.clip_to_frustum(frustum) {
+                Some(poly) => poly,
+                None => return ProbeResult::NoCut,
+            },
+            None => poly.clone(),
+        };
+        let poly = match self.origin {
+            Some(origin) => poly.translate(-origin),
+            None => poly,
+        };
+
+        let mut cuts = 0;
+        let mut coplanar = false;
+        let mut stack = vec![(NodeIdx(0), poly)];
+        while let Some((node_idx, poly)) = stack.pop() {
+            let node = &self.nodes[node_idx.index()];
+            if node.values.is_empty() {
+                continue;
+            }
+            let mut front: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+            let mut back: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+            match self.cut(node_idx, Cow::Owned(poly), &mut front, &mut back) {
+                Cut::Sibling(_) => coplanar = true,
+                Cut::Done | Cut::Failed(_) => {
+                    if !front.is_empty() && !back.is_empty() {
+                        cuts += 1;
+                    }
+                    if let Some(child) = node.front {
+                        stack.extend(front.into_iter().map(|p| (child, p)));
+                    }
+                    if let Some(child) = node.back {
+                        stack.extend(back.into_iter().map(|p| (child, p)));
+                    }
+                }
+            }
+        }
+
+        if cuts != 0 {
+            ProbeResult::WouldSplit(cuts)
+        } else if coplanar {
+            ProbeResult::Coplanar
+        } else {
+            ProbeResult::NoCut
+        }
+    }
+
+    /// Find the first polygon hit by the ray starting at `origin`
+    /// and going in the `dir` direction.
+    ///
+    /// The tree is walked from the side of the node planes where the ray
+    /// starts, so the first hit found is the closest one. If the ray hits
+    /// several polygons on the same plane, the one added last is returned.
+    pub fn raycast(&mut self, origin: Point3D<T, U>, dir: Vector3D<T, U>) -> Option<Hit<A, T, U>> {
+        self.build();
+        let offset = self.origin().to_vector();
+        let origin = origin - offset;
+        let tolerance = self.epsilons.coplanarity;
+
+        let mut stack = vec![Visit::Node(NodeIdx(0))];
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Node(node_idx) => {
+                    let node = &self.nodes[node_idx.index()];
+                    let plane = match node.values.first() {
+                        None => continue,
+                        Some(first) => &self.polygons[first.index()].plane,
+                    };
+                    // the ray misses everything in the sub-tree
+                    match node.bounds {
+                        Some(ref bounds) if ray_hits_box(&origin, &dir, bounds, tolerance) => {}
+                        _ => continue,
+                    }
+                    let dist = plane.signed_distance_to(&origin);
+                    let is_front = if dist == T::zero() {
+                        plane.normal.dot(dir) > T::zero()
+                    } else {
+                        dist > T::zero()
+                    };
+                    let (near, far) = if is_front {
+                        (node.front, node.back)
+                    } else {
+                        (node.back, node.front)
+                    };
+                    // pushed in reverse, so that `near` is visited first
+                    stack.extend(far.map(Visit::Node));
+                    stack.push(Visit::Values(node_idx));
+                    stack.extend(near.map(Visit::Node));
+                }
+                Visit::Values(node_idx) => {
+                    let values = &self.nodes[node_idx.index()].values;
+                    let plane = &self.polygons[values[0].index()].plane;
+                    let denom = plane.normal.dot(dir);
+                    if denom == T::zero() {
+                        continue;
+                    }
+                    let t = -plane.signed_distance_to(&origin) / denom;
+                    if t < T::zero() {
+                        continue;
+                    }
+                    let point = origin + dir * t;
+                    let hit = values.iter().rev().find_map(|id| {
+                        let poly = &self.polygons[id.index()];
+                        if poly.contains_point(&point, tolerance) {
+                            Some(poly.anchor.clone())
+                        } else {
+                            None
+                        }
+                    });
+                    if let Some(anchor) = hit {
+                        return Some(Hit {
+                            anchor,
+                            point: point + offset,
+                            t,
+                        });
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Get the number of nodes in the tree.
+    ///
+    /// With `Heuristic::MinSplits`, the tree is only built when sorting.
+    pub fn node_count(&self) -> usize {
+        self.nodes.len() - self.free_nodes.len()
+    }
+
+    /// Get the number of polygon fragments stored in the tree,
+    /// including the ones produced by splitting.
+    pub fn fragment_count(&self) -> usize {
+        self.polygons.len() - self.free_fragments.len()
+    }
+
+    /// Get the statistics of the work done since the last reset.
+    pub fn stats(&self) -> SplitStats {
+        SplitStats {
+            output_polys: self.result.len(),
+            tree_depth: self.depth(),
+            node_count: self.node_count(),
+            ..self.stats
+        }
+    }
+
+    /// Get the length of the longest path from the root to a leaf.
+    ///
+    /// A value close to `node_count` means the tree is degenerate,
+    /// e.g. due to many parallel planes being added.
+    pub fn depth(&self) -> usize {
+        if self.nodes[0].values.is_empty() {
+            return 0;
+        }
+        let mut max_depth = 0;
+        let mut stack = vec![(NodeIdx(0), 1)];
+        while let Some((node_idx, depth)) = stack.pop() {
+            let node = &self.nodes[node_idx.index()];
+            max_depth = max_depth.max(depth);
+            stack.extend(node.front.iter().chain(&node.back).map(|&n| (n, depth + 1)));
+        }
+        max_depth
+    }
+
+    /// Get the origin of the polygon fragment with the specified identifier.
+    ///
+    /// Returns `None` unless the provenance is tracked.
+    pub fn fragment_info(&self, id: FragmentId) -> Option<FragmentInfo<A>> {
+        let cuts = self.provenance.as_ref()?.get(id.index())?;
+        Some(FragmentInfo {
+            source_anchor: self.polygons[id.index()].anchor.clone(),
+            cut_planes: cuts.clone(),
+        })
+    }
+
+    /// Match the fragments of the last `sort` with the ones of a previous
+    /// output, e.g. of the last frame.
+    ///
+    /// For each fragment of the last sort, in order, this gives the index
+    /// of the fragment in `previous` with the same anchor that overlaps it
+    /// the most, or `None` if there is no such fragment. The overlap is
+    /// measured on the plane of the current fragment, so the polygons may
+    /// move a bit between the frames. The same previous fragment may
+    /// correspond to several current ones, e.g. when it got split.
+    pub fn correlate(&self, previous: &[Polygon<A, T, U>]) -> Vec<Option<usize>>
+    where
+        A: PartialEq,
+    {
+        self.result
+            .iter()
+            .map(|poly| {
+                let mut best = None;
+                let mut best_area = T::zero();
+                for (i, other) in previous.iter().enumerate() {
+                    if other.anchor != poly.anchor {
+                        continue;
+                    }
+                    let area = poly.overlap_area(other);
+                    if area * area > self.epsilons.degeneracy && area > best_area {
+                        best = Some(i);
+                        best_area = area;
+                    }
+                }
+                best
+            })
+            .collect()
+    }
+
+    /// Get the key of the polygon fragment with the specified identifier.
+    pub fn fragment_key(&self, id: FragmentId) -> FragmentKey {
+        self.keys[id.index()]
+    }
+
+    /// Get the anchors and the keys of the fragments of the last sort,
+    /// in the sorted order.
+    ///
+    /// They match the sorted polygons, unless the fragments
+    /// got merged or dropped by the vertex snapping.
+    pub fn sorted_keys(&self) -> impl Iterator<Item = (A, FragmentKey)> + '_ {
+        self.result_ids.iter().map(move |id| {
+            (
+                self.polygons[id.index()].anchor.clone(),
+                self.keys[id.index()],
+            )
+        })
+    }
+
+    /// Get the polygon defining the plane with the specified identifier.
+    pub fn plane_source(&self, id: PlaneId) -> &Polygon<A, T, U> {
+        let first = self.nodes[id.0 as usize].values[0];
+        &self.polygons[first.index()]
+    }
+
+    /// Check the invariants of the last sorted output against the view vector,
+    /// using the thresholds of this splitter. See `validate` for details.
+    pub fn validate(&self, view: Vector3D<T, U>) -> Result<(), ValidationError> {
+        let ids = &self.result_ids;
+        validate_by(
+            ids.len(),
+            |i| &self.polygons[ids[i].index()],
+            view,
+            &self.epsilons,
+        )
+    }
+
+    /// Get the pairs of anchors of the node polygon and the polygon cut by it,
+    /// for which the side of the cut could be wrong due to the precision.
+    ///
+    /// Returns an empty slice unless the uncertainty is checked.
+    pub fn uncertain_cuts(&self) -> &[(A, A)] {
+        self.uncertain_cuts.as_deref().unwrap_or(&[])
+    }
+
+    /// Get the anchors of the polygons that couldn't be split because
+    /// of the precision issues, along with the errors. Such polygons are
+    /// put whole on the side of the node plane where most of them is.
+    pub fn split_errors(&self) -> &[(A, SplitError)] {
+        &self.split_errors
+    }
+
+    /// Get the polygon fragment with the specified identifier.
+    pub fn fragment(&self, id: FragmentId) -> &Polygon<A, T, U> {
+        &self.polygons[id.index()]
     }
 
     /// Process a set of polygons at once.
-    pub fn solve(&mut self, input: &[Polygon<A>], view: Vector3D<f64>) -> &[Polygon<A>]
+    ///
+    /// This is equivalent to calling `reset`, then `add` for each polygon,
+    /// and finally `sort`. The polygons can be given either by value or by reference.
+    pub fn solve<I>(&mut self, input: I, view: Vector3D<T, U>) -> &[Polygon<A, T, U>]
     where
-        A: Copy,
+        I: IntoIterator,
+        I::Item: Borrow<Polygon<A, T, U>>,
     {
         self.reset();
         for p in input {
-            self.add(p.clone());
+            self.add_ref(p.borrow(), None);
         }
         self.sort(view)
     }
 
-    /// Insert a value into the sub-tree starting with this node.
-    /// This operation may spawn additional leafs/branches of the tree.
-    fn insert(&mut self, node_idx: NodeIdx, value: &Polygon<A>) {
-        let node = &mut self.nodes[node_idx.0];
-        if node.values.is_empty() {
-            node.values.push(add_polygon(&mut self.polygons, value));
+    /// Build the tree out of the buffered polygons.
+    fn build(&mut self) {
+        if self.pending.is_empty() {
             return;
         }
+        let mut pending = core::mem::take(&mut self.pending);
+        if self.deterministic {
+            pending.sort_by(|a, b| compare_polygons(&a.0, &b.0));
+        }
+        match self.heuristic {
+            Heuristic::FirstPolygon => {
+                for (poly, source) in pending.drain(..) {
+                    self.insert(NodeIdx(0), poly, Lineage::new(source));
+                }
+                self.pending = pending;
+            }
+            Heuristic::MinSplits => {
+                self.profile(ProfileEvent::InsertStart);
+                self.build_min_splits(pending);
+                self.profile(ProfileEvent::InsertEnd);
+            }
+        }
+    }
+
+    /// Build the tree choosing the node planes that cause the fewest splits.
+    fn build_min_splits(&mut self, pending: Vec<(Polygon<A, T, U>, u32)>) {
+        let polys: Vec<(Polygon<A, T, U>, Lineage)> = pending
+            .into_iter()
+            .map(|(p, source)| (p, Lineage::new(source)))
+            .collect();
+        let mut stack = vec![(NodeIdx(0), polys)];
 
-        let mut front: SmallVec<[Polygon<A>; 2]> = SmallVec::new();
-        let mut back: SmallVec<[Polygon<A>; 2]> = SmallVec::new();
-        let first = node.values[0].0;
-        match self.polygons[first].cut(value, &mut front, &mut back) {
-            PlaneCut::Sibling => {
-                node.values.push(add_polygon(&mut self.polygons, value));
+        while let Some((node_idx, mut polys)) = stack.pop() {
+            if self.nodes[node_idx.index()].values.is_empty() {
+                let best = select_splitter(&polys, self.epsilons.coplanarity);
+                let (poly, lineage) = polys.swap_remove(best);
+                let bounds = poly.bounding_box();
+                self.add_fragment(node_idx, poly, lineage, bounds);
             }
-            PlaneCut::Cut => {
-                if front.len() != 0 {
-                    if self.nodes[node_idx.0].front.is_none() {
-                        self.nodes[node_idx.0].front = Some(add_node(&mut self.nodes));
+
+            let mut front = Vec::new();
+            let mut back = Vec::new();
+            for (value, mut lineage) in polys.drain(..) {
+                let mut front_cut: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+                let mut back_cut: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+                match self.cut_within_budget(node_idx, value, &mut front_cut, &mut back_cut) {
+                    Err(value) => {
+                        let bounds = value.bounding_box();
+                        self.add_fragment(node_idx, value, lineage, bounds)
                     }
-                    let node_front = self.nodes[node_idx.0].front.unwrap();
-                    for p in &front {
-                        self.insert(node_front, p)
+                    Ok(()) => {
+                        self.check_uncertainty(node_idx, &front_cut, &back_cut);
+                        if !front_cut.is_empty() && !back_cut.is_empty() {
+                            self.stats.cuts_performed += 1;
+                            self.notify_split(node_idx, &front_cut[0].anchor);
+                            if self.provenance.is_some() {
+                                lineage.cuts.push(PlaneId(node_idx.0));
+                            }
+                        }
+                        front.extend(front_cut.into_iter().map(|p| (p, lineage.clone())));
+                        back.extend(back_cut.into_iter().map(|p| (p, lineage.clone())));
                     }
                 }
-                if back.len() != 0 {
-                    if self.nodes[node_idx.0].back.is_none() {
-                        self.nodes[node_idx.0].back = Some(add_node(&mut self.nodes));
+            }
+
+            if !back.is_empty() {
+                if self.nodes[node_idx.index()].back.is_none() {
+                    self.nodes[node_idx.index()].back = Some(add_node(
+                        &mut self.nodes,
+                        &mut self.free_nodes,
+                        &mut self.spare_nodes,
+                        &mut self.stats.allocations,
+                    ));
+                }
+                stack.push((self.nodes[node_idx.index()].back.unwrap(), back));
+            }
+            if !front.is_empty() {
+                if self.nodes[node_idx.index()].front.is_none() {
+                    self.nodes[node_idx.index()].front = Some(add_node(
+                        &mut self.nodes,
+                        &mut self.free_nodes,
+                        &mut self.spare_nodes,
+                        &mut self.stats.allocations,
+                    ));
+                }
+                stack.push((self.nodes[node_idx.index()].front.unwrap(), front));
+            }
+        }
+        self.refit(NodeIdx(0));
+    }
+
+    /// Store a fragment as a value of the node.
+    ///
+    /// The slot of a removed fragment is reused if there is one.
+    fn add_fragment(
+        &mut self,
+        node_idx: NodeIdx,
+        poly: Polygon<A, T, U>,
+        lineage: Lineage,
+        bounds: Box3D<T, U>,
+    ) {
+        let split = &mut self.split_counts[lineage.source as usize];
+        let key = FragmentKey {
+            source: lineage.source,
+            split: *split,
+        };
+        *split += 1;
+        let id = match self.free_fragments.pop() {
+            Some(id) => {
+                self.polygons[id.index()] = poly;
+                self.bounds[id.index()] = bounds;
+                self.keys[id.index()] = key;
+                if let Some(ref mut provenance) = self.provenance {
+                    provenance[id.index()] = lineage.cuts;
+                }
+                id
+            }
+            None => {
+                let id = FragmentId(self.polygons.len() as u32);
+                self.keys.push(key);
+                self.bounds.push(bounds);
+                let capacity = self.polygons.capacity();
+                self.polygons.push(poly);
+                if self.polygons.capacity() != capacity {
+                    self.stats.allocations += 1;
+                }
+                if let Some(ref mut provenance) = self.provenance {
+                    provenance.push(lineage.cuts);
+                }
+                id
+            }
+        };
+        self.nodes[node_idx.index()].values.push(id);
+    }
+
+    /// Cut the polygon with the plane of the node.
+    ///
+    /// The polygon is moved to its side if it's not split,
+    /// and given back if it lies on the plane.
+    fn cut<'a>(
+        &self,
+        node_idx: NodeIdx,
+        poly: Cow<'a, Polygon<A, T, U>>,
+        front: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+        back: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+    ) -> Cut<'a, A, T, U> {
+        let first = self.nodes[node_idx.index()].values[0].index();
+        #[cfg(feature = "robust")]
+        if self.exact {
+            return match self.polygons[first].cut_exact(&poly, front, back, &self.epsilons) {
+                crate::PlaneCut::Sibling => Cut::Sibling(poly),
+                crate::PlaneCut::Cut => Cut::Done,
+            };
+        }
+        self.polygons[first].cut_cow(poly, front, back, &self.epsilons)
+    }
+
+    /// Record the error of a failed cut, giving back the sibling polygon.
+    fn check_cut<'a>(
+        &mut self,
+        anchor: A,
+        cut: Cut<'a, A, T, U>,
+    ) -> Result<(), Cow<'a, Polygon<A, T, U>>> {
+        match cut {
+            Cut::Sibling(poly) => Err(poly),
+            Cut::Done => Ok(()),
+            Cut::Failed(error) => {
+                self.split_errors.push((anchor, error));
+                Ok(())
+            }
+        }
+    }
+
+    /// Cut the polygon with the plane of the node, unless that exceeds
+    /// the fragment budget, in which case it's put on one side as a whole.
+    fn cut_within_budget(
+        &mut self,
+        node_idx: NodeIdx,
+        poly: Polygon<A, T, U>,
+        front: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+        back: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+    ) -> Result<(), Polygon<A, T, U>> {
+        let anchor = poly.anchor.clone();
+        let over_budget = self
+            .budget
+            .is_some_and(|budget| self.fragment_count() >= budget);
+        if !over_budget {
+            let cut = self.cut(node_idx, Cow::Owned(poly), front, back);
+            return self.check_cut(anchor, cut).map_err(Cow::into_owned);
+        }
+        let cut = self.cut(node_idx, Cow::Borrowed(&poly), front, back);
+        if self.check_cut(anchor, cut).is_err() {
+            return Err(poly);
+        }
+        if !front.is_empty() && !back.is_empty() {
+            if self.budget_overflow == 0 {
+                log::warn!("\tFragment budget exceeded, no longer splitting");
+            }
+            self.budget_overflow += 1;
+            front.clear();
+            back.clear();
+            let first = &self.polygons[self.nodes[node_idx.index()].values[0].index()];
+            if first.plane.signed_distance_to(&poly.centroid()) >= T::zero() {
+                front.push(poly);
+            } else {
+                back.push(poly);
+            }
+        }
+        Ok(())
+    }
+
+    /// Report a polygon cut by the plane of the node
+    /// to the profiler and the callback.
+    fn notify_split(&mut self, node_idx: NodeIdx, anchor: &A) {
+        self.profile(ProfileEvent::Cut);
+        let callback = match self.split_callback {
+            Some(ref mut callback) => callback,
+            None => return,
+        };
+        let first = self.nodes[node_idx.index()].values[0];
+        let mut plane = self.polygons[first.index()].plane.clone();
+        if let Some(origin) = self.origin {
+            plane.offset = plane.offset - plane.normal.dot(origin);
+        }
+        callback(anchor, &plane);
+    }
+
+    /// Record the cut if the side of any of the resulting polygons is uncertain.
+    fn check_uncertainty(
+        &mut self,
+        node_idx: NodeIdx,
+        front: &[Polygon<A, T, U>],
+        back: &[Polygon<A, T, U>],
+    ) {
+        let uncertain_cuts = match self.uncertain_cuts {
+            Some(ref mut uncertain_cuts) => uncertain_cuts,
+            None => return,
+        };
+        let first = &self.polygons[self.nodes[node_idx.index()].values[0].index()];
+        if let Some(poly) = front
+            .iter()
+            .chain(back)
+            .find(|poly| first.plane.signed_distance_sum_sign(poly).is_none())
+        {
+            log::warn!(
+                "\tUncertain side of {:?} against the plane of {:?}",
+                poly.anchor,
+                first.anchor
+            );
+            uncertain_cuts.push((first.anchor.clone(), poly.anchor.clone()));
+        }
+    }
+
+    /// Insert a value into the sub-tree starting with this node.
+    /// This operation may spawn additional leafs/branches of the tree.
+    ///
+    /// The traversal uses an explicit stack, so that deep trees
+    /// don't overflow the call stack.
+    fn insert(&mut self, node_idx: NodeIdx, value: Polygon<A, T, U>, lineage: Lineage) {
+        self.profile(ProfileEvent::InsertStart);
+        let mut stack = core::mem::take(&mut self.insert_stack);
+        let bounds = value.bounding_box();
+        stack.push((node_idx, value, lineage, bounds, 0));
+
+        while let Some((node_idx, value, mut lineage, bounds, depth)) = stack.pop() {
+            let node = &mut self.nodes[node_idx.index()];
+            node.bounds = Some(union_bounds(node.bounds, &bounds));
+            if node.values.is_empty() {
+                self.add_fragment(node_idx, value, lineage, bounds);
+                continue;
+            }
+
+            // skip classifying the points if the whole box is on one side
+            if let Some(is_front) = self.bounds_side(node_idx, &bounds) {
+                let child = self.child(node_idx, is_front);
+                stack.push((child, value, lineage, bounds, depth + 1));
+                continue;
+            }
+
+            #[cfg(feature = "tracing")]
+            let span = tracing::debug_span!(
+                "cut",
+                source = lineage.source,
+                depth,
+                classification = tracing::field::Empty,
+                fragments = tracing::field::Empty,
+            )
+            .entered();
+            let mut front: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+            let mut back: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+            match self.cut_within_budget(node_idx, value, &mut front, &mut back) {
+                Err(value) => {
+                    #[cfg(feature = "tracing")]
+                    span.record("classification", "coplanar");
+                    self.add_fragment(node_idx, value, lineage, bounds)
+                }
+                Ok(()) => {
+                    #[cfg(feature = "tracing")]
+                    {
+                        let classification = match (front.is_empty(), back.is_empty()) {
+                            (false, true) => "front",
+                            (true, false) => "back",
+                            _ => "spanning",
+                        };
+                        span.record("classification", classification);
+                        span.record("fragments", front.len() + back.len());
+                    }
+                    self.check_uncertainty(node_idx, &front, &back);
+                    if !front.is_empty() && !back.is_empty() {
+                        self.stats.cuts_performed += 1;
+                        self.notify_split(node_idx, &front[0].anchor);
+                        if self.provenance.is_some() {
+                            lineage.cuts.push(PlaneId(node_idx.0));
+                        }
+                    }
+                    // the back side is pushed first, so that the front
+                    // sub-tree is populated first, same as the recursion did
+                    if !back.is_empty() {
+                        let node_back = self.child(node_idx, false);
+                        stack.extend(back.into_iter().rev().map(|p| {
+                            let bounds = p.bounding_box();
+                            (node_back, p, lineage.clone(), bounds, depth + 1)
+                        }));
                     }
-                    let node_back = self.nodes[node_idx.0].back.unwrap();
-                    for p in &back {
-                        self.insert(node_back, p)
+                    if !front.is_empty() {
+                        let node_front = self.child(node_idx, true);
+                        stack.extend(front.into_iter().rev().map(|p| {
+                            let bounds = p.bounding_box();
+                            (node_front, p, lineage.clone(), bounds, depth + 1)
+                        }));
                     }
                 }
             }
         }
+
+        self.insert_stack = stack;
+        self.profile(ProfileEvent::InsertEnd);
+    }
+
+    /// Report the event to the profiler, if any.
+    fn profile(&mut self, event: ProfileEvent) {
+        if let Some(ref mut profiler) = self.profiler {
+            profiler.event(event);
+        }
+    }
+
+    /// Tell on which side of the node plane the bounding box is,
+    /// if it's entirely on one side of it.
+    fn bounds_side(&self, node_idx: NodeIdx, bounds: &Box3D<T, U>) -> Option<bool> {
+        let plane = &self.polygons[self.nodes[node_idx.index()].values[0].index()].plane;
+        let half = T::one() / (T::one() + T::one());
+        let center = bounds.min.lerp(bounds.max, half);
+        let extent = (bounds.max - bounds.min) * half;
+        let normal = plane.normal;
+        let radius =
+            normal.x.abs() * extent.x + normal.y.abs() * extent.y + normal.z.abs() * extent.z;
+        let dist = plane.signed_distance_to(&center);
+        let epsilon = self.epsilons.coplanarity;
+        if dist - radius > epsilon {
+            Some(true)
+        } else if dist + radius < -epsilon {
+            Some(false)
+        } else {
+            None
+        }
+    }
+
+    /// Get the front or back child of the node, adding it if needed.
+    fn child(&mut self, node_idx: NodeIdx, is_front: bool) -> NodeIdx {
+        let node = &self.nodes[node_idx.index()];
+        if let Some(child) = if is_front { node.front } else { node.back } {
+            return child;
+        }
+        let child = add_node(
+            &mut self.nodes,
+            &mut self.free_nodes,
+            &mut self.spare_nodes,
+            &mut self.stats.allocations,
+        );
+        let node = &mut self.nodes[node_idx.index()];
+        if is_front {
+            node.front = Some(child);
+        } else {
+            node.back = Some(child);
+        }
+        child
     }
 
     /// Build the draw order of this sub-tree into an `out` vector,
     /// so that the contained planes are sorted back to front according
     /// to the view vector defined as the `base` plane front direction.
-    pub fn order(&self, node: NodeIdx, base: &Polygon<A>, out: &mut Vec<Polygon<A>>) {
-        let node = &self.nodes[node.0];
-        let (former, latter) = match node.values.first() {
-            None => return,
-            Some(first) => {
-                if base.is_aligned(&self.polygons[first.0]) {
-                    (node.front, node.back)
-                } else {
-                    (node.back, node.front)
+    pub fn order(&self, node: NodeIdx, base: &Polygon<A, T, U>, out: &mut Vec<FragmentId>) {
+        self.order_by(
+            node,
+            &|poly: &Polygon<A, T, U>| base.is_aligned(poly),
+            None,
+            out,
+        );
+    }
+
+    /// Build the draw order of this sub-tree into an `out` vector,
+    /// where `front_first` tells if the front side of a node plane
+    /// needs to be drawn before the back side. The viewer is behind
+    /// the fragments for which it's true, so they are skipped if the
+    /// `sidedness` is given and tells they are single-sided.
+    fn order_by<F>(
+        &self,
+        node: NodeIdx,
+        front_first: &F,
+        sidedness: Option<&dyn Fn(&A) -> Sidedness>,
+        out: &mut Vec<FragmentId>,
+    ) where
+        F: Fn(&Polygon<A, T, U>) -> bool,
+    {
+        let mut stack = vec![Visit::Node(node)];
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Node(node_idx) => {
+                    let node = &self.nodes[node_idx.index()];
+                    let (former, latter) = match node.values.first() {
+                        None => continue,
+                        Some(first) => {
+                            if front_first(&self.polygons[first.index()]) {
+                                (node.front, node.back)
+                            } else {
+                                (node.back, node.front)
+                            }
+                        }
+                    };
+                    // pushed in reverse, so that `former` is visited first
+                    stack.extend(latter.map(Visit::Node));
+                    stack.push(Visit::Values(node_idx));
+                    stack.extend(former.map(Visit::Node));
+                }
+                Visit::Values(node_idx) => {
+                    let start = out.len();
+                    let values = &self.nodes[node_idx.index()].values;
+                    match sidedness {
+                        Some(sidedness) => out.extend(values.iter().cloned().filter(|id| {
+                            let poly = &self.polygons[id.index()];
+                            !front_first(poly) || sidedness(&poly.anchor) == Sidedness::DoubleSided
+                        })),
+                        None => out.extend_from_slice(values),
+                    }
+                    if let Some(ref cmp) = self.sibling_order {
+                        out[start..].sort_by(|a, b| {
+                            cmp(
+                                &self.polygons[a.index()].anchor,
+                                &self.polygons[b.index()].anchor,
+                            )
+                        });
+                    }
                 }
             }
-        };
+        }
+    }
+}
+
+impl<A, T, U> Splitter<A, T, U> for BspSplitter<A, T, U>
+where
+    A: Clone + fmt::Debug + Default,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn reset(&mut self) {
+        BspSplitter::reset(self);
+    }
+
+    fn add(&mut self, polygon: Polygon<A, T, U>) {
+        BspSplitter::add(self, polygon);
+    }
+
+    fn add_all<I>(&mut self, polygons: I)
+    where
+        I: IntoIterator<Item = Polygon<A, T, U>>,
+    {
+        BspSplitter::add_all(self, polygons);
+    }
+
+    fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        BspSplitter::sort(self, view)
+    }
+}
+
+impl<A, T, U> Extend<Polygon<A, T, U>> for BspSplitter<A, T, U>
+where
+    A: Clone + fmt::Debug + Default,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn extend<I: IntoIterator<Item = Polygon<A, T, U>>>(&mut self, iter: I) {
+        self.add_all(iter);
+    }
+}
 
-        if let Some(node) = former {
-            self.order(node, base, out);
+/// Round the polygon points to the grid, then weld the points
+/// that ended up in the neighboring grid cells.
+fn snap_to_grid<A, T: Float, U>(polygons: &mut Vec<Polygon<A, T, U>>, step: T) {
+    let mut welded: BTreeMap<[i64; 3], Point3D<T, U>> = BTreeMap::new();
+    for poly in polygons.iter_mut() {
+        for point in poly.points.iter_mut() {
+            let cell = [point.x, point.y, point.z].map(|c| (c / step).round());
+            let key = cell.map(|c| c.to_i64().unwrap_or(0));
+            // points closer than the grid size can be rounded apart
+            let neighbor = (0..27).find_map(|i| {
+                let offset = [i / 9 - 1, i / 3 % 3 - 1, i % 3 - 1];
+                let other = [0, 1, 2].map(|k| key[k] + offset[k]);
+                welded
+                    .get(&other)
+                    .filter(|p| (**p - *point).square_length() < step * step)
+            });
+            *point = match neighbor {
+                Some(&p) => p,
+                None => {
+                    let p = Point3D::new(cell[0] * step, cell[1] * step, cell[2] * step);
+                    welded.insert(key, p);
+                    p
+                }
+            };
         }
+        // remove the edges that collapsed
+        let count = poly.points.len();
+        let keep: SmallVec<[bool; 4]> = (0..count)
+            .map(|i| poly.points[i] != poly.points[(i + 1) % count])
+            .collect();
+        if keep.contains(&false) {
+            let edges = &poly.edges;
+            let mut i = 0;
+            poly.points.retain(|_| {
+                i += 1;
+                keep[i - 1]
+            });
+            poly.edges = (0..edges.len())
+                .filter(|&i| keep[i])
+                .map(|i| edges[i])
+                .collect();
+        }
+    }
+    polygons.retain(|poly| poly.points.len() >= 3);
+}
 
-        out.reserve(node.values.len());
-        for poly_idx in &node.values {
-            out.push(self.polygons[poly_idx.0].clone());
+/// Insert the vertices lying on the edges of other polygons into them.
+fn repair_t_junctions<A, T, U>(polygons: &mut [Polygon<A, T, U>], epsilon: T)
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let bounds: Vec<_> = polygons.iter().map(|poly| poly.bounding_box()).collect();
+    let mut points = Vec::new();
+    for i in 0..polygons.len() {
+        let bounds_i = bounds[i].inflate(epsilon, epsilon, epsilon);
+        points.clear();
+        for (j, other) in polygons.iter().enumerate() {
+            if j != i && bounds_i.intersects(&bounds[j]) {
+                points.extend(other.points.iter().filter(|p| bounds_i.contains(**p)));
+            }
         }
+        if polygons[i].insert_edge_points(&points, epsilon) {
+            log::debug!("\tRepaired T-junctions of fragment {}", i);
+        }
+    }
+}
+
+/// Get the box containing both boxes, where `None` is the empty box.
+///
+/// Unlike `Box3D::union`, this keeps the flat boxes of the axis-aligned polygons.
+fn union_bounds<T: Float, U>(a: Option<Box3D<T, U>>, b: &Box3D<T, U>) -> Box3D<T, U> {
+    match a {
+        Some(a) => Box3D::new(a.min.min(b.min), a.max.max(b.max)),
+        None => *b,
+    }
+}
 
-        if let Some(node) = latter {
-            self.order(node, base, out);
+/// Check if the ray starting at `origin` and going in the `dir` direction
+/// passes within `tolerance` of the box.
+fn ray_hits_box<T: Float, U>(
+    origin: &Point3D<T, U>,
+    dir: &Vector3D<T, U>,
+    bounds: &Box3D<T, U>,
+    tolerance: T,
+) -> bool {
+    let axes = [
+        (origin.x, dir.x, bounds.min.x, bounds.max.x),
+        (origin.y, dir.y, bounds.min.y, bounds.max.y),
+        (origin.z, dir.z, bounds.min.z, bounds.max.z),
+    ];
+    let (mut t_min, mut t_max) = (T::zero(), T::infinity());
+    for &(o, d, lo, hi) in &axes {
+        let (lo, hi) = (lo - tolerance, hi + tolerance);
+        if d == T::zero() {
+            if o < lo || o > hi {
+                return false;
+            }
+            continue;
+        }
+        let (t0, t1) = ((lo - o) / d, (hi - o) / d);
+        t_min = t_min.max(t0.min(t1));
+        t_max = t_max.min(t0.max(t1));
+        if t_min > t_max {
+            return false;
         }
     }
+    true
 }
 
-pub fn add_polygon<A: Copy>(polygons: &mut Vec<Polygon<A>>, poly: &Polygon<A>) -> PolygonIdx {
-    let index = PolygonIdx(polygons.len());
-    polygons.push(poly.clone());
-    index
+/// Compare polygons by their plane equations, then by their points.
+fn compare_polygons<A, T: Float, U>(a: &Polygon<A, T, U>, b: &Polygon<A, T, U>) -> Ordering {
+    fn cmp<T: Float>(a: T, b: T) -> Ordering {
+        a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+    }
+    let (na, nb) = (a.plane.normal, b.plane.normal);
+    cmp(na.x, nb.x)
+        .then(cmp(na.y, nb.y))
+        .then(cmp(na.z, nb.z))
+        .then(cmp(a.plane.offset, b.plane.offset))
+        .then_with(|| {
+            let pa = a.points.iter().flat_map(|p| [p.x, p.y, p.z]);
+            let pb = b.points.iter().flat_map(|p| [p.x, p.y, p.z]);
+            pa.partial_cmp(pb).unwrap_or(Ordering::Equal)
+        })
+}
+
+/// Choose the polygon whose plane splits the fewest of the others,
+/// while keeping the two sides balanced. Only a subset of the polygons
+/// is considered as candidates, to keep the cost bounded for large inputs.
+fn select_splitter<A, T, U>(polys: &[(Polygon<A, T, U>, Lineage)], epsilon: T) -> usize
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let step = polys.len().div_ceil(MAX_CANDIDATES);
+    let mut best = (usize::MAX, 0);
+    for candidate in (0..polys.len()).step_by(step.max(1)) {
+        let plane = &polys[candidate].0.plane;
+        let (mut front, mut back, mut splits) = (0usize, 0usize, 0);
+        for (i, (poly, _)) in polys.iter().enumerate() {
+            if i == candidate {
+                continue;
+            }
+            let (mut is_front, mut is_back) = (false, false);
+            for point in &poly.points {
+                let dist = plane.signed_distance_to(point);
+                is_front |= dist > epsilon;
+                is_back |= dist < -epsilon;
+            }
+            match (is_front, is_back) {
+                (true, true) => splits += 1,
+                (true, false) => front += 1,
+                (false, true) => back += 1,
+                (false, false) => {}
+            }
+        }
+        let score = splits * SPLIT_COST + front.abs_diff(back);
+        if score < best.0 {
+            best = (score, candidate);
+        }
+    }
+    best.1
 }
 
-pub fn add_node(nodes: &mut Vec<BspNode>) -> NodeIdx {
-    let index = NodeIdx(nodes.len());
-    nodes.push(BspNode::new());
+pub fn add_node<T, U>(
+    nodes: &mut Vec<Node<T, U>>,
+    free_nodes: &mut Vec<NodeIdx>,
+    spare_nodes: &mut Vec<Node<T, U>>,
+    allocations: &mut usize,
+) -> NodeIdx {
+    if let Some(index) = free_nodes.pop() {
+        return index;
+    }
+    let index = NodeIdx(nodes.len() as u32);
+    let capacity = nodes.capacity();
+    nodes.push(spare_nodes.pop().unwrap_or_default());
+    if nodes.capacity() != capacity {
+        *allocations += 1;
+    }
     index
 }
 
 /// A node in the `BspTree`, which can be considered a tree itself.
-#[derive(Clone, Debug)]
-pub struct BspNode {
-    values: SmallVec<[PolygonIdx; 4]>,
+pub struct Node<T = f64, U = UnknownUnit> {
+    values: SmallVec<[FragmentId; 4]>,
     front: Option<NodeIdx>,
     back: Option<NodeIdx>,
+    /// The bounding box of the fragments in the sub-tree,
+    /// including the ones on the node plane.
+    bounds: Option<Box3D<T, U>>,
+}
+
+impl<T: Copy, U> Clone for Node<T, U> {
+    fn clone(&self) -> Self {
+        Node {
+            values: self.values.clone(),
+            front: self.front,
+            back: self.back,
+            bounds: self.bounds,
+        }
+    }
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Node<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Node")
+            .field("values", &self.values)
+            .field("front", &self.front)
+            .field("back", &self.back)
+            .field("bounds", &self.bounds)
+            .finish()
+    }
 }
 
-impl BspNode {
+impl<T, U> Node<T, U> {
     /// Create a new node.
     pub fn new() -> Self {
-        BspNode {
+        Node {
             values: SmallVec::new(),
             front: None,
             back: None,
+            bounds: None,
         }
     }
 }
+
+impl<T, U> Default for Node<T, U> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
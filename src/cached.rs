@@ -0,0 +1,166 @@
+use crate::{Polygon, Splitter};
+
+use euclid::{UnknownUnit, Vector3D};
+use num_traits::Float;
+
+use alloc::vec::Vec;
+use core::hash::{Hash, Hasher};
+
+/// The 64-bit FNV-1a hash, which is small and available without `std`.
+/// This is the default hasher of the `CachedSplitter`.
+#[derive(Clone, Copy, Debug)]
+pub struct SceneHasher(u64);
+
+impl SceneHasher {
+    const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+}
+
+impl Default for SceneHasher {
+    fn default() -> Self {
+        SceneHasher(Self::OFFSET)
+    }
+}
+
+impl Hasher for SceneHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(Self::PRIME);
+        }
+    }
+}
+
+fn hash_vector<T: Float, U, H: Hasher>(v: Vector3D<T, U>, hasher: &mut H) {
+    v.x.integer_decode().hash(hasher);
+    v.y.integer_decode().hash(hasher);
+    v.z.integer_decode().hash(hasher);
+}
+
+/// A splitter remembering the result of the last sort.
+///
+/// The added polygons and the view vector are hashed, and if the hash
+/// matches the one of the last sort, they are compared with the ones of
+/// the last sort. If they are the same, the previous result is returned
+/// without touching the inner splitter. Any change in the polygons,
+/// including their order and anchors, makes the inner splitter
+/// process them again.
+///
+/// The comparison makes sure a hash collision never returns the result
+/// of another scene, but it means that a cache hit still goes over all
+/// the points once. A copy of the scene is kept for it, which is only
+/// updated when the scene changes, and reuses its storage.
+///
+/// This is meant for the frames presenting a static scene, which
+/// still have to go through `reset`, `add` and `sort` every time.
+pub struct CachedSplitter<S, A, T = f64, U = UnknownUnit, H = SceneHasher> {
+    inner: S,
+    pending: Vec<Polygon<A, T, U>>,
+    hasher: H,
+    last_input: Vec<Polygon<A, T, U>>,
+    last_view: Option<Vector3D<T, U>>,
+    last_hash: Option<u64>,
+    result: Vec<Polygon<A, T, U>>,
+    hit: bool,
+}
+
+impl<S, A, T, U> CachedSplitter<S, A, T, U, SceneHasher> {
+    /// Create a new caching splitter on top of another one.
+    pub fn new(inner: S) -> Self {
+        Self::with_hasher(inner)
+    }
+}
+
+impl<S, A, T, U, H: Default> CachedSplitter<S, A, T, U, H> {
+    /// Create a new caching splitter on top of another one,
+    /// hashing the scenes with `H` instead of the `SceneHasher`.
+    pub fn with_hasher(inner: S) -> Self {
+        CachedSplitter {
+            inner,
+            pending: Vec::new(),
+            hasher: H::default(),
+            last_input: Vec::new(),
+            last_view: None,
+            last_hash: None,
+            result: Vec::new(),
+            hit: false,
+        }
+    }
+
+    /// Get the underlying splitter.
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Get the underlying splitter for configuration.
+    ///
+    /// This invalidates the cached result, since the configuration
+    /// may change the output.
+    pub fn inner_mut(&mut self) -> &mut S {
+        self.invalidate();
+        &mut self.inner
+    }
+
+    /// Forget the cached result, so that the next sort is done
+    /// by the inner splitter.
+    pub fn invalidate(&mut self) {
+        self.last_hash = None;
+    }
+
+    /// Check if the last sort returned the cached result.
+    pub fn was_hit(&self) -> bool {
+        self.hit
+    }
+}
+
+impl<S, A, T, U, H> Splitter<A, T, U> for CachedSplitter<S, A, T, U, H>
+where
+    S: Splitter<A, T, U>,
+    A: Clone + Hash + PartialEq,
+    T: Float,
+    H: Hasher + Default + Clone,
+{
+    /// Start a new scene. The cached result is kept until
+    /// the next sort finds out if the scene has changed.
+    fn reset(&mut self) {
+        self.pending.clear();
+        self.hasher = H::default();
+    }
+
+    fn add(&mut self, polygon: Polygon<A, T, U>) {
+        polygon.points.len().hash(&mut self.hasher);
+        for point in &polygon.points {
+            hash_vector(point.to_vector(), &mut self.hasher);
+        }
+        hash_vector(polygon.plane.normal, &mut self.hasher);
+        polygon.plane.offset.integer_decode().hash(&mut self.hasher);
+        polygon.anchor.hash(&mut self.hasher);
+        polygon.edges.hash(&mut self.hasher);
+        self.pending.push(polygon);
+    }
+
+    fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        let mut hasher = self.hasher.clone();
+        hash_vector(view, &mut hasher);
+        let hash = hasher.finish();
+
+        // a hash collision must not return the result of another scene
+        self.hit = self.last_hash == Some(hash)
+            && self.last_view == Some(view)
+            && self.last_input == self.pending;
+        if !self.hit {
+            log::debug!("\tScene hash {:x} changed, sorting again", hash);
+            self.inner.reset();
+            self.inner.add_all(self.pending.iter().cloned());
+            self.result.clear();
+            self.result.extend_from_slice(self.inner.sort(view));
+            self.last_input.clone_from(&self.pending);
+            self.last_view = Some(view);
+            self.last_hash = Some(hash);
+        }
+        &self.result
+    }
+}
@@ -0,0 +1,150 @@
+//! C API for embedders, suitable for generating a header with cbindgen.
+//!
+//! Polygons are passed as flat arrays of `x, y, z` doubles with a `u64` anchor.
+//! All the functions accept a null splitter pointer and do nothing with it.
+
+use crate::{BspSplitter, Polygon};
+
+use euclid::default::{Point3D, Vector3D};
+
+use alloc::{boxed::Box, vec::Vec};
+use core::slice;
+
+/// A polygon in the C representation.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct PlaneSplitPolygon {
+    /// Pointer to `3 * point_count` coordinates.
+    pub points: *const f64,
+    /// Number of points.
+    pub point_count: usize,
+    /// Anchor identifying the source polygon.
+    pub anchor: u64,
+}
+
+/// Opaque splitter handle.
+#[derive(Default)]
+pub struct PlaneSplitter {
+    splitter: BspSplitter<u64>,
+    coordinates: Vec<f64>,
+    fragments: Vec<(usize, usize, u64)>,
+}
+
+/// Create a new splitter, to be released with `plane_split_free`.
+#[no_mangle]
+pub extern "C" fn plane_split_new() -> *mut PlaneSplitter {
+    Box::into_raw(Box::default())
+}
+
+/// Release a splitter created with `plane_split_new`.
+///
+/// # Safety
+/// `splitter` must be null or come from `plane_split_new`, and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn plane_split_free(splitter: *mut PlaneSplitter) {
+    if !splitter.is_null() {
+        drop(Box::from_raw(splitter));
+    }
+}
+
+/// Remove all the polygons from the splitter.
+///
+/// # Safety
+/// `splitter` must be null or a live splitter.
+#[no_mangle]
+pub unsafe extern "C" fn plane_split_reset(splitter: *mut PlaneSplitter) {
+    if let Some(this) = splitter.as_mut() {
+        this.splitter.reset();
+        this.coordinates.clear();
+        this.fragments.clear();
+    }
+}
+
+/// Add a polygon to the splitter. Returns false if the polygon is degenerate.
+///
+/// # Safety
+/// `splitter` must be null or a live splitter, and `polygon` must point
+/// to a polygon with `3 * point_count` readable coordinates.
+#[no_mangle]
+pub unsafe extern "C" fn plane_split_add(
+    splitter: *mut PlaneSplitter,
+    polygon: *const PlaneSplitPolygon,
+) -> bool {
+    let (this, polygon) = match (splitter.as_mut(), polygon.as_ref()) {
+        (Some(this), Some(polygon)) => (this, polygon),
+        _ => return false,
+    };
+    if polygon.points.is_null() {
+        return false;
+    }
+    let coordinates = slice::from_raw_parts(polygon.points, 3 * polygon.point_count);
+    let points = coordinates
+        .chunks_exact(3)
+        .map(|c| Point3D::new(c[0], c[1], c[2]));
+    match Polygon::from_points(points, polygon.anchor) {
+        Some(poly) => {
+            this.splitter.add(poly);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Sort the polygons for the given view direction,
+/// returning the number of resulting fragments.
+///
+/// # Safety
+/// `splitter` must be null or a live splitter.
+#[no_mangle]
+pub unsafe extern "C" fn plane_split_sort(
+    splitter: *mut PlaneSplitter,
+    view_x: f64,
+    view_y: f64,
+    view_z: f64,
+) -> usize {
+    let this = match splitter.as_mut() {
+        Some(this) => this,
+        None => return 0,
+    };
+    this.coordinates.clear();
+    this.fragments.clear();
+    let view = Vector3D::new(view_x, view_y, view_z);
+    for poly in this.splitter.sort(view) {
+        let start = this.coordinates.len();
+        for p in poly.points.iter() {
+            this.coordinates.extend_from_slice(&[p.x, p.y, p.z]);
+        }
+        this.fragments.push((start, poly.points.len(), poly.anchor));
+    }
+    this.fragments.len()
+}
+
+/// Get a fragment produced by the last `plane_split_sort`.
+/// Returns false if the index is out of range.
+///
+/// The points stay valid until the next call that modifies the splitter.
+///
+/// # Safety
+/// `splitter` must be null or a live splitter, and `fragment` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn plane_split_get(
+    splitter: *const PlaneSplitter,
+    index: usize,
+    fragment: *mut PlaneSplitPolygon,
+) -> bool {
+    let this = match splitter.as_ref() {
+        Some(this) => this,
+        None => return false,
+    };
+    match this.fragments.get(index) {
+        Some(&(start, point_count, anchor)) if !fragment.is_null() => {
+            *fragment = PlaneSplitPolygon {
+                points: this.coordinates[start..].as_ptr(),
+                point_count,
+                anchor,
+            };
+            true
+        }
+        _ => false,
+    }
+}
@@ -1,18 +1,223 @@
 use crate::{Intersection, NegativeHemisphereError, Plane, Polygon};
 
-use euclid::default::{Rect, Scale, Transform3D, Vector3D};
+use euclid::{approxeq::ApproxEq, Box3D, Point3D, Rect, Scale, Transform3D, UnknownUnit, Vector3D};
+use num_traits::Float;
 
-use std::{fmt, iter, mem};
+use alloc::{vec, vec::Vec};
+use core::{fmt, iter, mem};
+
+/// A view frustum, defined by six planes looking inside.
+pub struct Frustum<T = f64, U = UnknownUnit> {
+    /// Left, right, bottom, top, near, and far planes.
+    pub planes: [Plane<T, U>; 6],
+}
+
+impl<T: Copy, U> Clone for Frustum<T, U> {
+    fn clone(&self) -> Self {
+        Frustum {
+            planes: self.planes.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Frustum<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Frustum")
+            .field("planes", &self.planes)
+            .finish()
+    }
+}
+
+impl<T: PartialEq, U> PartialEq for Frustum<T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.planes == other.planes
+    }
+}
+
+impl<T, U> Frustum<T, U>
+where
+    T: Float + ApproxEq<T>,
+{
+    /// Extract the frustum from a projection transformation, so that it contains
+    /// everything projected into the [-1, 1] range of all the coordinates.
+    /// Returns None if any of the planes is degenerate.
+    pub fn from_transform<V>(t: &Transform3D<T, U, V>) -> Option<Self> {
+        let mx = Vector3D::new(t.m11, t.m21, t.m31);
+        let my = Vector3D::new(t.m12, t.m22, t.m32);
+        let mz = Vector3D::new(t.m13, t.m23, t.m33);
+        let mw = Vector3D::new(t.m14, t.m24, t.m34);
+        let plane = |normal: Vector3D<T, U>, offset: T| {
+            Plane::from_unnormalized(normal, offset).ok().flatten()
+        };
+        Some(Frustum {
+            planes: [
+                plane(mw + mx, t.m44 + t.m41)?,
+                plane(mw - mx, t.m44 - t.m41)?,
+                plane(mw + my, t.m44 + t.m42)?,
+                plane(mw - my, t.m44 - t.m42)?,
+                plane(mw + mz, t.m44 + t.m43)?,
+                plane(mw - mz, t.m44 - t.m43)?,
+            ],
+        })
+    }
+}
+
+/// A convex region, defined by any number of planes looking inside,
+/// such as a clip rectangle of a layer.
+pub struct ClipRegion<T = f64, U = UnknownUnit> {
+    /// Planes bounding the region.
+    pub planes: Vec<Plane<T, U>>,
+}
+
+impl<T: Copy, U> Clone for ClipRegion<T, U> {
+    fn clone(&self) -> Self {
+        ClipRegion {
+            planes: self.planes.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for ClipRegion<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClipRegion")
+            .field("planes", &self.planes)
+            .finish()
+    }
+}
+
+impl<T, U> Default for ClipRegion<T, U> {
+    fn default() -> Self {
+        ClipRegion { planes: Vec::new() }
+    }
+}
+
+impl<T: PartialEq, U> PartialEq for ClipRegion<T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.planes == other.planes
+    }
+}
+
+impl<T, U> ClipRegion<T, U>
+where
+    T: Float + ApproxEq<T>,
+{
+    /// Create a region bounded by the given planes.
+    pub fn new(planes: Vec<Plane<T, U>>) -> Self {
+        ClipRegion { planes }
+    }
+
+    /// Create the region covered by a rectangle in the local space,
+    /// extended along the local Z axis, as seen in the space the
+    /// `transform` maps to.
+    ///
+    /// Returns None if the transform isn't invertible.
+    pub fn from_rect<V>(rect: &Rect<T, V>, transform: &Transform3D<T, V, U>) -> Option<Self> {
+        let (min, max) = (rect.min(), rect.max());
+        let (zero, one) = (T::zero(), T::one());
+        let local: [Plane<T, V>; 4] = [
+            Plane {
+                normal: Vector3D::new(one, zero, zero),
+                offset: -min.x,
+            },
+            Plane {
+                normal: Vector3D::new(-one, zero, zero),
+                offset: max.x,
+            },
+            Plane {
+                normal: Vector3D::new(zero, one, zero),
+                offset: -min.y,
+            },
+            Plane {
+                normal: Vector3D::new(zero, -one, zero),
+                offset: max.y,
+            },
+        ];
+        let planes = local
+            .iter()
+            .map(|plane| plane.transform(transform))
+            .collect::<Option<_>>()?;
+        Some(ClipRegion { planes })
+    }
+
+    /// Create the region inside of an axis-aligned box.
+    pub fn from_box(bounds: &Box3D<T, U>) -> Self {
+        let (min, max) = (bounds.min, bounds.max);
+        let axes = [
+            Vector3D::new(T::one(), T::zero(), T::zero()),
+            Vector3D::new(T::zero(), T::one(), T::zero()),
+            Vector3D::new(T::zero(), T::zero(), T::one()),
+        ];
+        let planes = axes
+            .iter()
+            .flat_map(|&axis| {
+                let low = Plane {
+                    normal: axis,
+                    offset: -axis.dot(min.to_vector()),
+                };
+                let high = Plane {
+                    normal: -axis,
+                    offset: axis.dot(max.to_vector()),
+                };
+                iter::once(low).chain(iter::once(high))
+            })
+            .collect();
+        ClipRegion { planes }
+    }
+
+    /// Add a plane, clipping everything behind it.
+    pub fn add(&mut self, plane: Plane<T, U>) {
+        self.planes.push(plane);
+    }
+
+    /// Check if the point is inside the region.
+    pub fn contains(&self, point: &Point3D<T, U>) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.signed_distance_to(point) >= T::zero())
+    }
+}
+
+impl<T, U> From<Frustum<T, U>> for ClipRegion<T, U> {
+    fn from(frustum: Frustum<T, U>) -> Self {
+        let [a, b, c, d, e, f] = frustum.planes;
+        ClipRegion {
+            planes: vec![a, b, c, d, e, f],
+        }
+    }
+}
 
 /// A helper object to clip polygons by a number of planes.
-#[derive(Debug)]
-pub struct Clipper<A> {
-    clips: Vec<Plane>,
-    results: Vec<Polygon<A>>,
-    temp: Vec<Polygon<A>>,
+pub struct Clipper<A, T = f64, U = UnknownUnit> {
+    clips: Vec<Plane<T, U>>,
+    results: Vec<Polygon<A, T, U>>,
+    temp: Vec<Polygon<A, T, U>>,
+}
+
+impl<A: fmt::Debug, T: fmt::Debug, U> fmt::Debug for Clipper<A, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Clipper")
+            .field("clips", &self.clips)
+            .field("results", &self.results)
+            .field("temp", &self.temp)
+            .finish()
+    }
+}
+
+impl<A, T, U> Default for Clipper<A, T, U>
+where
+    A: Clone + fmt::Debug,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
-impl<A: Copy + fmt::Debug> Clipper<A> {
+impl<A, T, U> Clipper<A, T, U>
+where
+    A: Clone + fmt::Debug,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
     /// Create a new clipper object.
     pub fn new() -> Self {
         Clipper {
@@ -28,10 +233,10 @@ impl<A: Copy + fmt::Debug> Clipper<A> {
     }
 
     /// Extract the clipping planes that define the frustum for a given transformation.
-    pub fn frustum_planes(
-        t: &Transform3D<f64>,
-        bounds: Option<Rect<f64>>,
-    ) -> Result<impl Iterator<Item = Plane>, NegativeHemisphereError> {
+    pub fn frustum_planes<V>(
+        t: &Transform3D<T, U, V>,
+        bounds: Option<Rect<T, V>>,
+    ) -> Result<impl Iterator<Item = Plane<T, U>>, NegativeHemisphereError> {
         let mw = Vector3D::new(t.m14, t.m24, t.m34);
         let plane_positive = Plane::from_unnormalized(mw, t.m44)?;
 
@@ -66,18 +271,18 @@ impl<A: Copy + fmt::Debug> Clipper<A> {
 
         Ok(bounds_iter_maybe
             .into_iter()
-            .flat_map(|pi| pi)
+            .flatten()
             .chain(plane_positive))
     }
 
     /// Add a clipping plane to the list. The plane will clip everything behind it,
     /// where the direction is set by the plane normal.
-    pub fn add(&mut self, plane: Plane) {
+    pub fn add(&mut self, plane: Plane<T, U>) {
         self.clips.push(plane);
     }
 
     /// Clip specified polygon by the contained planes, return the fragmented polygons.
-    pub fn clip(&mut self, polygon: Polygon<A>) -> &[Polygon<A>] {
+    pub fn clip(&mut self, polygon: Polygon<A, T, U>) -> &[Polygon<A, T, U>] {
         log::debug!("\tClipping {:?}", polygon);
         self.results.clear();
         self.results.push(polygon);
@@ -94,7 +299,7 @@ impl<A: Copy + fmt::Debug> Clipper<A> {
                             iter::once(poly)
                                 .chain(res1)
                                 .chain(res2)
-                                .filter(|p| clip.signed_distance_sum_to(p) > 0.0),
+                                .filter(|p| clip.signed_distance_sum_to(p) > T::zero()),
                         );
                         continue;
                     }
@@ -105,7 +310,7 @@ impl<A: Copy + fmt::Debug> Clipper<A> {
                     Intersection::Outside => clip.signed_distance_sum_to(&poly),
                 };
 
-                if dist > 0.0 {
+                if dist > T::zero() {
                     self.results.push(poly);
                 }
             }
@@ -117,12 +322,12 @@ impl<A: Copy + fmt::Debug> Clipper<A> {
     /// Clip the primitive with the frustum of the specified transformation,
     /// returning a sequence of polygons in the transformed space.
     /// Returns None if the transformation can't be frustum clipped.
-    pub fn clip_transformed<'a>(
+    pub fn clip_transformed<'a, V>(
         &'a mut self,
-        polygon: Polygon<A>,
-        transform: &'a Transform3D<f64>,
-        bounds: Option<Rect<f64>>,
-    ) -> Result<impl 'a + Iterator<Item = Polygon<A>>, NegativeHemisphereError> {
+        polygon: Polygon<A, T, U>,
+        transform: &'a Transform3D<T, U, V>,
+        bounds: Option<Rect<T, V>>,
+    ) -> Result<impl 'a + Iterator<Item = Polygon<A, T, V>>, NegativeHemisphereError> {
         let planes = Self::frustum_planes(transform, bounds)?;
 
         let old_count = self.clips.len();
@@ -136,7 +341,7 @@ impl<A: Copy + fmt::Debug> Clipper<A> {
         let polys = self
             .results
             .drain(..)
-            .flat_map(move |poly| poly.transform(transform));
+            .flat_map(move |poly| poly.transform_clipped(transform));
         Ok(polys)
     }
 }
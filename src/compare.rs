@@ -0,0 +1,189 @@
+use crate::{Polygon, Splitter};
+
+use euclid::{approxeq::ApproxEq, UnknownUnit, Vector3D};
+use num_traits::Float;
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// A difference between the outputs of two splitters.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Divergence<A, T = f64> {
+    /// The total area of the fragments of an anchor is different.
+    Coverage {
+        /// Anchor of the source polygon.
+        anchor: A,
+        /// Area covered in the output of the first splitter.
+        first: T,
+        /// Area covered in the output of the second splitter.
+        second: T,
+    },
+    /// Only one of the splitters draws a fragment of `former`
+    /// before an overlapping fragment of `latter`.
+    Order {
+        /// Anchor of the fragment drawn first.
+        former: A,
+        /// Anchor of the fragment drawn later.
+        latter: A,
+        /// True if it's the first splitter drawing them in this order.
+        in_first: bool,
+    },
+}
+
+/// A splitter running two other splitters on the same input
+/// and reporting the differences between their outputs.
+///
+/// The output of the first splitter is returned from `sort`.
+pub struct CompareSplitter<S, R, A, T = f64, U = UnknownUnit> {
+    first: S,
+    second: R,
+    result: Vec<Polygon<A, T, U>>,
+    divergences: Vec<Divergence<A, T>>,
+}
+
+impl<S, R, A, T, U> CompareSplitter<S, R, A, T, U> {
+    /// Create a new comparison of two splitters.
+    pub fn new(first: S, second: R) -> Self {
+        CompareSplitter {
+            first,
+            second,
+            result: Vec::new(),
+            divergences: Vec::new(),
+        }
+    }
+
+    /// Get the differences found by the last `sort`.
+    pub fn divergences(&self) -> &[Divergence<A, T>] {
+        &self.divergences
+    }
+
+    /// Return the inner splitters.
+    pub fn into_inner(self) -> (S, R) {
+        (self.first, self.second)
+    }
+}
+
+impl<S, R, A, T, U> Splitter<A, T, U> for CompareSplitter<S, R, A, T, U>
+where
+    S: Splitter<A, T, U>,
+    R: Splitter<A, T, U>,
+    A: Clone + PartialEq,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn reset(&mut self) {
+        self.first.reset();
+        self.second.reset();
+        self.result.clear();
+        self.divergences.clear();
+    }
+
+    fn add(&mut self, polygon: Polygon<A, T, U>) {
+        self.first.add(polygon.clone());
+        self.second.add(polygon);
+    }
+
+    fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        self.result.clear();
+        self.result.extend_from_slice(self.first.sort(view));
+        let second = self.second.sort(view);
+        self.divergences.clear();
+
+        let first_areas = anchor_areas(&self.result);
+        let second_areas = anchor_areas(second);
+        let tolerance = T::approx_epsilon().sqrt();
+        for &(ref anchor, first) in &first_areas {
+            let second = area_of(&second_areas, anchor);
+            if (first - second).abs() > tolerance * first.max(second).max(T::one()) {
+                self.divergences.push(Divergence::Coverage {
+                    anchor: anchor.clone(),
+                    first,
+                    second,
+                });
+            }
+        }
+        for &(ref anchor, second) in &second_areas {
+            if !first_areas.iter().any(|(a, _)| a == anchor) {
+                self.divergences.push(Divergence::Coverage {
+                    anchor: anchor.clone(),
+                    first: T::zero(),
+                    second,
+                });
+            }
+        }
+
+        let first_order = anchor_order(&self.result, &view, tolerance);
+        let second_order = anchor_order(second, &view, tolerance);
+        for pair in &first_order {
+            if !second_order.contains(pair) {
+                let (former, latter) = pair.clone();
+                self.divergences.push(Divergence::Order {
+                    former,
+                    latter,
+                    in_first: true,
+                });
+            }
+        }
+        for pair in &second_order {
+            if !first_order.contains(pair) {
+                let (former, latter) = pair.clone();
+                self.divergences.push(Divergence::Order {
+                    former,
+                    latter,
+                    in_first: false,
+                });
+            }
+        }
+
+        &self.result
+    }
+}
+
+/// Sum up the areas of the fragments of each anchor.
+fn anchor_areas<A, T, U>(polygons: &[Polygon<A, T, U>]) -> Vec<(A, T)>
+where
+    A: Clone + PartialEq,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let mut areas: Vec<(A, T)> = Vec::new();
+    for poly in polygons {
+        let area = poly.area();
+        match areas.iter_mut().find(|(a, _)| *a == poly.anchor) {
+            Some(entry) => entry.1 = entry.1 + area,
+            None => areas.push((poly.anchor.clone(), area)),
+        }
+    }
+    areas
+}
+
+fn area_of<A: PartialEq, T: Float>(areas: &[(A, T)], anchor: &A) -> T {
+    areas
+        .iter()
+        .find(|(a, _)| a == anchor)
+        .map_or(T::zero(), |&(_, area)| area)
+}
+
+/// Collect the pairs of anchors, for which a fragment of the former
+/// is drawn before an overlapping fragment of the latter.
+fn anchor_order<A, T, U>(
+    polygons: &[Polygon<A, T, U>],
+    view: &Vector3D<T, U>,
+    epsilon: T,
+) -> Vec<(A, A)>
+where
+    A: Clone + PartialEq,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let mut pairs = Vec::new();
+    for (i, former) in polygons.iter().enumerate() {
+        for latter in &polygons[i + 1..] {
+            let pair = (former.anchor.clone(), latter.anchor.clone());
+            if pair.0 != pair.1
+                && !pairs.contains(&pair)
+                && former.overlaps_along(latter, view, epsilon)
+            {
+                pairs.push(pair);
+            }
+        }
+    }
+    pairs
+}
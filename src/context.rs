@@ -0,0 +1,205 @@
+use crate::{BspSplitter, Clipper, EpsilonConfig, Plane, Polygon, Splitter};
+
+use euclid::{approxeq::ApproxEq, UnknownUnit, Vector3D};
+use num_traits::Float;
+
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+/// Identifier of a 3D rendering context in a `ContextSplitter`.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub struct ContextId(u32);
+
+impl ContextId {
+    /// The context that exists in every splitter, and isn't flattened.
+    pub const ROOT: Self = ContextId(0);
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+struct Context<A, T, U> {
+    anchors: Vec<A>,
+    polygons: Vec<Polygon<usize, T, U>>,
+    /// The nested context each polygon is a placement of, if any.
+    children: Vec<Option<ContextId>>,
+}
+
+impl<A, T, U> Context<A, T, U> {
+    fn new() -> Self {
+        Context {
+            anchors: Vec::new(),
+            polygons: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+}
+
+/// A splitter for nested 3D rendering contexts, as established by
+/// the CSS `transform-style: preserve-3d`.
+///
+/// The polygons are split and sorted only against the others of
+/// the same context. A context other than the root is flattened into
+/// its placement polygon, which is sorted within the parent context:
+/// each fragment of the placement, standing for the backdrop of the
+/// flattened element, is followed in the result by the sorted content
+/// of the child, projected along the view onto
+/// the placement plane and clipped to the fragment.
+///
+/// The polygons of all contexts are given in the same space.
+pub struct ContextSplitter<A, T = f64, U = UnknownUnit> {
+    contexts: Vec<Context<A, T, U>>,
+    inner: BspSplitter<usize, T, U>,
+    clipper: Clipper<A, T, U>,
+    flattened: Vec<Vec<Polygon<A, T, U>>>,
+}
+
+impl<A, T, U> ContextSplitter<A, T, U>
+where
+    A: Clone + fmt::Debug,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    /// Create a new context splitter, with the root context only.
+    pub fn new() -> Self {
+        Self::with_epsilons(EpsilonConfig::default())
+    }
+
+    /// Create a new context splitter, with the thresholds
+    /// used by the BSP splitter of every context.
+    pub fn with_epsilons(epsilons: EpsilonConfig<T>) -> Self {
+        ContextSplitter {
+            contexts: vec![Context::new()],
+            inner: BspSplitter::with_epsilons(epsilons),
+            clipper: Clipper::new(),
+            flattened: Vec::new(),
+        }
+    }
+
+    /// Add a context nested in `parent`, which is flattened into
+    /// the `placement` polygon. The placement takes part in the sort
+    /// of the parent context like any other polygon.
+    pub fn add_context(&mut self, parent: ContextId, placement: Polygon<A, T, U>) -> ContextId {
+        let id = ContextId(self.contexts.len() as u32);
+        self.push(parent, placement, Some(id));
+        self.contexts.push(Context::new());
+        id
+    }
+
+    /// Add a polygon to the specified context.
+    pub fn add_to(&mut self, context: ContextId, polygon: Polygon<A, T, U>) {
+        self.push(context, polygon, None);
+    }
+
+    /// Get the number of contexts, including the root.
+    pub fn context_count(&self) -> usize {
+        self.contexts.len()
+    }
+
+    fn push(&mut self, context: ContextId, polygon: Polygon<A, T, U>, child: Option<ContextId>) {
+        let context = &mut self.contexts[context.index()];
+        let slot = context.anchors.len();
+        context.polygons.push(polygon.map_anchor(|_| slot));
+        context.anchors.push(polygon.anchor);
+        context.children.push(child);
+    }
+
+    /// Project the flattened content of a context onto a placement fragment.
+    fn place(
+        &mut self,
+        content: &[Polygon<A, T, U>],
+        fragment: &Polygon<usize, T, U>,
+        view: Vector3D<T, U>,
+        out: &mut Vec<Polygon<A, T, U>>,
+    ) {
+        let plane = &fragment.plane;
+        let ndot = plane.normal.dot(view);
+        if ndot.abs() <= T::approx_epsilon() {
+            // the placement is seen edge-on, so is its content
+            return;
+        }
+
+        self.clipper.reset();
+        let center = fragment.centroid();
+        let count = fragment.points.len();
+        for (i, &a) in fragment.points.iter().enumerate() {
+            let b = fragment.points[(i + 1) % count];
+            let mut normal = plane.normal.cross(b - a);
+            if normal.dot(center - a) < T::zero() {
+                normal = -normal;
+            }
+            if let Some(edge) = Plane::from_point_normal(a, normal) {
+                self.clipper.add(edge);
+            }
+        }
+
+        for poly in content {
+            let points = poly
+                .points
+                .iter()
+                .map(|&p| p - view * (plane.signed_distance_to(&p) / ndot));
+            // the polygons seen edge-on don't cover anything
+            if let Some(projected) = Polygon::from_points(points, poly.anchor.clone()) {
+                out.extend_from_slice(self.clipper.clip(projected));
+            }
+        }
+    }
+}
+
+impl<A, T, U> Default for ContextSplitter<A, T, U>
+where
+    A: Clone + fmt::Debug,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A, T, U> Splitter<A, T, U> for ContextSplitter<A, T, U>
+where
+    A: Clone + fmt::Debug,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn reset(&mut self) {
+        self.contexts.truncate(1);
+        let root = &mut self.contexts[0];
+        root.anchors.clear();
+        root.polygons.clear();
+        root.children.clear();
+        self.flattened.clear();
+    }
+
+    /// Add a polygon to the root context.
+    fn add(&mut self, polygon: Polygon<A, T, U>) {
+        self.add_to(ContextId::ROOT, polygon);
+    }
+
+    fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        self.flattened.clear();
+        self.flattened.resize_with(self.contexts.len(), Vec::new);
+
+        // the children are created after their parents,
+        // so they are flattened first
+        for index in (0..self.contexts.len()).rev() {
+            let mut out = Vec::new();
+            let sorted = self
+                .inner
+                .solve(&self.contexts[index].polygons, view)
+                .to_vec();
+            for fragment in &sorted {
+                let context = &self.contexts[index];
+                let anchor = &context.anchors[fragment.anchor];
+                out.push(fragment.map_anchor(|_| anchor.clone()));
+                if let Some(child) = context.children[fragment.anchor] {
+                    let content = core::mem::take(&mut self.flattened[child.index()]);
+                    self.place(&content, fragment, view, &mut out);
+                    self.flattened[child.index()] = content;
+                }
+            }
+            self.flattened[index] = out;
+        }
+
+        &self.flattened[0]
+    }
+}
@@ -0,0 +1,381 @@
+use crate::{EpsilonConfig, Polygon, Splitter};
+
+use euclid::{approxeq::ApproxEq, Point3D, UnknownUnit, Vector3D};
+use num_traits::Float;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Finest precision the coordinates are rounded to by `Dump::minimize`,
+/// in decimal digits after the point.
+const MAX_ROUND_DIGITS: i32 = 3;
+
+/// A recorded splitter input, which can be stored as JSON
+/// and replayed later to reproduce an ordering problem.
+#[derive(Serialize, Deserialize)]
+#[serde(bound(serialize = "A: Serialize, T: Serialize"))]
+#[serde(bound(deserialize = "A: Deserialize<'de>, T: Deserialize<'de>"))]
+pub struct Dump<A, T = f64, U = UnknownUnit> {
+    /// Polygons in the order they were added.
+    pub polygons: Vec<Polygon<A, T, U>>,
+    /// View vector used for sorting.
+    pub view: Vector3D<T, U>,
+    /// Sorted fragments produced out of the polygons, if recorded.
+    #[serde(default = "Vec::new")]
+    pub sorted: Vec<Polygon<A, T, U>>,
+}
+
+impl<A: Clone, T: Copy, U> Clone for Dump<A, T, U> {
+    fn clone(&self) -> Self {
+        Dump {
+            polygons: self.polygons.clone(),
+            view: self.view,
+            sorted: self.sorted.clone(),
+        }
+    }
+}
+
+impl<A: fmt::Debug, T: fmt::Debug, U> fmt::Debug for Dump<A, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Dump")
+            .field("polygons", &self.polygons)
+            .field("view", &self.view)
+            .field("sorted", &self.sorted)
+            .finish()
+    }
+}
+
+impl<A: PartialEq, T: PartialEq, U> PartialEq for Dump<A, T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.polygons == other.polygons && self.view == other.view && self.sorted == other.sorted
+    }
+}
+
+/// The differences between two dumps, as found by `Dump::diff`.
+///
+/// Polygons and fragments are matched by their anchors and points,
+/// regardless of which point comes first.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DumpDiff<A, T = f64> {
+    /// Whether the view vectors are different.
+    pub view_changed: bool,
+    /// Indices of the input polygons only found in the first dump.
+    pub removed_polygons: Vec<usize>,
+    /// Indices of the input polygons only found in the second dump.
+    pub added_polygons: Vec<usize>,
+    /// Indices of the sorted fragments only found in the first dump.
+    pub removed_fragments: Vec<usize>,
+    /// Indices of the sorted fragments only found in the second dump.
+    pub added_fragments: Vec<usize>,
+    /// Indices of the fragments found in both dumps, in the first one
+    /// and in the second one, whose place in the order of the matching
+    /// fragments is different.
+    pub moved_fragments: Vec<(usize, usize)>,
+    /// Anchors whose fragments cover a different area, along with
+    /// the area in the first dump and in the second one.
+    pub coverage: Vec<(A, T, T)>,
+}
+
+impl<A, T> DumpDiff<A, T> {
+    /// Check if no differences were found.
+    pub fn is_empty(&self) -> bool {
+        !self.view_changed
+            && self.removed_polygons.is_empty()
+            && self.added_polygons.is_empty()
+            && self.removed_fragments.is_empty()
+            && self.added_fragments.is_empty()
+            && self.moved_fragments.is_empty()
+            && self.coverage.is_empty()
+    }
+}
+
+impl<A, T, U> Dump<A, T, U>
+where
+    A: Clone,
+    T: Copy,
+{
+    /// Create an empty dump for the given view vector.
+    pub fn new(view: Vector3D<T, U>) -> Self {
+        Dump {
+            polygons: Vec::new(),
+            view,
+            sorted: Vec::new(),
+        }
+    }
+
+    /// Record a polygon being added.
+    pub fn add(&mut self, polygon: &Polygon<A, T, U>) {
+        self.polygons.push(polygon.clone());
+    }
+
+    /// Record the sorted fragments, replacing the previously recorded ones.
+    pub fn set_sorted(&mut self, sorted: &[Polygon<A, T, U>]) {
+        self.sorted = sorted.to_vec();
+    }
+
+    /// Run the recorded input through a splitter, returning the sorted polygons.
+    pub fn replay<S: Splitter<A, T, U>>(&self, splitter: &mut S) -> Vec<Polygon<A, T, U>> {
+        splitter.solve(&self.polygons, self.view).to_vec()
+    }
+}
+
+impl<A, T, U> Dump<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    /// Shrink a failing dump to a smaller one that still fails, i.e. for which
+    /// `fails` returns true, in order to make the problem easier to look into.
+    ///
+    /// The polygons are removed first, in chunks getting smaller as
+    /// in delta debugging. Then the point coordinates of the remaining
+    /// polygons are rounded to as few decimal digits as possible.
+    /// Every change is kept only if the dump still fails.
+    pub fn minimize<F>(&self, mut fails: F) -> Self
+    where
+        F: FnMut(&Self) -> bool,
+    {
+        let mut dump = self.clone();
+        // the input is going to change, so the output is no longer valid
+        dump.sorted.clear();
+        let mut chunks = 2;
+        while !dump.polygons.is_empty() {
+            let len = dump.polygons.len();
+            let chunk_len = len.div_ceil(chunks);
+            let reduced = (0..len).step_by(chunk_len).find_map(|start| {
+                let end = (start + chunk_len).min(len);
+                let candidate = Dump {
+                    polygons: dump.polygons[..start]
+                        .iter()
+                        .chain(&dump.polygons[end..])
+                        .cloned()
+                        .collect(),
+                    view: dump.view,
+                    sorted: Vec::new(),
+                };
+                if fails(&candidate) {
+                    Some(candidate)
+                } else {
+                    None
+                }
+            });
+            match reduced {
+                Some(candidate) => {
+                    dump = candidate;
+                    chunks = (chunks - 1).max(2);
+                }
+                None if chunk_len == 1 => break,
+                None => chunks = (chunks * 2).min(len),
+            }
+        }
+
+        let ten = T::from(10).unwrap();
+        for i in 0..dump.polygons.len() {
+            for digits in 0..=MAX_ROUND_DIGITS {
+                let scale = ten.powi(digits);
+                let polygon = &dump.polygons[i];
+                let points = polygon
+                    .points
+                    .iter()
+                    .map(|p| (p.to_vector() * scale).map(|c| c.round()).to_point() / scale);
+                let rounded = match Polygon::from_points(points, polygon.anchor.clone()) {
+                    Some(rounded) if rounded.is_valid() && rounded.points != polygon.points => {
+                        rounded
+                    }
+                    _ => continue,
+                };
+                let previous = core::mem::replace(&mut dump.polygons[i], rounded);
+                if fails(&dump) {
+                    break;
+                }
+                dump.polygons[i] = previous;
+            }
+        }
+        dump
+    }
+}
+
+impl<A, T, U> Dump<A, T, U>
+where
+    A: Clone + PartialEq,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    /// Compare the dump with another one, e.g. recorded with a different
+    /// version of the crate, to find the changes in the sorted output.
+    pub fn diff(&self, other: &Self) -> DumpDiff<A, T> {
+        self.diff_with_epsilons(other, &EpsilonConfig::default())
+    }
+
+    /// Compare the dump with another one, using custom thresholds.
+    /// The points and the relative coverage are compared
+    /// with the coplanarity threshold.
+    pub fn diff_with_epsilons(&self, other: &Self, epsilons: &EpsilonConfig<T>) -> DumpDiff<A, T> {
+        let eps = epsilons.coplanarity;
+        let (removed_polygons, added_polygons, _) =
+            match_polygons(&self.polygons, &other.polygons, eps);
+        let (removed_fragments, added_fragments, pairs) =
+            match_polygons(&self.sorted, &other.sorted, eps);
+
+        // the pairs are in the order of the first dump, and the longest run
+        // of them also in the order of the second dump is considered in place
+        let in_place = longest_increasing(&pairs.iter().map(|&(_, j)| j).collect::<Vec<_>>());
+        let moved_fragments = pairs
+            .iter()
+            .enumerate()
+            .filter(|&(k, _)| !in_place[k])
+            .map(|(_, &pair)| pair)
+            .collect();
+
+        let mut coverage: Vec<(A, T, T)> = Vec::new();
+        for (poly, is_first) in self
+            .sorted
+            .iter()
+            .map(|p| (p, true))
+            .chain(other.sorted.iter().map(|p| (p, false)))
+        {
+            let index = match coverage.iter().position(|entry| entry.0 == poly.anchor) {
+                Some(index) => index,
+                None => {
+                    coverage.push((poly.anchor.clone(), T::zero(), T::zero()));
+                    coverage.len() - 1
+                }
+            };
+            let entry = &mut coverage[index];
+            if is_first {
+                entry.1 = entry.1 + poly.area();
+            } else {
+                entry.2 = entry.2 + poly.area();
+            }
+        }
+        coverage.retain(|&(_, a, b)| (a - b).abs() > eps * a.max(b).max(T::one()));
+
+        DumpDiff {
+            view_changed: !self.view.approx_eq_eps(&other.view, &Vector3D::splat(eps)),
+            removed_polygons,
+            added_polygons,
+            removed_fragments,
+            added_fragments,
+            moved_fragments,
+            coverage,
+        }
+    }
+}
+
+/// Match the polygons of the first list with the ones of the second list.
+///
+/// Returns the indices of the unmatched polygons in each list,
+/// and the pairs of the matched indices, in the order of the first list.
+fn match_polygons<A, T, U>(
+    first: &[Polygon<A, T, U>],
+    second: &[Polygon<A, T, U>],
+    eps: T,
+) -> (Vec<usize>, Vec<usize>, Vec<(usize, usize)>)
+where
+    A: PartialEq,
+    T: Float + ApproxEq<T>,
+{
+    let mut is_matched = vec![false; second.len()];
+    let mut unmatched = Vec::new();
+    let mut pairs = Vec::new();
+    for (i, poly) in first.iter().enumerate() {
+        let found = second
+            .iter()
+            .enumerate()
+            .position(|(j, other)| !is_matched[j] && same_polygon(poly, other, eps));
+        match found {
+            Some(j) => {
+                is_matched[j] = true;
+                pairs.push((i, j));
+            }
+            None => unmatched.push(i),
+        }
+    }
+    let extra = (0..second.len()).filter(|&j| !is_matched[j]).collect();
+    (unmatched, extra, pairs)
+}
+
+/// Find the longest increasing subsequence of the values,
+/// returning whether each of them is a part of it.
+fn longest_increasing(values: &[usize]) -> Vec<bool> {
+    // the positions of the smallest last values of the subsequences of each length
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous = vec![None; values.len()];
+    for (k, &value) in values.iter().enumerate() {
+        let length = tails.partition_point(|&t| values[t] < value);
+        previous[k] = length.checked_sub(1).map(|l| tails[l]);
+        if length == tails.len() {
+            tails.push(k);
+        } else {
+            tails[length] = k;
+        }
+    }
+    let mut result = vec![false; values.len()];
+    let mut current = tails.last().cloned();
+    while let Some(k) = current {
+        result[k] = true;
+        current = previous[k];
+    }
+    result
+}
+
+/// Check if the polygons have the same anchor and the same points,
+/// starting from any of them.
+fn same_polygon<A: PartialEq, T: Float + ApproxEq<T>, U>(
+    a: &Polygon<A, T, U>,
+    b: &Polygon<A, T, U>,
+    eps: T,
+) -> bool {
+    let count = a.points.len();
+    if a.anchor != b.anchor || count != b.points.len() {
+        return false;
+    }
+    let eps = Point3D::splat(eps);
+    (0..count).any(|shift| {
+        (0..count).all(|i| a.points[i].approx_eq_eps(&b.points[(i + shift) % count], &eps))
+    })
+}
+
+impl<A, T, U> Dump<A, T, U>
+where
+    A: Serialize + DeserializeOwned,
+    T: Serialize + DeserializeOwned,
+{
+    /// Load a dump stored as JSON.
+    pub fn from_reader<R: io::Read>(reader: R) -> serde_json::Result<Self> {
+        serde_json::from_reader(reader)
+    }
+
+    /// Store the dump as JSON.
+    pub fn to_writer<W: io::Write>(&self, writer: W) -> serde_json::Result<()> {
+        serde_json::to_writer(writer, self)
+    }
+
+    /// Load all the dumps stored as JSON in the directory, i.e. the files
+    /// with the "json" extension, along with their paths, sorted by the path.
+    pub fn load_dir<P: AsRef<Path>>(dir: P) -> io::Result<Vec<(PathBuf, Self)>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "json") {
+                paths.push(path);
+            }
+        }
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|path| {
+                let file = io::BufReader::new(fs::File::open(&path)?);
+                let dump = Self::from_reader(file).map_err(|e| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{}: {}", path.display(), e),
+                    )
+                })?;
+                Ok((path, dump))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,261 @@
+//! glTF 2.0 export, which can be opened in any glTF viewer.
+//!
+//! Every input polygon becomes a translucent gray mesh, every sorted fragment
+//! a mesh colored from blue (drawn first) to red (drawn last), and the node
+//! planes of a BSP tree, if requested, translucent yellow squares.
+//! The geometry is embedded into the file, so it's self-contained.
+
+use super::plane_square;
+use crate::{BspSplitter, Polygon};
+
+use euclid::{approxeq::ApproxEq, default::Point3D};
+use num_traits::Float;
+
+use std::io::{self, Write};
+
+/// Component type of the vertex positions.
+const FLOAT: u32 = 5126;
+/// Component type of the vertex indices.
+const UNSIGNED_INT: u32 = 5125;
+/// Buffer view target of the vertex positions.
+const ARRAY_BUFFER: u32 = 34962;
+/// Buffer view target of the vertex indices.
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+/// A glTF scene to be written out.
+pub struct GltfExport<'a, A, T = f64> {
+    input: &'a [Polygon<A, T>],
+    sorted: &'a [Polygon<A, T>],
+    planes: Vec<[Point3D<T>; 4]>,
+}
+
+/// A mesh of a single convex face, with its place in the buffer.
+struct Mesh {
+    name: String,
+    material: usize,
+    vertex_offset: usize,
+    vertex_count: usize,
+    index_offset: usize,
+    index_count: usize,
+    min: [f32; 3],
+    max: [f32; 3],
+}
+
+impl<'a, A, T> GltfExport<'a, A, T>
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    /// Create a new export of the input polygons and the sorted fragments.
+    pub fn new(input: &'a [Polygon<A, T>], sorted: &'a [Polygon<A, T>]) -> Self {
+        GltfExport {
+            input,
+            sorted,
+            planes: Vec::new(),
+        }
+    }
+
+    /// Also export the node planes of a BSP tree, as squares
+    /// of the given half-size around the node polygons.
+    pub fn with_tree_planes(mut self, splitter: &BspSplitter<A, T>, extent: T) -> Self {
+        self.planes = splitter
+            .node_polygons()
+            .map(|poly| plane_square(poly, extent))
+            .collect();
+        self
+    }
+
+    /// Write the scene as a glTF JSON file into `out`.
+    pub fn write(&self, out: &mut dyn Write) -> io::Result<()> {
+        let faces = self
+            .input
+            .iter()
+            .enumerate()
+            .map(|(i, poly)| (format!("input_{}", i), 0, &poly.points[..]))
+            .chain(
+                self.planes
+                    .iter()
+                    .enumerate()
+                    .map(|(i, square)| (format!("plane_{}", i), 1, &square[..])),
+            )
+            .chain(
+                self.sorted
+                    .iter()
+                    .enumerate()
+                    .map(|(i, poly)| (format!("fragment_{}", i), 2 + i, &poly.points[..])),
+            );
+
+        let mut buffer = Vec::new();
+        let mut meshes = Vec::new();
+        for (name, material, points) in faces {
+            meshes.push(add_face(&mut buffer, name, material, points));
+        }
+
+        write!(
+            out,
+            r#"{{"asset":{{"version":"2.0","generator":"plane-split"}}"#
+        )?;
+        write!(out, r#","scene":0,"scenes":[{{"nodes":["#)?;
+        write_list(out, 0..meshes.len(), |out, i| write!(out, "{}", i))?;
+        write!(out, "]}}],\"nodes\":[")?;
+        write_list(out, meshes.iter().enumerate(), |out, (i, mesh)| {
+            write!(out, r#"{{"mesh":{},"name":"{}"}}"#, i, mesh.name)
+        })?;
+        write!(out, "],\"meshes\":[")?;
+        write_list(out, meshes.iter().enumerate(), |out, (i, mesh)| {
+            write!(
+                out,
+                r#"{{"primitives":[{{"attributes":{{"POSITION":{}}},"indices":{},"material":{}}}]}}"#,
+                2 * i,
+                2 * i + 1,
+                mesh.material,
+            )
+        })?;
+
+        write!(out, "],\"materials\":[")?;
+        write_material(out, "input", [0.5, 0.5, 0.5, 0.3])?;
+        write!(out, ",")?;
+        write_material(out, "plane", [1.0, 1.0, 0.0, 0.2])?;
+        let count = self.sorted.len().max(2) - 1;
+        for i in 0..self.sorted.len() {
+            let ratio = i as f64 / count as f64;
+            write!(out, ",")?;
+            write_material(out, &format!("order_{}", i), [ratio, 0.0, 1.0 - ratio, 1.0])?;
+        }
+
+        write!(out, "],\"accessors\":[")?;
+        write_list(out, meshes.iter().enumerate(), |out, (i, mesh)| {
+            write!(
+                out,
+                r#"{{"bufferView":{},"componentType":{},"count":{},"type":"VEC3","min":[{},{},{}],"max":[{},{},{}]}},"#,
+                2 * i,
+                FLOAT,
+                mesh.vertex_count,
+                mesh.min[0],
+                mesh.min[1],
+                mesh.min[2],
+                mesh.max[0],
+                mesh.max[1],
+                mesh.max[2],
+            )?;
+            write!(
+                out,
+                r#"{{"bufferView":{},"componentType":{},"count":{},"type":"SCALAR"}}"#,
+                2 * i + 1,
+                UNSIGNED_INT,
+                mesh.index_count,
+            )
+        })?;
+        write!(out, "],\"bufferViews\":[")?;
+        write_list(out, meshes.iter(), |out, mesh| {
+            write!(
+                out,
+                r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}},"#,
+                mesh.vertex_offset,
+                mesh.index_offset - mesh.vertex_offset,
+                ARRAY_BUFFER,
+            )?;
+            write!(
+                out,
+                r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+                mesh.index_offset,
+                4 * mesh.index_count,
+                ELEMENT_ARRAY_BUFFER,
+            )
+        })?;
+        write!(
+            out,
+            r#"],"buffers":[{{"byteLength":{},"uri":"data:application/octet-stream;base64,"#,
+            buffer.len(),
+        )?;
+        write_base64(out, &buffer)?;
+        writeln!(out, "\"}}]}}")
+    }
+}
+
+/// Append the vertex positions and the triangle fan indices of a convex
+/// face to the buffer, which is kept 4-byte aligned.
+fn add_face<T: Float>(
+    buffer: &mut Vec<u8>,
+    name: String,
+    material: usize,
+    points: &[Point3D<T>],
+) -> Mesh {
+    let vertex_offset = buffer.len();
+    let mut min = [f32::INFINITY; 3];
+    let mut max = [f32::NEG_INFINITY; 3];
+    for p in points {
+        let p = p.to_f32();
+        for (k, &c) in [p.x, p.y, p.z].iter().enumerate() {
+            buffer.extend_from_slice(&c.to_le_bytes());
+            min[k] = min[k].min(c);
+            max[k] = max[k].max(c);
+        }
+    }
+    let index_offset = buffer.len();
+    let mut index_count = 0;
+    for i in 1..points.len().saturating_sub(1) {
+        for &index in &[0, i, i + 1] {
+            buffer.extend_from_slice(&(index as u32).to_le_bytes());
+            index_count += 1;
+        }
+    }
+    Mesh {
+        name,
+        material,
+        vertex_offset,
+        vertex_count: points.len(),
+        index_offset,
+        index_count,
+        min,
+        max,
+    }
+}
+
+/// Write the items separated by commas.
+fn write_list<I: Iterator>(
+    out: &mut dyn Write,
+    items: I,
+    mut write_item: impl FnMut(&mut dyn Write, I::Item) -> io::Result<()>,
+) -> io::Result<()> {
+    for (i, item) in items.enumerate() {
+        if i != 0 {
+            write!(out, ",")?;
+        }
+        write_item(out, item)?;
+    }
+    Ok(())
+}
+
+/// Write a double-sided material of the given color, translucent if the alpha is below 1.
+fn write_material(out: &mut dyn Write, name: &str, color: [f64; 4]) -> io::Result<()> {
+    write!(
+        out,
+        r#"{{"name":"{}","pbrMetallicRoughness":{{"baseColorFactor":[{},{},{},{}],"metallicFactor":0}},"alphaMode":"{}","doubleSided":true}}"#,
+        name,
+        color[0],
+        color[1],
+        color[2],
+        color[3],
+        if color[3] < 1.0 { "BLEND" } else { "OPAQUE" },
+    )
+}
+
+/// Write the data encoded as base64, with padding.
+fn write_base64(out: &mut dyn Write, data: &[u8]) -> io::Result<()> {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let bits = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        let mut encoded = [b'='; 4];
+        for (k, c) in encoded.iter_mut().enumerate().take(chunk.len() + 1) {
+            *c = ALPHABET[(bits >> (18 - 6 * k) & 0x3f) as usize];
+        }
+        out.write_all(&encoded)?;
+    }
+    Ok(())
+}
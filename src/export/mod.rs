@@ -0,0 +1,41 @@
+//! Exporting the splitter input and output for visual inspection.
+
+use crate::Polygon;
+
+use euclid::{
+    approxeq::ApproxEq,
+    default::{Point3D, Vector3D},
+};
+use num_traits::Float;
+
+pub mod gltf;
+pub mod obj;
+mod svg;
+
+pub use self::svg::svg;
+
+/// Build a square lying on the polygon plane, centered around the polygon.
+fn plane_square<A, T: Float + ApproxEq<T>>(poly: &Polygon<A, T>, extent: T) -> [Point3D<T>; 4] {
+    let normal = poly.plane.normal;
+    let count = T::from(poly.points.len()).unwrap();
+    let sum = poly
+        .points
+        .iter()
+        .fold(Vector3D::zero(), |sum, p| sum + p.to_vector());
+    let center = (sum / count).to_point();
+    let center = center - normal * poly.plane.signed_distance_to(&center);
+
+    let axis = if normal.x.abs() < T::from(0.5).unwrap() {
+        Vector3D::new(T::one(), T::zero(), T::zero())
+    } else {
+        Vector3D::new(T::zero(), T::one(), T::zero())
+    };
+    let u = normal.cross(axis).normalize() * extent;
+    let v = normal.cross(u);
+    [
+        center - u - v,
+        center + u - v,
+        center + u + v,
+        center - u + v,
+    ]
+}
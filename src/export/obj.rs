@@ -0,0 +1,105 @@
+//! Wavefront OBJ export, which can be opened in Blender and other 3D editors.
+//!
+//! The input polygons are written as a single translucent group, followed by
+//! a group per sorted fragment, colored from blue (drawn first) to red (drawn last).
+
+use super::plane_square;
+use crate::{BspSplitter, Polygon};
+
+use euclid::{approxeq::ApproxEq, default::Point3D};
+use num_traits::Float;
+
+use std::io::{self, Write};
+
+/// An OBJ scene to be written out.
+pub struct ObjExport<'a, A, T = f64> {
+    input: &'a [Polygon<A, T>],
+    sorted: &'a [Polygon<A, T>],
+    planes: Vec<[Point3D<T>; 4]>,
+}
+
+impl<'a, A, T> ObjExport<'a, A, T>
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    /// Create a new export of the input polygons and the sorted fragments.
+    pub fn new(input: &'a [Polygon<A, T>], sorted: &'a [Polygon<A, T>]) -> Self {
+        ObjExport {
+            input,
+            sorted,
+            planes: Vec::new(),
+        }
+    }
+
+    /// Also export the node planes of a BSP tree, as squares
+    /// of the given half-size around the node polygons.
+    pub fn with_tree_planes(mut self, splitter: &BspSplitter<A, T>, extent: T) -> Self {
+        self.planes = splitter
+            .node_polygons()
+            .map(|poly| plane_square(poly, extent))
+            .collect();
+        self
+    }
+
+    /// Write the scene into `obj`, and its materials into `mtl`.
+    /// The `mtl_name` is the file name of the material library,
+    /// as it is referenced from the OBJ file.
+    pub fn write(
+        &self,
+        obj: &mut dyn Write,
+        mtl: &mut dyn Write,
+        mtl_name: &str,
+    ) -> io::Result<()> {
+        writeln!(mtl, "newmtl input")?;
+        writeln!(mtl, "Kd 0.5 0.5 0.5")?;
+        writeln!(mtl, "d 0.3")?;
+        writeln!(mtl, "newmtl plane")?;
+        writeln!(mtl, "Kd 1.0 1.0 0.0")?;
+        writeln!(mtl, "d 0.2")?;
+        let count = self.sorted.len().max(2) - 1;
+        for i in 0..self.sorted.len() {
+            let ratio = i as f64 / count as f64;
+            writeln!(mtl, "newmtl order_{}", i)?;
+            writeln!(mtl, "Kd {} 0.0 {}", ratio, 1.0 - ratio)?;
+        }
+
+        writeln!(obj, "mtllib {}", mtl_name)?;
+        let mut base = 1;
+        writeln!(obj, "g input")?;
+        writeln!(obj, "usemtl input")?;
+        for poly in self.input {
+            base = write_face(obj, base, &poly.points)?;
+        }
+        for (i, poly) in self.sorted.iter().enumerate() {
+            writeln!(obj, "g fragment_{}", i)?;
+            writeln!(obj, "usemtl order_{}", i)?;
+            base = write_face(obj, base, &poly.points)?;
+        }
+        for (i, square) in self.planes.iter().enumerate() {
+            writeln!(obj, "g plane_{}", i)?;
+            writeln!(obj, "usemtl plane")?;
+            base = write_face(obj, base, square)?;
+        }
+        Ok(())
+    }
+}
+
+/// Write the vertices and the face of a polygon, given the index of its first vertex.
+/// Returns the index of the next vertex.
+fn write_face<T: Float>(
+    obj: &mut dyn Write,
+    base: usize,
+    points: &[Point3D<T>],
+) -> io::Result<usize> {
+    for p in points {
+        let p = p.to_f64();
+        writeln!(obj, "v {} {} {}", p.x, p.y, p.z)?;
+    }
+    write!(obj, "f")?;
+    for i in 0..points.len() {
+        write!(obj, " {}", base + i)?;
+    }
+    writeln!(obj)?;
+    Ok(base + points.len())
+}
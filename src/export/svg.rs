@@ -0,0 +1,149 @@
+//! SVG export of the sorted fragments, as seen along the view vector.
+//!
+//! The fragments are filled in the order they are drawn, colored from blue
+//! (drawn first) to red (drawn last), with the edges introduced by cutting
+//! dashed, and the anchor written in the middle of each fragment.
+
+use crate::{EdgeOrigin, Polygon};
+
+use euclid::{
+    approxeq::ApproxEq,
+    default::{Point2D, Point3D, Vector2D, Vector3D},
+};
+use num_traits::Float;
+
+use std::{
+    fmt,
+    io::{self, Write},
+};
+
+/// Margin around the fragments, relative to the size of the picture.
+const MARGIN: f64 = 0.05;
+
+/// Write the sorted fragments as an SVG picture into `writer`, projecting
+/// them along the `view` vector, which points towards the viewer.
+/// The fragments are expected to be sorted back to front.
+pub fn svg<A, T>(
+    fragments: &[Polygon<A, T>],
+    view: Vector3D<T>,
+    writer: &mut dyn Write,
+) -> io::Result<()>
+where
+    A: Clone + fmt::Display,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let (right, up) = screen_axes(view.to_f64().normalize());
+    let project = |p: &Point3D<T>| {
+        let p = p.to_f64().to_vector();
+        // the SVG vertical axis points down
+        Point2D::new(p.dot(right), -p.dot(up))
+    };
+    let outlines: Vec<Vec<Point2D<f64>>> = fragments
+        .iter()
+        .map(|poly| poly.points.iter().map(project).collect())
+        .collect();
+
+    let (mut min, mut max) = (
+        Point2D::splat(f64::INFINITY),
+        Point2D::splat(-f64::INFINITY),
+    );
+    for p in outlines.iter().flatten() {
+        min = min.min(*p);
+        max = max.max(*p);
+    }
+    if outlines.iter().all(|outline| outline.is_empty()) {
+        min = Point2D::zero();
+        max = Point2D::new(1.0, 1.0);
+    }
+    let size = (max - min).max(Vector2D::splat(f64::EPSILON));
+    let margin = size.x.max(size.y) * MARGIN;
+    // text and lines scale with the picture
+    let unit = size.x.max(size.y) / 200.0;
+
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}">"#,
+        min.x - margin,
+        min.y - margin,
+        size.x + 2.0 * margin,
+        size.y + 2.0 * margin,
+    )?;
+    let count = fragments.len().max(2) - 1;
+    for (i, (poly, outline)) in fragments.iter().zip(&outlines).enumerate() {
+        let ratio = i as f64 / count as f64;
+        let red = (ratio * 255.0).round();
+        let blue = 255.0 - red;
+        write!(writer, r#"<polygon points=""#)?;
+        for p in outline {
+            write!(writer, "{},{} ", p.x, p.y)?;
+        }
+        writeln!(
+            writer,
+            r#"" fill="rgb({},0,{})" fill-opacity="0.8" stroke="black" stroke-width="{}"/>"#,
+            red, blue, unit,
+        )?;
+        for j in 0..outline.len() {
+            if poly.edge_origin(j) == EdgeOrigin::Cut {
+                let (a, b) = (outline[j], outline[(j + 1) % outline.len()]);
+                writeln!(
+                    writer,
+                    r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="white" stroke-width="{}" stroke-dasharray="{}"/>"#,
+                    a.x,
+                    a.y,
+                    b.x,
+                    b.y,
+                    unit,
+                    4.0 * unit,
+                )?;
+            }
+        }
+    }
+    for (poly, outline) in fragments.iter().zip(&outlines) {
+        if outline.is_empty() {
+            continue;
+        }
+        let sum = outline
+            .iter()
+            .fold(Vector2D::zero(), |sum, p| sum + p.to_vector());
+        let center = sum / outline.len() as f64;
+        writeln!(
+            writer,
+            r#"<text x="{}" y="{}" font-size="{}" text-anchor="middle">{}</text>"#,
+            center.x,
+            center.y,
+            8.0 * unit,
+            Escaped(&poly.anchor),
+        )?;
+    }
+    writeln!(writer, "</svg>")
+}
+
+/// Get the directions pointing right and up on the screen, when looking
+/// against the unit `view` vector. The up direction is the Y axis projected
+/// on the screen, unless the view is close to it.
+fn screen_axes(view: Vector3D<f64>) -> (Vector3D<f64>, Vector3D<f64>) {
+    let up = if view.y.abs() > 0.9 {
+        Vector3D::new(0.0, 0.0, -view.y.signum())
+    } else {
+        Vector3D::new(0.0, 1.0, 0.0)
+    };
+    let right = up.cross(view).normalize();
+    (right, view.cross(right))
+}
+
+/// Writes the value with the characters special to XML escaped.
+struct Escaped<'a, A>(&'a A);
+
+impl<A: fmt::Display> fmt::Display for Escaped<'_, A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for c in self.0.to_string().chars() {
+            match c {
+                '<' => f.write_str("&lt;")?,
+                '>' => f.write_str("&gt;")?,
+                '&' => f.write_str("&amp;")?,
+                _ => write!(f, "{}", c)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{Plane, Polygon};
+
+use ::glam::{DVec3, Vec3, Vec3A};
+use euclid::default::{Point3D, Vector3D};
+
+/// A `glam` vector type that polygons with the matching
+/// scalar type can be built from and exposed as.
+pub trait GlamVector: Copy {
+    /// The type of the vector components.
+    type Scalar;
+
+    /// Convert the vector into a point.
+    fn to_point(self) -> Point3D<Self::Scalar>;
+
+    /// Convert a vector into this type.
+    fn from_vector(vector: Vector3D<Self::Scalar>) -> Self;
+}
+
+impl GlamVector for Vec3 {
+    type Scalar = f32;
+
+    fn to_point(self) -> Point3D<f32> {
+        Point3D::new(self.x, self.y, self.z)
+    }
+
+    fn from_vector(vector: Vector3D<f32>) -> Self {
+        Vec3::new(vector.x, vector.y, vector.z)
+    }
+}
+
+impl GlamVector for Vec3A {
+    type Scalar = f32;
+
+    fn to_point(self) -> Point3D<f32> {
+        Point3D::new(self.x, self.y, self.z)
+    }
+
+    fn from_vector(vector: Vector3D<f32>) -> Self {
+        Vec3A::new(vector.x, vector.y, vector.z)
+    }
+}
+
+impl GlamVector for DVec3 {
+    type Scalar = f64;
+
+    fn to_point(self) -> Point3D<f64> {
+        Point3D::new(self.x, self.y, self.z)
+    }
+
+    fn from_vector(vector: Vector3D<f64>) -> Self {
+        DVec3::new(vector.x, vector.y, vector.z)
+    }
+}
+
+impl<A, T> Polygon<A, T>
+where
+    A: Clone,
+    T: num_traits::Float + euclid::approxeq::ApproxEq<T> + core::fmt::Debug,
+{
+    /// Construct a polygon from `glam` vectors, like `from_points` does.
+    pub fn from_glam_points<V, I>(points: I, anchor: A) -> Option<Self>
+    where
+        V: GlamVector<Scalar = T>,
+        I: IntoIterator<Item = V>,
+    {
+        Self::from_points(points.into_iter().map(V::to_point), anchor)
+    }
+
+    /// Get the points as `glam` vectors.
+    pub fn glam_points<V>(&self) -> impl Iterator<Item = V> + '_
+    where
+        V: GlamVector<Scalar = T>,
+    {
+        self.points.iter().map(|p| V::from_vector(p.to_vector()))
+    }
+}
+
+impl<T: Copy> Plane<T> {
+    /// Get the normal as a `glam` vector.
+    pub fn glam_normal<V>(&self) -> V
+    where
+        V: GlamVector<Scalar = T>,
+    {
+        V::from_vector(self.normal)
+    }
+}
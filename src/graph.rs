@@ -0,0 +1,233 @@
+use crate::{
+    naive::{is_behind, topological_sort},
+    EpsilonConfig, PlaneCut, Polygon, Splitter,
+};
+
+use euclid::{approxeq::ApproxEq, UnknownUnit, Vector3D};
+use num_traits::Float;
+use smallvec::SmallVec;
+
+use alloc::{vec, vec::Vec};
+use core::fmt;
+
+/// A splitter ordering the polygons by their occlusion graph.
+///
+/// The graph of the polygons overlapping each other along the view is
+/// built and sorted topologically. Only the polygons intersecting each
+/// other, or forming a cycle in the graph, are split. This typically
+/// produces far fewer fragments than the BSP tree, which splits everything
+/// crossing a node plane, but the work needs to be redone for each view.
+pub struct GraphSplitter<A, T = f64, U = UnknownUnit> {
+    polygons: Vec<Polygon<A, T, U>>,
+    result: Vec<Polygon<A, T, U>>,
+    epsilons: EpsilonConfig<T>,
+}
+
+impl<A, T, U> GraphSplitter<A, T, U>
+where
+    T: Float + ApproxEq<T>,
+{
+    /// Create a new graph splitter.
+    pub fn new() -> Self {
+        Self::with_epsilons(EpsilonConfig::default())
+    }
+
+    /// Create a new graph splitter with custom thresholds.
+    pub fn with_epsilons(epsilons: EpsilonConfig<T>) -> Self {
+        GraphSplitter {
+            polygons: Vec::new(),
+            result: Vec::new(),
+            epsilons,
+        }
+    }
+}
+
+impl<A, T, U> Default for GraphSplitter<A, T, U>
+where
+    T: Float + ApproxEq<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A fragment with the index of its source polygon.
+type Fragment<A, T, U> = (usize, Polygon<A, T, U>);
+
+/// The occlusion graph of the fragments, which is updated in place
+/// when some of them get split.
+struct Graph<A, T, U> {
+    fragments: Vec<Fragment<A, T, U>>,
+    /// The fragments to be drawn after each of the fragments.
+    after: Vec<Vec<usize>>,
+    /// Whether the edges of each of the fragments are computed.
+    settled: Vec<bool>,
+    /// The fragments which edges are still to be computed.
+    queue: Vec<usize>,
+}
+
+impl<A, T, U> Graph<A, T, U> {
+    fn new(fragments: Vec<Fragment<A, T, U>>) -> Self {
+        let count = fragments.len();
+        Graph {
+            fragments,
+            after: vec![Vec::new(); count],
+            settled: vec![false; count],
+            queue: (0..count).rev().collect(),
+        }
+    }
+
+    /// Replace the fragment by the pieces it was split into. The first
+    /// piece takes its place, and the edges of all of them are queued
+    /// to be computed, while the rest of the graph is kept.
+    fn replace(&mut self, k: usize, pieces: SmallVec<[Polygon<A, T, U>; 2]>) {
+        self.after[k].clear();
+        for after in self.after.iter_mut() {
+            after.retain(|&i| i != k);
+        }
+        self.settled[k] = false;
+        self.queue.push(k);
+
+        let source = self.fragments[k].0;
+        let mut pieces = pieces.into_iter();
+        self.fragments[k].1 = pieces.next().unwrap();
+        for piece in pieces {
+            self.queue.push(self.fragments.len());
+            self.fragments.push((source, piece));
+            self.after.push(Vec::new());
+            self.settled.push(false);
+        }
+    }
+}
+
+impl<A, T, U> GraphSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    /// Cut the fragment by the plane of `cutter`, returning the pieces
+    /// if it actually ends up on both sides.
+    fn split(
+        &self,
+        cutter: &Polygon<A, T, U>,
+        fragment: &Polygon<A, T, U>,
+    ) -> Option<SmallVec<[Polygon<A, T, U>; 2]>> {
+        let mut front = SmallVec::new();
+        let mut back = SmallVec::new();
+        match cutter.cut_with_epsilons(fragment, &mut front, &mut back, &self.epsilons) {
+            PlaneCut::Cut if !front.is_empty() && !back.is_empty() => {
+                front.extend(back);
+                Some(front)
+            }
+            _ => None,
+        }
+    }
+
+    /// Compute the edges between the fragment and the settled ones.
+    /// If the fragment intersects one of them, it gets split instead, and
+    /// its pieces are queued to be connected in turn.
+    fn connect(&self, graph: &mut Graph<A, T, U>, k: usize, view: &Vector3D<T, U>) {
+        let epsilon = self.epsilons.coplanarity;
+        for s in 0..graph.fragments.len() {
+            if !graph.settled[s] {
+                continue;
+            }
+            let (a, b) = (&graph.fragments[s].1, &graph.fragments[k].1);
+            if !a.overlaps_along(b, view, epsilon) {
+                continue;
+            }
+            let is_behind = match is_behind(a, b, view, epsilon) {
+                Some(is_behind) => is_behind,
+                None => {
+                    if let Some(pieces) = self.split(a, b) {
+                        graph.replace(k, pieces);
+                        return;
+                    }
+                    // the coplanar polygons are drawn in the order they were added
+                    graph.fragments[s].0 <= graph.fragments[k].0
+                }
+            };
+            if is_behind {
+                graph.after[s].push(k);
+            } else {
+                graph.after[k].push(s);
+            }
+        }
+        graph.settled[k] = true;
+    }
+
+    /// Compute the edges of all the queued fragments.
+    fn settle(&self, graph: &mut Graph<A, T, U>, view: &Vector3D<T, U>) {
+        while let Some(k) = graph.queue.pop() {
+            self.connect(graph, k, view);
+        }
+    }
+
+    /// Find a cycle among the nodes that couldn't be ordered,
+    /// each of which has at least one predecessor among them.
+    fn find_cycle(after: &[Vec<usize>], remaining: &[usize]) -> Vec<usize> {
+        let mut path = vec![remaining[0]];
+        loop {
+            let last = *path.last().unwrap();
+            let prev = remaining
+                .iter()
+                .cloned()
+                .find(|&i| after[i].contains(&last))
+                .unwrap();
+            if let Some(start) = path.iter().position(|&i| i == prev) {
+                return path.split_off(start);
+            }
+            path.push(prev);
+        }
+    }
+}
+
+impl<A, T, U> Splitter<A, T, U> for GraphSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn reset(&mut self) {
+        self.polygons.clear();
+        self.result.clear();
+    }
+
+    fn add(&mut self, polygon: Polygon<A, T, U>) {
+        self.polygons.push(polygon);
+    }
+
+    fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        let mut graph = Graph::new(self.polygons.iter().cloned().enumerate().collect());
+        let order = loop {
+            self.settle(&mut graph, &view);
+            let (order, remaining) = topological_sort(&graph.after, false);
+            if remaining.is_empty() {
+                break order;
+            }
+
+            // split the fragments of a cycle by the plane of one of them
+            let cycle = Self::find_cycle(&graph.after, &remaining);
+            let cut = cycle.iter().find_map(|&cutter| {
+                cycle.iter().find_map(|&k| {
+                    if k == cutter {
+                        return None;
+                    }
+                    self.split(&graph.fragments[cutter].1, &graph.fragments[k].1)
+                        .map(|pieces| (k, pieces))
+                })
+            });
+            match cut {
+                Some((k, pieces)) => {
+                    log::debug!("\tSplitting fragment {} to break a cycle", k);
+                    graph.replace(k, pieces);
+                }
+                None => break topological_sort(&graph.after, true).0,
+            }
+        };
+
+        self.result.clear();
+        self.result
+            .extend(order.into_iter().map(|i| graph.fragments[i].1.clone()));
+        &self.result
+    }
+}
@@ -0,0 +1,83 @@
+use num_traits::Float;
+
+use core::{cmp::Ordering, ops};
+
+/// A range of values that is guaranteed to contain the exact result
+/// of a floating point computation.
+///
+/// Rust doesn't expose the rounding modes, so the bounds are pushed outwards
+/// after each operation by the largest possible rounding error instead.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Interval<T> {
+    pub lo: T,
+    pub hi: T,
+}
+
+impl<T: Float> Interval<T> {
+    /// Create an interval around a value known up to a few roundings.
+    pub fn around(value: T, roundings: T) -> Self {
+        let error = value.abs() * T::epsilon() * roundings;
+        Interval {
+            lo: value - error,
+            hi: value + error,
+        }
+        .widen()
+    }
+
+    /// Return the sign of all the values in the interval,
+    /// or `None` if it contains zero.
+    pub fn sign(&self) -> Option<Ordering> {
+        if self.lo > T::zero() {
+            Some(Ordering::Greater)
+        } else if self.hi < T::zero() {
+            Some(Ordering::Less)
+        } else {
+            None
+        }
+    }
+
+    fn widen(self) -> Self {
+        let ulp = |v: T| v.abs() * T::epsilon() + T::min_positive_value();
+        Interval {
+            lo: self.lo - ulp(self.lo),
+            hi: self.hi + ulp(self.hi),
+        }
+    }
+}
+
+impl<T: Float> From<T> for Interval<T> {
+    fn from(value: T) -> Self {
+        Interval {
+            lo: value,
+            hi: value,
+        }
+    }
+}
+
+impl<T: Float> ops::Add for Interval<T> {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Interval {
+            lo: self.lo + other.lo,
+            hi: self.hi + other.hi,
+        }
+        .widen()
+    }
+}
+
+impl<T: Float> ops::Mul for Interval<T> {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        let products = [
+            self.lo * other.lo,
+            self.lo * other.hi,
+            self.hi * other.lo,
+            self.hi * other.hi,
+        ];
+        Interval {
+            lo: products.iter().cloned().fold(T::infinity(), T::min),
+            hi: products.iter().cloned().fold(T::neg_infinity(), T::max),
+        }
+        .widen()
+    }
+}
@@ -4,51 +4,135 @@ Plane splitting.
 Uses [euclid](https://crates.io/crates/euclid) for the math basis.
 Introduces new geometrical primitives and associated logic.
 
-Automatically splits a given set of 4-point polygons into sub-polygons
+Automatically splits a given set of convex polygons into sub-polygons
 that don't intersect each other. This is useful for WebRender, to sort
 the resulting sub-polygons by depth and avoid transparency blending issues.
 */
 #![warn(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
 
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("either the `std` or the `libm` feature is required");
+
+extern crate alloc;
+
+#[cfg(feature = "arbitrary")]
+mod arbitrary_types;
+mod auto;
 mod bsp;
+mod cached;
+#[cfg(feature = "capi")]
+pub mod capi;
 mod clip;
+mod compare;
+mod context;
+#[cfg(feature = "dump")]
+mod dump;
+#[cfg(feature = "debug-export")]
+pub mod export;
+#[cfg(feature = "glam")]
+mod glam_types;
+mod graph;
+mod interval;
+mod naive;
+#[cfg(feature = "bytemuck")]
+mod pod;
 mod polygon;
+#[cfg(feature = "robust")]
+mod robust;
+#[cfg(feature = "bench-scenes")]
+pub mod scenes;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod tiled;
+mod tree;
+mod triangles;
+mod validate;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 
-pub use polygon::PlaneCut;
+pub use polygon::{PlaneCut, SplitError};
 
-use euclid::{
-    approxeq::ApproxEq,
-    default::{Point3D, Scale, Vector3D},
-};
+use euclid::{approxeq::ApproxEq, Box3D, Point3D, Scale, Transform3D, UnknownUnit, Vector3D};
+use num_traits::Float;
+use smallvec::{smallvec, SmallVec};
+
+use alloc::vec::Vec;
+use core::{borrow::Borrow, cmp::Ordering, fmt, ops};
 
-use std::ops;
+use self::interval::Interval;
 
-pub use self::bsp::BspSplitter;
-pub use self::clip::Clipper;
-pub use self::polygon::{Intersection, LineProjection, Polygon};
+pub use self::auto::AutoSplitter;
+pub use self::bsp::{
+    BspSnapshot, BspSplitter, FragmentId, FragmentInfo, FragmentKey, Heuristic, Hit, NodeView,
+    PlaneId, ProbeResult, ProfileEvent, Profiler, Sidedness, SortOptions, SortOrder, SplitStats,
+    TraversalOrder, Traverse,
+};
+pub use self::cached::{CachedSplitter, SceneHasher};
+pub use self::clip::{ClipRegion, Clipper, Frustum};
+pub use self::compare::{CompareSplitter, Divergence};
+pub use self::context::{ContextId, ContextSplitter};
+#[cfg(feature = "dump")]
+pub use self::dump::{Dump, DumpDiff};
+#[cfg(feature = "glam")]
+pub use self::glam_types::GlamVector;
+pub use self::graph::GraphSplitter;
+pub use self::naive::NaiveSplitter;
+#[cfg(feature = "bytemuck")]
+pub use self::pod::PodPolygon;
+pub use self::polygon::{EdgeOrigin, Intersection, LineProjection, Polygon, PolygonError, Winding};
+#[cfg(feature = "robust")]
+pub use self::robust::RobustSplitter;
+pub use self::tiled::TiledSplitter;
+pub use self::tree::{BspNode, BspPlane};
+pub use self::triangles::{FlatOutput, TriangleBuffer};
+pub use self::validate::{order_pair, validate, verify_order, OrderViolation, ValidationError};
 
-fn is_zero(value: f64) -> bool {
+fn is_zero<T>(value: T) -> bool
+where
+    T: Float + ApproxEq<T>,
+{
     //HACK: this is rough, but the original Epsilon is too strict
-    (value * value).approx_eq(&0.0)
+    (value * value).approx_eq(&T::zero())
 }
 
-fn is_zero_vec(vec: Vector3D<f64>) -> bool {
-    vec.dot(vec).approx_eq(&0.0)
+fn is_zero_vec<T, U>(vec: Vector3D<T, U>) -> bool
+where
+    T: Float + ApproxEq<T>,
+{
+    vec.dot(vec).approx_eq(&T::zero())
 }
 
 /// A generic line.
-#[derive(Debug)]
-pub struct Line {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "T: serde::Serialize")))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "T: serde::Deserialize<'de>"))
+)]
+pub struct Line<T = f64, U = UnknownUnit> {
     /// Arbitrary point on the line.
-    pub origin: Point3D<f64>,
+    pub origin: Point3D<T, U>,
     /// Normalized direction of the line.
-    pub dir: Vector3D<f64>,
+    pub dir: Vector3D<T, U>,
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Line<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Line")
+            .field("origin", &self.origin)
+            .field("dir", &self.dir)
+            .finish()
+    }
 }
 
-impl Line {
+impl<T, U> Line<T, U>
+where
+    T: Float + ApproxEq<T>,
+{
     /// Check if the line has consistent parameters.
     pub fn is_valid(&self) -> bool {
-        is_zero(self.dir.dot(self.dir) - 1.0)
+        is_zero(self.dir.dot(self.dir) - T::one())
     }
     /// Check if two lines match each other.
     pub fn matches(&self, other: &Self) -> bool {
@@ -56,9 +140,28 @@ impl Line {
         is_zero_vec(self.dir.cross(other.dir)) && is_zero_vec(self.dir.cross(diff))
     }
 
+    /// Get the point of the line closest to the given one.
+    pub fn project_point(&self, point: &Point3D<T, U>) -> Point3D<T, U> {
+        self.origin + self.dir * self.dir.dot(*point - self.origin)
+    }
+
+    /// Compute the distance from the line to a point.
+    pub fn distance_to_point(&self, point: &Point3D<T, U>) -> T {
+        (*point - self.project_point(point)).length()
+    }
+
+    /// Compute the point where the line crosses a plane.
+    /// Returns `None` if the line is parallel to the plane.
+    pub fn intersect_plane(&self, plane: &Plane<T, U>) -> Option<Point3D<T, U>> {
+        if plane.normal.dot(self.dir).abs() < T::approx_epsilon() {
+            return None;
+        }
+        Some(self.origin + self.dir * plane.distance_to_line(self))
+    }
+
     /// Intersect an edge given by the end points.
     /// Returns the fraction of the edge where the intersection occurs.
-    fn intersect_edge(&self, edge: ops::Range<Point3D<f64>>) -> Option<f64> {
+    fn intersect_edge(&self, edge: ops::Range<Point3D<T, U>>) -> Option<T> {
         let edge_vec = edge.end - edge.start;
         let origin_vec = self.origin - edge.start;
         // edge.start + edge_vec * t = r + k * d
@@ -68,7 +171,7 @@ impl Line {
         let pr = origin_vec - self.dir * self.dir.dot(origin_vec);
         let pb = edge_vec - self.dir * self.dir.dot(edge_vec);
         let denom = pb.dot(pb);
-        if denom.approx_eq(&0.0) {
+        if denom.approx_eq(&T::zero()) {
             None
         } else {
             Some(pr.dot(pb) / denom)
@@ -76,24 +179,184 @@ impl Line {
     }
 }
 
+/// A line segment between two points.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "T: serde::Serialize")))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "T: serde::Deserialize<'de>"))
+)]
+pub struct LineSegment<T = f64, U = UnknownUnit> {
+    /// Start point of the segment.
+    pub start: Point3D<T, U>,
+    /// End point of the segment.
+    pub end: Point3D<T, U>,
+}
+
+impl<T: Copy, U> Clone for LineSegment<T, U> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: Copy, U> Copy for LineSegment<T, U> {}
+
+impl<T: fmt::Debug, U> fmt::Debug for LineSegment<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("LineSegment")
+            .field("start", &self.start)
+            .field("end", &self.end)
+            .finish()
+    }
+}
+
+impl<T: PartialEq, U> PartialEq for LineSegment<T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.start == other.start && self.end == other.end
+    }
+}
+
+impl<T, U> LineSegment<T, U>
+where
+    T: Float + ApproxEq<T>,
+{
+    /// Get the infinite line containing the segment,
+    /// or `None` if the segment has no length.
+    pub fn line(&self) -> Option<Line<T, U>> {
+        let dir = self.end - self.start;
+        if is_zero_vec(dir) {
+            None
+        } else {
+            Some(Line {
+                origin: self.start,
+                dir: dir.normalize(),
+            })
+        }
+    }
+}
+
 /// An infinite plane in 3D space, defined by equation:
 /// dot(v, normal) + offset = 0
 /// When used for plane splitting, it's defining a hemisphere
 /// with equation "dot(v, normal) + offset > 0".
-#[derive(Debug, PartialEq)]
-pub struct Plane {
+///
+/// The unit `U` tells the `euclid` space the plane is in.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound(serialize = "T: serde::Serialize")))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "T: serde::Deserialize<'de>"))
+)]
+pub struct Plane<T = f64, U = UnknownUnit> {
     /// Normalized vector perpendicular to the plane.
-    pub normal: Vector3D<f64>,
+    pub normal: Vector3D<T, U>,
     /// Constant offset from the normal plane, specified in the
     /// direction opposite to the normal.
-    pub offset: f64,
+    pub offset: T,
 }
 
-impl Clone for Plane {
+impl<T: Copy, U> Clone for Plane<T, U> {
     fn clone(&self) -> Self {
         Plane {
-            normal: self.normal.clone(),
-            offset: self.offset.clone(),
+            normal: self.normal,
+            offset: self.offset,
+        }
+    }
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Plane<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Plane")
+            .field("normal", &self.normal)
+            .field("offset", &self.offset)
+            .finish()
+    }
+}
+
+impl<T: PartialEq, U> PartialEq for Plane<T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.normal == other.normal && self.offset == other.offset
+    }
+}
+
+impl<T: Copy, U> Plane<T, U> {
+    /// Mark the plane as being in the `UnknownUnit` space.
+    pub fn to_untyped(&self) -> Plane<T> {
+        self.cast_unit()
+    }
+
+    /// Mark an untyped plane as being in the space `U`.
+    pub fn from_untyped(plane: &Plane<T>) -> Self {
+        plane.cast_unit()
+    }
+
+    /// Change the space the plane is in, without changing the equation.
+    pub fn cast_unit<V>(&self) -> Plane<T, V> {
+        Plane {
+            normal: self.normal.cast_unit(),
+            offset: self.offset,
+        }
+    }
+}
+
+/// Shows the plane equation compactly, e.g. "n=(0,0,1), o=-5".
+impl<T: fmt::Display, U> fmt::Display for Plane<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let n = &self.normal;
+        write!(f, "n=({},{},{}), o={}", n.x, n.y, n.z, self.offset)
+    }
+}
+
+/// The relation of a polygon to a plane.
+pub enum Classification<T = f64, U = UnknownUnit> {
+    /// The polygon is in front of the plane, i.e. on the side
+    /// the normal points to.
+    Front,
+    /// The polygon is behind the plane.
+    Back,
+    /// The polygon lies on the plane.
+    Coplanar,
+    /// The polygon crosses the plane along the line.
+    Spanning(Line<T, U>),
+}
+
+impl<T: fmt::Debug, U> fmt::Debug for Classification<T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Classification::Front => f.write_str("Front"),
+            Classification::Back => f.write_str("Back"),
+            Classification::Coplanar => f.write_str("Coplanar"),
+            Classification::Spanning(ref line) => f.debug_tuple("Spanning").field(line).finish(),
+        }
+    }
+}
+
+/// Thresholds used when classifying and splitting polygons.
+///
+/// The defaults work well for scenes in the pixel space, but scenes
+/// with very large or very small coordinates may need to adjust them.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EpsilonConfig<T = f64> {
+    /// Maximum distance between two parallel planes for them
+    /// to be considered the same plane.
+    pub coplanarity: T,
+    /// Maximum squared length of the area vector of a polygon for it
+    /// to be considered empty, also used to weld nearby cut points.
+    pub degeneracy: T,
+    /// Minimum sine of the angle between two planes for them
+    /// to be considered intersecting rather than parallel.
+    pub intersection: T,
+}
+
+impl<T> Default for EpsilonConfig<T>
+where
+    T: Float + ApproxEq<T>,
+{
+    fn default() -> Self {
+        EpsilonConfig {
+            coplanarity: T::approx_epsilon().sqrt(),
+            degeneracy: T::epsilon(),
+            intersection: T::approx_epsilon(),
         }
     }
 }
@@ -103,21 +366,24 @@ impl Clone for Plane {
 #[derive(Clone, Debug, Hash, PartialEq, PartialOrd)]
 pub struct NegativeHemisphereError;
 
-impl Plane {
+impl<T, U> Plane<T, U>
+where
+    T: Float + ApproxEq<T>,
+{
     /// Construct a new plane from unnormalized equation.
     pub fn from_unnormalized(
-        normal: Vector3D<f64>,
-        offset: f64,
+        normal: Vector3D<T, U>,
+        offset: T,
     ) -> Result<Option<Self>, NegativeHemisphereError> {
         let square_len = normal.square_length();
-        if square_len < f64::approx_epsilon() * f64::approx_epsilon() {
-            if offset > 0.0 {
+        if square_len < T::approx_epsilon() * T::approx_epsilon() {
+            if offset > T::zero() {
                 Ok(None)
             } else {
                 Err(NegativeHemisphereError)
             }
         } else {
-            let kf = 1.0 / square_len.sqrt();
+            let kf = T::one() / square_len.sqrt();
             Ok(Some(Plane {
                 normal: normal * Scale::new(kf),
                 offset: offset * kf,
@@ -125,55 +391,232 @@ impl Plane {
         }
     }
 
-    /// Check if this plane contains another one.
+    /// Construct the plane passing through three points. The normal
+    /// points to the side from which the points go counter-clockwise,
+    /// so that `signed_distance_to` is positive on that side.
+    ///
+    /// Returns `None` if the points are on the same line.
+    pub fn from_points(a: Point3D<T, U>, b: Point3D<T, U>, c: Point3D<T, U>) -> Option<Self> {
+        Self::from_point_normal(a, (b - a).cross(c - a))
+    }
+
+    /// Construct the plane passing through a point, with the given normal
+    /// that doesn't need to be normalized. The `signed_distance_to`
+    /// is positive on the side the normal points to.
+    ///
+    /// Returns `None` if the normal is zero.
+    pub fn from_point_normal(point: Point3D<T, U>, normal: Vector3D<T, U>) -> Option<Self> {
+        Plane {
+            normal,
+            offset: -point.to_vector().dot(normal),
+        }
+        .normalize()
+    }
+
+    /// Scale the plane equation, so that the normal is of unit length
+    /// and `signed_distance_to` returns the actual distances.
+    ///
+    /// Returns `None` if the normal is zero.
+    pub fn normalize(&self) -> Option<Self> {
+        let square_len = self.normal.square_length();
+        if square_len < T::approx_epsilon() * T::approx_epsilon() {
+            return None;
+        }
+        let kf = T::one() / square_len.sqrt();
+        Some(Plane {
+            normal: self.normal * kf,
+            offset: self.offset * kf,
+        })
+    }
+
+    /// Turn the plane to face the other way, which negates
+    /// the signs of the distances to it.
+    pub fn flip(&mut self) {
+        self.normal = -self.normal;
+        self.offset = -self.offset;
+    }
+
+    /// Transform the plane by a projective transform, i.e. return the plane
+    /// containing the transformed points of this one. The normal is
+    /// transformed by the inverse-transpose of the matrix and renormalized.
+    ///
+    /// Returns `None` if the transform isn't invertible, or if the plane
+    /// ends up at infinity.
+    pub fn transform<V>(&self, transform: &Transform3D<T, U, V>) -> Option<Plane<T, V>> {
+        let m = transform.inverse()?;
+        let (n, d) = (self.normal, self.offset);
+        let normal = Vector3D::new(
+            m.m11 * n.x + m.m12 * n.y + m.m13 * n.z + m.m14 * d,
+            m.m21 * n.x + m.m22 * n.y + m.m23 * n.z + m.m24 * d,
+            m.m31 * n.x + m.m32 * n.y + m.m33 * n.z + m.m34 * d,
+        );
+        let offset = m.m41 * n.x + m.m42 * n.y + m.m43 * n.z + m.m44 * d;
+        Plane::from_unnormalized(normal, offset).ok().flatten()
+    }
+
+    /// Check if this plane contains another one, i.e. if they are the same
+    /// plane, regardless of the orientation of their normals.
     pub fn contains(&self, other: &Self) -> bool {
-        //TODO: actually check for inside/outside
-        self.normal == other.normal && self.offset == other.offset
+        self.contains_with_epsilons(other, &EpsilonConfig::default())
+    }
+
+    /// Check if this plane contains another one, with custom thresholds.
+    /// The planes need to be parallel within the `intersection` epsilon,
+    /// and no further apart than the `coplanarity` one.
+    pub fn contains_with_epsilons(&self, other: &Self, epsilons: &EpsilonConfig<T>) -> bool {
+        if self.intersect_with_epsilons(other, epsilons).is_some() {
+            return false;
+        }
+        let ndot = self.normal.dot(other.normal);
+        (self.offset - ndot * other.offset).abs() < epsilons.coplanarity
+    }
+
+    /// Get a pair of unit vectors lying on the plane, perpendicular to each
+    /// other, such that `tangent.cross(bitangent)` is the plane normal.
+    /// The same plane always produces the same basis.
+    pub fn basis(&self) -> (Vector3D<T, U>, Vector3D<T, U>) {
+        orthonormal_basis(&self.normal)
     }
 
     /// Return the signed distance from this plane to a point.
     /// The distance is negative if the point is on the other side of the plane
     /// from the direction of the normal.
-    pub fn signed_distance_to(&self, point: &Point3D<f64>) -> f64 {
+    pub fn signed_distance_to(&self, point: &Point3D<T, U>) -> T {
         point.to_vector().dot(self.normal) + self.offset
     }
 
     /// Compute the distance across the line to the plane plane,
     /// starting from the line origin.
-    pub fn distance_to_line(&self, line: &Line) -> f64 {
+    pub fn distance_to_line(&self, line: &Line<T, U>) -> T {
         self.signed_distance_to(&line.origin) / -self.normal.dot(line.dir)
     }
 
     /// Compute the sum of signed distances to each of the points
     /// of another plane. Useful to know the relation of a plane that
     /// is a product of a split, and we know it doesn't intersect `self`.
-    pub fn signed_distance_sum_to<A>(&self, poly: &Polygon<A>) -> f64 {
+    pub fn signed_distance_sum_to<A>(&self, poly: &Polygon<A, T, U>) -> T {
         poly.points
             .iter()
-            .fold(0.0, |u, p| u + self.signed_distance_to(p))
+            .fold(T::zero(), |u, p| u + self.signed_distance_to(p))
+    }
+
+    /// Compute the sign of `signed_distance_sum_to`, taking into account
+    /// the rounding errors of the evaluation and of the plane equation.
+    ///
+    /// Returns `None` if the sign can't be determined, in which case the
+    /// evaluation is repeated with `f64` precision if `T` is less precise.
+    pub(crate) fn signed_distance_sum_sign<A>(&self, poly: &Polygon<A, T, U>) -> Option<Ordering> {
+        fn sum_sign<F: Float>(normal: [F; 3], offset: F, points: &[[F; 3]]) -> Option<Ordering> {
+            // the normalized plane equation is off by a few roundings
+            let roundings = F::one() + F::one() + F::one() + F::one();
+            let normal = normal.map(|n| Interval::around(n, roundings));
+            let offset = Interval::around(offset, roundings);
+            let sum = points.iter().fold(Interval::from(F::zero()), |sum, p| {
+                sum + normal[0] * p[0].into()
+                    + normal[1] * p[1].into()
+                    + normal[2] * p[2].into()
+                    + offset
+            });
+            sum.sign()
+        }
+
+        let points: SmallVec<[[T; 3]; 4]> = poly.points.iter().map(|p| p.to_array()).collect();
+        let sign = sum_sign(self.normal.to_array(), self.offset, &points);
+        // there is nothing to escalate to if `T` is already as precise as `f64`
+        if sign.is_some() || T::epsilon().to_f64() <= Some(f64::EPSILON) {
+            return sign;
+        }
+        let to_f64 = |v: T| v.to_f64().unwrap();
+        let points: SmallVec<[[f64; 3]; 4]> = points.iter().map(|p| p.map(to_f64)).collect();
+        sum_sign(
+            self.normal.to_array().map(to_f64),
+            to_f64(self.offset),
+            &points,
+        )
+    }
+
+    /// Classify a polygon as being in front of this plane, behind it,
+    /// on it, or crossing it.
+    pub fn classify<A>(&self, poly: &Polygon<A, T, U>) -> Classification<T, U> {
+        self.classify_with_epsilons(poly, &EpsilonConfig::default())
+    }
+
+    /// Classify a polygon against this plane, using custom thresholds
+    /// for detecting parallel and coplanar planes.
+    pub fn classify_with_epsilons<A>(
+        &self,
+        poly: &Polygon<A, T, U>,
+        epsilons: &EpsilonConfig<T>,
+    ) -> Classification<T, U> {
+        let dist = match self.intersect_with_epsilons(&poly.plane, epsilons) {
+            None => {
+                let ndot = self.normal.dot(poly.plane.normal);
+                let dist = self.offset - ndot * poly.plane.offset;
+                //Note: we deliberately make the comparison wider than just with T::epsilon().
+                // This is done to avoid mistakenly ordering items that should be on the same
+                // plane but end up slightly different due to the floating point precision.
+                if dist.abs() < epsilons.coplanarity {
+                    return Classification::Coplanar;
+                }
+                dist
+            }
+            //Note: we can't start with `are_outside` because it's subject to FP precision
+            Some(_) if self.are_outside(&poly.points[..]) => self.signed_distance_sum_to(poly),
+            Some(line) => return Classification::Spanning(line),
+        };
+        if dist > T::zero() {
+            Classification::Front
+        } else {
+            Classification::Back
+        }
     }
 
     /// Check if a convex shape defined by a set of points is completely
     /// outside of this plane. Merely touching the surface is not
     /// considered an intersection.
-    pub fn are_outside(&self, points: &[Point3D<f64>]) -> bool {
+    pub fn are_outside(&self, points: &[Point3D<T, U>]) -> bool {
         let d0 = self.signed_distance_to(&points[0]);
         points[1..]
             .iter()
-            .all(|p| self.signed_distance_to(p) * d0 > 0.0)
+            .all(|p| self.signed_distance_to(p) * d0 > T::zero())
+    }
+
+    /// Compute the point where three planes meet.
+    /// Returns `None` if any two of them are parallel, or if all of them
+    /// go through the same line.
+    pub fn intersect_3(a: &Self, b: &Self, c: &Self) -> Option<Point3D<T, U>> {
+        let bc = b.normal.cross(c.normal);
+        let det = a.normal.dot(bc);
+        if det.abs() < T::approx_epsilon() {
+            return None;
+        }
+        let ca = c.normal.cross(a.normal);
+        let ab = a.normal.cross(b.normal);
+        let sum = bc * a.offset + ca * b.offset + ab * c.offset;
+        Some((sum / -det).to_point())
     }
 
     //TODO(breaking): turn this into Result<Line, DotProduct>
     /// Compute the line of intersection with another plane.
-    pub fn intersect(&self, other: &Self) -> Option<Line> {
+    pub fn intersect(&self, other: &Self) -> Option<Line<T, U>> {
+        self.intersect_with_epsilons(other, &EpsilonConfig::default())
+    }
+
+    /// Compute the line of intersection with another plane,
+    /// using custom thresholds to detect parallel planes.
+    pub fn intersect_with_epsilons(
+        &self,
+        other: &Self,
+        epsilons: &EpsilonConfig<T>,
+    ) -> Option<Line<T, U>> {
         // compute any point on the intersection between planes
         // (n1, v) + d1 = 0
         // (n2, v) + d2 = 0
         // v = a*n1/w + b*n2/w; w = (n1, n2)
         // v = (d2*w - d1) / (1 - w*w) * n1 - (d2 - d1*w) / (1 - w*w) * n2
         let w = self.normal.dot(other.normal);
-        let divisor = 1.0 - w * w;
-        if divisor < f64::approx_epsilon() * f64::approx_epsilon() {
+        let divisor = T::one() - w * w;
+        if divisor < epsilons.intersection * epsilons.intersection {
             return None;
         }
         let origin = Point3D::origin() + self.normal * ((other.offset * w - self.offset) / divisor)
@@ -190,6 +633,140 @@ impl Plane {
     }
 }
 
+/// Generic plane splitter interface.
+///
+/// The unit `U` tells the `euclid` space the polygons are in.
+pub trait Splitter<A, T = f64, U = UnknownUnit> {
+    /// Put the splitter back in it initial state.
+    fn reset(&mut self);
+
+    /// Add a polygon to the splitter.
+    fn add(&mut self, polygon: Polygon<A, T, U>);
+
+    /// Add a number of polygons to the splitter,
+    /// which may reserve the storage for them up front.
+    fn add_all<I>(&mut self, polygons: I)
+    where
+        I: IntoIterator<Item = Polygon<A, T, U>>,
+    {
+        for polygon in polygons {
+            self.add(polygon);
+        }
+    }
+
+    /// Sort the added and split polygons against the view vector.
+    fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>];
+
+    /// Sort the added and split polygons against the view vector, and
+    /// convert the anchors of the result, e.g. from indices to the data
+    /// they refer to. The splitter keeps the original anchors.
+    fn map_anchors<B, F>(&mut self, view: Vector3D<T, U>, mut f: F) -> Vec<Polygon<B, T, U>>
+    where
+        F: FnMut(&A) -> B,
+        T: Copy,
+    {
+        self.sort(view)
+            .iter()
+            .map(|p| p.map_anchor(&mut f))
+            .collect()
+    }
+
+    /// Process a set of polygons at once.
+    fn solve<I>(&mut self, input: I, view: Vector3D<T, U>) -> &[Polygon<A, T, U>]
+    where
+        I: IntoIterator,
+        I::Item: Borrow<Polygon<A, T, U>>,
+        A: Clone,
+        T: Copy,
+    {
+        self.reset();
+        self.add_all(input.into_iter().map(|p| p.borrow().clone()));
+        self.sort(view)
+    }
+
+    /// Sort the added and split polygons against the view vector,
+    /// and triangulate them.
+    fn sort_triangulated(&mut self, view: Vector3D<T, U>) -> TriangleBuffer<A, T, U>
+    where
+        A: Clone + PartialEq,
+        T: Float,
+    {
+        TriangleBuffer::from_polygons(self.sort(view))
+    }
+
+    /// Sort the added and split polygons against the view vector,
+    /// and pack their points into a flat `f32` array.
+    fn sort_flat(&mut self, view: Vector3D<T, U>) -> FlatOutput<A>
+    where
+        A: Clone,
+        T: Float,
+    {
+        FlatOutput::from_polygons(self.sort(view))
+    }
+}
+
+/// Estimate the number of fragments that splitting the polygons would
+/// produce, without building the tree, e.g. to give up early on scenes
+/// that would be too expensive to split.
+///
+/// Each polygon is considered to be cut by the planes of the polygons
+/// whose bounding boxes overlap with it and which have its points on both
+/// sides, into as many pieces as the lines of these cuts can divide it into.
+/// The farther planes are ignored, even though the tree may still use them.
+pub fn estimate_fragments<A, T, U>(polys: &[Polygon<A, T, U>]) -> usize
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let epsilon = EpsilonConfig::<T>::default().coplanarity;
+    let crosses = |plane: &Plane<T, U>, poly: &Polygon<A, T, U>| {
+        let distances: SmallVec<[T; 4]> = poly
+            .points
+            .iter()
+            .map(|p| plane.signed_distance_to(p))
+            .collect();
+        distances.iter().any(|&d| d > epsilon) && distances.iter().any(|&d| d < -epsilon)
+    };
+    let bounds: Vec<_> = polys.iter().map(|poly| poly.bounding_box()).collect();
+    let overlap = |a: &Box3D<T, U>, b: &Box3D<T, U>| {
+        a.min.x <= b.max.x
+            && b.min.x <= a.max.x
+            && a.min.y <= b.max.y
+            && b.min.y <= a.max.y
+            && a.min.z <= b.max.z
+            && b.min.z <= a.max.z
+    };
+    polys
+        .iter()
+        .enumerate()
+        .map(|(i, poly)| {
+            let cuts = polys
+                .iter()
+                .enumerate()
+                .filter(|&(j, other)| {
+                    j != i && overlap(&bounds[i], &bounds[j]) && crosses(&other.plane, poly)
+                })
+                .count();
+            // the number of regions that this many lines can make
+            1 + cuts + cuts * cuts.saturating_sub(1) / 2
+        })
+        .sum()
+}
+
+/// Get a pair of unit vectors perpendicular to each other and to the unit
+/// `normal`, such that their cross product is the `normal`.
+fn orthonormal_basis<T: Float, U>(normal: &Vector3D<T, U>) -> (Vector3D<T, U>, Vector3D<T, U>) {
+    // any axis works, as long as it's not close to the normal
+    let axis = if normal.x.abs() > T::from(0.9).unwrap() {
+        Vector3D::new(T::zero(), T::one(), T::zero())
+    } else {
+        Vector3D::new(T::one(), T::zero(), T::zero())
+    };
+    let tangent = normal.cross(axis).normalize();
+    let bitangent = normal.cross(tangent);
+    (tangent, bitangent)
+}
+
 /// Helper method used for benchmarks and tests.
 /// Constructs a 3D grid of polygons.
 #[doc(hidden)]
@@ -197,7 +774,7 @@ pub fn make_grid(count: usize) -> Vec<Polygon<usize>> {
     let mut polys: Vec<Polygon<usize>> = Vec::with_capacity(count * 3);
     let len = count as f64;
     polys.extend((0..count).map(|i| Polygon {
-        points: [
+        points: smallvec![
             Point3D::new(0.0, i as f64, 0.0),
             Point3D::new(len, i as f64, 0.0),
             Point3D::new(len, i as f64, len),
@@ -208,9 +785,10 @@ pub fn make_grid(count: usize) -> Vec<Polygon<usize>> {
             offset: -(i as f64),
         },
         anchor: 0,
+        edges: SmallVec::new(),
     }));
     polys.extend((0..count).map(|i| Polygon {
-        points: [
+        points: smallvec![
             Point3D::new(i as f64, 0.0, 0.0),
             Point3D::new(i as f64, len, 0.0),
             Point3D::new(i as f64, len, len),
@@ -221,9 +799,10 @@ pub fn make_grid(count: usize) -> Vec<Polygon<usize>> {
             offset: -(i as f64),
         },
         anchor: 0,
+        edges: SmallVec::new(),
     }));
     polys.extend((0..count).map(|i| Polygon {
-        points: [
+        points: smallvec![
             Point3D::new(0.0, 0.0, i as f64),
             Point3D::new(len, 0.0, i as f64),
             Point3D::new(len, len, i as f64),
@@ -234,6 +813,7 @@ pub fn make_grid(count: usize) -> Vec<Polygon<usize>> {
             offset: -(i as f64),
         },
         anchor: 0,
+        edges: SmallVec::new(),
     }));
     polys
 }
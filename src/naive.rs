@@ -0,0 +1,222 @@
+use crate::{EpsilonConfig, PlaneCut, Polygon, Splitter};
+
+use euclid::{approxeq::ApproxEq, UnknownUnit, Vector3D};
+use num_traits::Float;
+use smallvec::SmallVec;
+
+use alloc::{collections::BinaryHeap, vec, vec::Vec};
+use core::{cmp::Reverse, fmt};
+
+/// A splitter doing the simplest thing that works, meant to be
+/// a reference for testing the other splitters against.
+///
+/// Every polygon is cut by the planes of all the other polygons,
+/// and the fragments are ordered by resolving the occlusion between
+/// each pair of them. The cost is at least quadratic in the number
+/// of polygons, so it's not suitable for the real content.
+pub struct NaiveSplitter<A, T = f64, U = UnknownUnit> {
+    polygons: Vec<Polygon<A, T, U>>,
+    result: Vec<Polygon<A, T, U>>,
+    epsilons: EpsilonConfig<T>,
+}
+
+impl<A, T, U> NaiveSplitter<A, T, U>
+where
+    T: Float + ApproxEq<T>,
+{
+    /// Create a new naive splitter.
+    pub fn new() -> Self {
+        Self::with_epsilons(EpsilonConfig::default())
+    }
+
+    /// Create a new naive splitter with custom thresholds.
+    pub fn with_epsilons(epsilons: EpsilonConfig<T>) -> Self {
+        NaiveSplitter {
+            polygons: Vec::new(),
+            result: Vec::new(),
+            epsilons,
+        }
+    }
+}
+
+impl<A, T, U> Default for NaiveSplitter<A, T, U>
+where
+    T: Float + ApproxEq<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A, T, U> NaiveSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    /// Cut every polygon by the planes of the others,
+    /// returning the fragments with the indices of their sources.
+    fn split_all(&self) -> Vec<(usize, Polygon<A, T, U>)> {
+        let mut fragments: Vec<_> = self.polygons.iter().cloned().enumerate().collect();
+        for (i, cutter) in self.polygons.iter().enumerate() {
+            let mut next = Vec::with_capacity(fragments.len());
+            for (source, fragment) in fragments {
+                let mut front: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+                let mut back: SmallVec<[Polygon<A, T, U>; 2]> = SmallVec::new();
+                if source == i
+                    || cutter.cut_with_epsilons(&fragment, &mut front, &mut back, &self.epsilons)
+                        == PlaneCut::Sibling
+                {
+                    next.push((source, fragment));
+                    continue;
+                }
+                next.extend(front.into_iter().chain(back).map(|p| (source, p)));
+            }
+            fragments = next;
+        }
+        fragments
+    }
+}
+
+impl<A, T, U> Splitter<A, T, U> for NaiveSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn reset(&mut self) {
+        self.polygons.clear();
+        self.result.clear();
+    }
+
+    fn add(&mut self, polygon: Polygon<A, T, U>) {
+        self.polygons.push(polygon);
+    }
+
+    fn add_all<I>(&mut self, polygons: I)
+    where
+        I: IntoIterator<Item = Polygon<A, T, U>>,
+    {
+        self.polygons.extend(polygons);
+    }
+
+    fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        let fragments = self.split_all();
+        let count = fragments.len();
+        let epsilon = self.epsilons.coplanarity;
+
+        // build the graph of the fragments that need to be drawn before others
+        let mut after: Vec<Vec<usize>> = vec![Vec::new(); count];
+        for i in 0..count {
+            for j in i + 1..count {
+                let (a, b) = (&fragments[i].1, &fragments[j].1);
+                if !a.overlaps_along(b, &view, epsilon) {
+                    continue;
+                }
+                // the coplanar polygons are drawn in the order they were added
+                let is_behind =
+                    is_behind(a, b, &view, epsilon).unwrap_or(fragments[i].0 <= fragments[j].0);
+                if is_behind {
+                    after[i].push(j);
+                } else {
+                    after[j].push(i);
+                }
+            }
+        }
+
+        let (order, _) = topological_sort(&after, true);
+        self.result.clear();
+        self.result
+            .extend(order.into_iter().map(|i| fragments[i].1.clone()));
+        &self.result
+    }
+}
+
+impl<A, T, U> Extend<Polygon<A, T, U>> for NaiveSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn extend<I: IntoIterator<Item = Polygon<A, T, U>>>(&mut self, iter: I) {
+        self.add_all(iter);
+    }
+}
+
+/// Tell if `a` needs to be drawn before `b`, assuming they overlap.
+/// Returns `None` if the order can't be determined from their planes,
+/// i.e. if they are coplanar or intersect each other.
+pub(crate) fn is_behind<A, T, U>(
+    a: &Polygon<A, T, U>,
+    b: &Polygon<A, T, U>,
+    view: &Vector3D<T, U>,
+    epsilon: T,
+) -> Option<bool>
+where
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    // the side of the plane facing the viewer is drawn last
+    let facing = |plane_poly: &Polygon<A, T, U>, other: &Polygon<A, T, U>| {
+        let plane = &plane_poly.plane;
+        let (min, max) = other
+            .points
+            .iter()
+            .map(|p| plane.signed_distance_to(p))
+            .fold((T::infinity(), T::neg_infinity()), |(lo, hi), d| {
+                (lo.min(d), hi.max(d))
+            });
+        let is_front = plane.normal.dot(*view) > T::zero();
+        if min > -epsilon && max > epsilon {
+            Some(is_front)
+        } else if max < epsilon && min < -epsilon {
+            Some(!is_front)
+        } else {
+            None
+        }
+    };
+    facing(a, b).or_else(|| facing(b, a).map(|is_closer| !is_closer))
+}
+
+/// Order the nodes of a graph, so that each comes before the nodes
+/// listed in its `after` entry, preferring the lower indices.
+///
+/// Returns the order, and the nodes left out of it because of the cycles.
+/// If `break_cycles` is set, the least blocked node is taken out of
+/// a cycle instead, so that all the nodes are ordered.
+pub(crate) fn topological_sort(
+    after: &[Vec<usize>],
+    break_cycles: bool,
+) -> (Vec<usize>, Vec<usize>) {
+    let count = after.len();
+    let mut blockers = vec![0usize; count];
+    for &latter in after.iter().flatten() {
+        blockers[latter] += 1;
+    }
+    let mut ready: BinaryHeap<Reverse<usize>> = (0..count)
+        .filter(|&i| blockers[i] == 0)
+        .map(Reverse)
+        .collect();
+    let mut done = vec![false; count];
+    let mut order = Vec::with_capacity(count);
+    while order.len() < count {
+        let next = match ready.pop() {
+            Some(Reverse(next)) => next,
+            None if break_cycles => {
+                let next = (0..count)
+                    .filter(|&i| !done[i])
+                    .min_by_key(|&i| (blockers[i], i))
+                    .unwrap();
+                log::warn!("\tOcclusion cycle at fragment {}", next);
+                next
+            }
+            None => break,
+        };
+        done[next] = true;
+        order.push(next);
+        for &latter in &after[next] {
+            blockers[latter] -= 1;
+            if blockers[latter] == 0 && !done[latter] {
+                ready.push(Reverse(latter));
+            }
+        }
+    }
+    let remaining = (0..count).filter(|&i| !done[i]).collect();
+    (order, remaining)
+}
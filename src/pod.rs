@@ -0,0 +1,84 @@
+use crate::{Plane, Polygon};
+
+use bytemuck::{Pod, Zeroable};
+use euclid::{Point3D, Vector3D};
+use num_traits::Float;
+use smallvec::SmallVec;
+
+use alloc::vec::Vec;
+
+/// A polygon with a fixed layout of plain `f32` data, which slices
+/// of can be cast to bytes with `bytemuck`, e.g. for uploading to
+/// the GPU or sending to another process.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Pod, Zeroable)]
+pub struct PodPolygon {
+    /// Points of the polygon, with the unused ones zeroed.
+    pub points: [[f32; 3]; PodPolygon::MAX_POINTS],
+    /// Number of the used points.
+    pub count: u32,
+    /// Normal of the polygon plane.
+    pub normal: [f32; 3],
+    /// Offset of the polygon plane.
+    pub offset: f32,
+    /// Anchor of the polygon.
+    pub anchor: u32,
+}
+
+impl PodPolygon {
+    /// Maximum number of points, which is enough for the fragments
+    /// of a quad cut a few times.
+    pub const MAX_POINTS: usize = 8;
+
+    /// Convert a polygon, with the given anchor.
+    ///
+    /// Returns `None` if the polygon has more than `MAX_POINTS` points.
+    pub fn from_polygon<A, T: Float, U>(polygon: &Polygon<A, T, U>, anchor: u32) -> Option<Self> {
+        if polygon.points.len() > Self::MAX_POINTS {
+            return None;
+        }
+        let convert = |v: Vector3D<T, U>| [v.x, v.y, v.z].map(|c| c.to_f32().unwrap_or(f32::NAN));
+        let mut pod = PodPolygon {
+            count: polygon.points.len() as u32,
+            normal: convert(polygon.plane.normal),
+            offset: polygon.plane.offset.to_f32().unwrap_or(f32::NAN),
+            anchor,
+            ..Self::default()
+        };
+        for (dst, src) in pod.points.iter_mut().zip(&polygon.points) {
+            *dst = convert(src.to_vector());
+        }
+        Some(pod)
+    }
+
+    /// Convert a slice of polygons, mapping their anchors.
+    ///
+    /// Returns `None` if any of the polygons has too many points.
+    pub fn from_polygons<A, T, U, F>(polygons: &[Polygon<A, T, U>], mut f: F) -> Option<Vec<Self>>
+    where
+        T: Float,
+        F: FnMut(&A) -> u32,
+    {
+        polygons
+            .iter()
+            .map(|poly| Self::from_polygon(poly, f(&poly.anchor)))
+            .collect()
+    }
+
+    /// Convert back to a polygon. The edge origins are lost.
+    pub fn to_polygon(&self) -> Polygon<u32, f32> {
+        let count = (self.count as usize).min(Self::MAX_POINTS);
+        Polygon {
+            points: self.points[..count]
+                .iter()
+                .map(|&[x, y, z]| Point3D::new(x, y, z))
+                .collect(),
+            plane: Plane {
+                normal: Vector3D::from(self.normal),
+                offset: self.offset,
+            },
+            anchor: self.anchor,
+            edges: SmallVec::new(),
+        }
+    }
+}
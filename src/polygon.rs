@@ -1,48 +1,32 @@
-use crate::{is_zero, Line, Plane};
+use crate::{
+    is_zero, orthonormal_basis, Classification, ClipRegion, EpsilonConfig, Frustum, Line,
+    LineSegment, Plane,
+};
 
 use euclid::{
-    approxeq::ApproxEq,
-    default::{Point2D, Point3D, Rect, Transform3D, Vector3D},
+    approxeq::ApproxEq, Box3D, Point2D, Point3D, Rect, Transform3D, UnknownUnit, Vector2D, Vector3D,
 };
-use smallvec::SmallVec;
+use num_traits::Float;
+use smallvec::{smallvec, SmallVec};
 
-use std::{iter, mem};
+use alloc::{borrow::Cow, vec::Vec};
+use core::{fmt, iter};
 
 /// The projection of a `Polygon` on a line.
-pub struct LineProjection {
+pub struct LineProjection<T = f64> {
     /// Projected value of each point in the polygon.
-    pub markers: [f64; 4],
+    /// Only 4 of them are stored inline, but any number is supported.
+    pub markers: SmallVec<[T; 4]>,
 }
 
-impl LineProjection {
+impl<T: Float> LineProjection<T> {
     /// Get the min/max of the line projection markers.
-    pub fn get_bounds(&self) -> (f64, f64) {
-        let (mut a, mut b, mut c, mut d) = (
-            self.markers[0],
-            self.markers[1],
-            self.markers[2],
-            self.markers[3],
-        );
-        // bitonic sort of 4 elements
-        // we could not just use `min/max` since they require `Ord` bound
-        //TODO: make it nicer
-        if a > c {
-            mem::swap(&mut a, &mut c);
-        }
-        if b > d {
-            mem::swap(&mut b, &mut d);
-        }
-        if a > b {
-            mem::swap(&mut a, &mut b);
-        }
-        if c > d {
-            mem::swap(&mut c, &mut d);
-        }
-        if b > c {
-            mem::swap(&mut b, &mut c);
-        }
-        debug_assert!(a <= b && b <= c && c <= d);
-        (a, d)
+    pub fn get_bounds(&self) -> (T, T) {
+        self.markers
+            .iter()
+            .fold((T::infinity(), T::neg_infinity()), |(min, max), &m| {
+                (min.min(m), max.max(m))
+            })
     }
 
     /// Check intersection with another line projection.
@@ -64,6 +48,20 @@ impl LineProjection {
         // they intersect if the footprint is smaller than the sum
         right - left < span.1 - span.0 + other_span.1 - other_span.0
     }
+
+    /// Compute the interval where this line projection overlaps another one.
+    /// Returns `None` if they don't overlap, or only touch each other.
+    pub fn overlap(&self, other: &Self) -> Option<(T, T)> {
+        let span = self.get_bounds();
+        let other_span = other.get_bounds();
+        let start = span.0.max(other_span.0);
+        let end = span.1.min(other_span.1);
+        if start < end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
 }
 
 /// Polygon intersection results.
@@ -79,142 +77,385 @@ pub enum Intersection<T> {
 impl<T> Intersection<T> {
     /// Return true if the intersection is completely outside.
     pub fn is_outside(&self) -> bool {
-        match *self {
-            Intersection::Outside => true,
-            _ => false,
-        }
+        matches!(*self, Intersection::Outside)
     }
     /// Return true if the intersection cuts the source polygon.
     pub fn is_inside(&self) -> bool {
-        match *self {
-            Intersection::Inside(_) => true,
-            _ => false,
-        }
+        matches!(*self, Intersection::Inside(_))
     }
 }
 
-/// A convex polygon with 4 points lying on a plane.
-#[derive(Debug, PartialEq)]
-pub struct Polygon<A> {
-    /// Points making the polygon.
-    pub points: [Point3D<f64>; 4],
+/// The origin of a polygon edge.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdgeOrigin {
+    /// The edge is a part of the outline of the original polygon.
+    Original,
+    /// The edge was introduced by cutting or clipping the polygon.
+    Cut,
+}
+
+/// The direction in which the points of a polygon go around its plane normal.
+#[derive(Clone, Copy, Debug, Hash, PartialEq, Eq)]
+pub enum Winding {
+    /// Counter-clockwise when looking against the normal,
+    /// which is the winding of the polygons built by this crate.
+    CounterClockwise,
+    /// Clockwise when looking against the normal.
+    Clockwise,
+    /// The polygon doesn't contain any space.
+    Degenerate,
+}
+
+/// A convex polygon with an arbitrary number of points lying on a plane.
+///
+/// The unit `U` tells the `euclid` space of the points.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(serialize = "A: serde::Serialize, T: serde::Serialize"))
+)]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(deserialize = "A: serde::Deserialize<'de>, T: serde::Deserialize<'de>"))
+)]
+pub struct Polygon<A, T = f64, U = UnknownUnit> {
+    /// Points making the polygon, in winding order.
+    pub points: SmallVec<[Point3D<T, U>; 4]>,
     /// A plane describing polygon orientation.
-    pub plane: Plane,
+    pub plane: Plane<T, U>,
     /// A simple anchoring index to allow association of the
     /// produced split polygons with the original one.
+    /// It gets cloned for every fragment, so it should be cheap
+    /// to clone, e.g. an index or an `Rc`.
     pub anchor: A,
+    /// Origin of each edge, going from the point with the same index
+    /// to the next one. Missing entries are considered original,
+    /// so this is empty for polygons that were never cut.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub edges: SmallVec<[EdgeOrigin; 4]>,
 }
 
-impl<A: Copy> Clone for Polygon<A> {
+impl<A: Clone, T: Copy, U> Clone for Polygon<A, T, U> {
     fn clone(&self) -> Self {
         Polygon {
-            points: [
-                self.points[0].clone(),
-                self.points[1].clone(),
-                self.points[2].clone(),
-                self.points[3].clone(),
-            ],
+            points: self.points.clone(),
             plane: self.plane.clone(),
+            anchor: self.anchor.clone(),
+            edges: self.edges.clone(),
+        }
+    }
+}
+
+impl<A: fmt::Debug, T: fmt::Debug, U> fmt::Debug for Polygon<A, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Polygon")
+            .field("points", &self.points)
+            .field("plane", &self.plane)
+            .field("anchor", &self.anchor)
+            .field("edges", &self.edges)
+            .finish()
+    }
+}
+
+impl<A: PartialEq, T: PartialEq, U> PartialEq for Polygon<A, T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.points == other.points
+            && self.plane == other.plane
+            && self.anchor == other.anchor
+            && self.edges == other.edges
+    }
+}
+
+/// Shows the anchor, the plane and the point count on one line,
+/// e.g. "anchor=3, n=(0,0,1), o=-5, 4 pts".
+impl<A: fmt::Display, T: fmt::Display, U> fmt::Display for Polygon<A, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "anchor={}, {}, {} pts",
+            self.anchor,
+            self.plane,
+            self.points.len()
+        )
+    }
+}
+
+impl<A, T: Copy, U> Polygon<A, T, U> {
+    /// Mark the polygon as being in the `UnknownUnit` space.
+    pub fn to_untyped(self) -> Polygon<A, T> {
+        self.cast_unit()
+    }
+
+    /// Mark an untyped polygon as being in the space `U`.
+    pub fn from_untyped(poly: Polygon<A, T>) -> Self {
+        poly.cast_unit()
+    }
+
+    /// Change the space the polygon is in, without changing the points.
+    pub fn cast_unit<V>(self) -> Polygon<A, T, V> {
+        Polygon {
+            points: self.points.iter().map(|p| p.cast_unit()).collect(),
+            plane: self.plane.cast_unit(),
             anchor: self.anchor,
+            edges: self.edges,
         }
     }
 }
 
-impl<A> Polygon<A>
+impl<A, T: Copy, U> Polygon<A, T, U> {
+    /// Get a copy of the polygon with the anchor converted by the function.
+    pub fn map_anchor<B, F>(&self, f: F) -> Polygon<B, T, U>
+    where
+        F: FnOnce(&A) -> B,
+    {
+        Polygon {
+            points: self.points.clone(),
+            plane: self.plane.clone(),
+            anchor: f(&self.anchor),
+            edges: self.edges.clone(),
+        }
+    }
+}
+
+impl<A, T, U> Polygon<A, T, U>
 where
-    A: Copy,
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
 {
     /// Construct a polygon from points that are already transformed.
     /// Return None if the polygon doesn't contain any space.
-    pub fn from_points(points: [Point3D<f64>; 4], anchor: A) -> Option<Self> {
-        let edge1 = points[1] - points[0];
-        let edge2 = points[2] - points[0];
-        let edge3 = points[3] - points[0];
-        let edge4 = points[3] - points[1];
-
-        if edge2.square_length() < f64::EPSILON || edge4.square_length() < f64::EPSILON {
+    pub fn from_points<I>(points: I, anchor: A) -> Option<Self>
+    where
+        I: IntoIterator<Item = Point3D<T, U>>,
+    {
+        let points: SmallVec<[Point3D<T, U>; 4]> = points.into_iter().collect();
+        if points.len() < 3 {
             return None;
         }
 
-        // one of them can be zero for redundant polygons produced by plane splitting
-        //Note: this would be nicer if we used triangles instead of quads in the first place...
-        // see https://github.com/servo/plane-split/issues/17
-        let normal_rough1 = edge1.cross(edge2);
-        let normal_rough2 = edge2.cross(edge3);
-        let square_length1 = normal_rough1.square_length();
-        let square_length2 = normal_rough2.square_length();
-        let normal = if square_length1 > square_length2 {
-            normal_rough1 / square_length1.sqrt()
-        } else {
-            normal_rough2 / square_length2.sqrt()
-        };
+        // Pick the largest of the triangle fan normals, since some of them
+        // can be zero for redundant polygons produced by plane splitting.
+        let base = points[0];
+        let (normal_rough, square_length) = points[1..]
+            .iter()
+            .zip(points[2..].iter())
+            .map(|(&a, &b)| (a - base).cross(b - base))
+            .fold((Vector3D::zero(), T::zero()), |(best, best_sl), n| {
+                let sl = n.square_length();
+                if sl > best_sl {
+                    (n, sl)
+                } else {
+                    (best, best_sl)
+                }
+            });
+        if square_length < T::epsilon() {
+            return None;
+        }
 
-        let offset = -points[0].to_vector().dot(normal);
+        let normal = normal_rough / square_length.sqrt();
+        let offset = -base.to_vector().dot(normal);
 
         Some(Polygon {
             points,
             plane: Plane { normal, offset },
             anchor,
+            edges: SmallVec::new(),
         })
     }
 
+    /// Construct a polygon from points that are only roughly planar, e.g.
+    /// produced by a chain of transformations, by fitting the plane to them
+    /// with Newell's method and projecting the points onto it.
+    ///
+    /// Returns the polygon along with the largest distance a point was moved.
+    /// Returns None if the polygon doesn't contain any space.
+    pub fn from_points_best_fit<I>(points: I, anchor: A) -> Option<(Self, T)>
+    where
+        I: IntoIterator<Item = Point3D<T, U>>,
+    {
+        let mut points: SmallVec<[Point3D<T, U>; 4]> = points.into_iter().collect();
+        let count = points.len();
+        if count < 3 {
+            return None;
+        }
+
+        let mut normal: Vector3D<T, U> = Vector3D::zero();
+        let mut center: Vector3D<T, U> = Vector3D::zero();
+        for i in 0..count {
+            let (a, b) = (points[i], points[(i + 1) % count]);
+            normal.x = normal.x + (a.y - b.y) * (a.z + b.z);
+            normal.y = normal.y + (a.z - b.z) * (a.x + b.x);
+            normal.z = normal.z + (a.x - b.x) * (a.y + b.y);
+            center += a.to_vector();
+        }
+        let square_length = normal.square_length();
+        if square_length < T::epsilon() {
+            return None;
+        }
+        let normal = normal / square_length.sqrt();
+        let center = center / T::from(count).unwrap();
+        let plane = Plane {
+            normal,
+            offset: -center.dot(normal),
+        };
+
+        let mut correction = T::zero();
+        for point in points.iter_mut() {
+            let distance = plane.signed_distance_to(point);
+            correction = correction.max(distance.abs());
+            *point -= normal * distance;
+        }
+        let poly = Polygon {
+            points,
+            plane,
+            anchor,
+            edges: SmallVec::new(),
+        };
+        Some((poly, correction))
+    }
+
+    /// Construct a polygon from points that are already transformed,
+    /// and check that it's valid, telling what's wrong otherwise.
+    pub fn try_new<I>(points: I, anchor: A) -> Result<Self, PolygonError<T>>
+    where
+        I: IntoIterator<Item = Point3D<T, U>>,
+    {
+        let poly = Self::from_points(points, anchor).ok_or(PolygonError::ZeroArea)?;
+        poly.validate()?;
+        Ok(poly)
+    }
+
+    /// Construct a polygon from points of any math library
+    /// supporting `mint`, like `from_points` does.
+    #[cfg(feature = "mint")]
+    pub fn from_mint_points<P, I>(points: I, anchor: A) -> Option<Self>
+    where
+        P: Into<mint::Point3<T>>,
+        I: IntoIterator<Item = P>,
+    {
+        Self::from_points(points.into_iter().map(|p| Point3D::from(p.into())), anchor)
+    }
+
+    /// Get the points as `mint` points, which convert
+    /// into the types of any math library supporting it.
+    #[cfg(feature = "mint")]
+    pub fn mint_points(&self) -> impl Iterator<Item = mint::Point3<T>> + '_ {
+        self.points.iter().map(|&p| p.into())
+    }
+
     /// Construct a polygon from a non-transformed rectangle.
-    pub fn from_rect(rect: Rect<f64>, anchor: A) -> Self {
+    pub fn from_rect(rect: Rect<T, U>, anchor: A) -> Self {
         let min = rect.min();
         let max = rect.max();
         Polygon {
-            points: [
+            points: smallvec![
                 min.to_3d(),
-                Point3D::new(max.x, min.y, 0.0),
+                Point3D::new(max.x, min.y, T::zero()),
                 max.to_3d(),
-                Point3D::new(min.x, max.y, 0.0),
+                Point3D::new(min.x, max.y, T::zero()),
             ],
             plane: Plane {
-                normal: Vector3D::new(0.0, 0.0, 1.0),
-                offset: 0.0,
+                normal: Vector3D::new(T::zero(), T::zero(), T::one()),
+                offset: T::zero(),
             },
             anchor,
+            edges: SmallVec::new(),
         }
     }
 
     /// Construct a polygon from a rectangle with 3D transform.
-    pub fn from_transformed_rect(
-        rect: Rect<f64>,
-        transform: Transform3D<f64>,
+    pub fn from_transformed_rect<V>(
+        rect: Rect<T, V>,
+        transform: Transform3D<T, V, U>,
         anchor: A,
     ) -> Option<Self> {
         let min = rect.min();
         let max = rect.max();
         let points = [
             transform.transform_point3d(min.to_3d())?,
-            transform.transform_point3d(Point3D::new(max.x, min.y, 0.0))?,
+            transform.transform_point3d(Point3D::new(max.x, min.y, T::zero()))?,
             transform.transform_point3d(max.to_3d())?,
-            transform.transform_point3d(Point3D::new(min.x, max.y, 0.0))?,
+            transform.transform_point3d(Point3D::new(min.x, max.y, T::zero()))?,
         ];
         Self::from_points(points, anchor)
     }
 
+    /// Construct a polygon from a rectangle with 3D transform, keeping only
+    /// the visible part of it, i.e. where the homogeneous `w` coordinate
+    /// is positive. The rectangle is clipped in the homogeneous space before
+    /// the projection, so the result may have 3 to 5 points.
+    ///
+    /// Returns `None` if the rectangle is completely behind the eye.
+    pub fn from_transformed_rect_with_validity<V>(
+        rect: Rect<T, V>,
+        transform: Transform3D<T, V, U>,
+        anchor: A,
+    ) -> Option<Self> {
+        let min = rect.min();
+        let max = rect.max();
+        let corners = [
+            min.to_3d(),
+            Point3D::new(max.x, min.y, T::zero()),
+            max.to_3d(),
+            Point3D::new(min.x, max.y, T::zero()),
+        ];
+        let homo = corners.map(|p| transform.transform_point3d_homogeneous(p));
+
+        let epsilon = T::approx_epsilon();
+        let mut points = SmallVec::<[Point3D<T, U>; 4]>::new();
+        let mut edges = SmallVec::new();
+        for i in 0..homo.len() {
+            let (h0, h1) = (homo[i], homo[(i + 1) % homo.len()]);
+            if h0.w >= epsilon {
+                points.push(h0.to_point3d()?);
+                edges.push(EdgeOrigin::Original);
+            }
+            if (h0.w >= epsilon) != (h1.w >= epsilon) {
+                let t = (h0.w - epsilon) / (h0.w - h1.w);
+                let point = Point3D::new(
+                    h0.x + (h1.x - h0.x) * t,
+                    h0.y + (h1.y - h0.y) * t,
+                    h0.z + (h1.z - h0.z) * t,
+                );
+                points.push(point / epsilon);
+                // leaving the visible region creates an edge along the clip plane
+                edges.push(if h1.w >= epsilon {
+                    EdgeOrigin::Original
+                } else {
+                    EdgeOrigin::Cut
+                });
+            }
+        }
+
+        let mut poly = Self::from_points(points, anchor)?;
+        if edges.contains(&EdgeOrigin::Cut) {
+            poly.edges = edges;
+        }
+        Some(poly)
+    }
+
     /// Construct a polygon from a rectangle with an invertible 3D transform.
-    pub fn from_transformed_rect_with_inverse(
-        rect: Rect<f64>,
-        transform: &Transform3D<f64>,
-        inv_transform: &Transform3D<f64>,
+    pub fn from_transformed_rect_with_inverse<V>(
+        rect: Rect<T, V>,
+        transform: &Transform3D<T, V, U>,
+        inv_transform: &Transform3D<T, U, V>,
         anchor: A,
     ) -> Option<Self> {
         let min = rect.min();
         let max = rect.max();
         let points = [
             transform.transform_point3d(min.to_3d())?,
-            transform.transform_point3d(Point3D::new(max.x, min.y, 0.0))?,
+            transform.transform_point3d(Point3D::new(max.x, min.y, T::zero()))?,
             transform.transform_point3d(max.to_3d())?,
-            transform.transform_point3d(Point3D::new(min.x, max.y, 0.0))?,
+            transform.transform_point3d(Point3D::new(min.x, max.y, T::zero()))?,
         ];
 
         // Compute the normal directly from the transformation. This guarantees consistent polygons
         // generated from various local rectanges on the same geometry plane.
         let normal_raw = Vector3D::new(inv_transform.m13, inv_transform.m23, inv_transform.m33);
         let normal_sql = normal_raw.square_length();
-        if normal_sql.approx_eq(&0.0) || transform.m44.approx_eq(&0.0) {
+        if normal_sql.approx_eq(&T::zero()) || transform.m44.approx_eq(&T::zero()) {
             None
         } else {
             let normal = normal_raw / normal_sql.sqrt();
@@ -222,20 +463,21 @@ where
                 / transform.m44;
 
             Some(Polygon {
-                points,
+                points: SmallVec::from_buf(points),
                 plane: Plane { normal, offset },
                 anchor,
+                edges: SmallVec::new(),
             })
         }
     }
 
     /// Bring a point into the local coordinate space, returning
     /// the 2D normalized coordinates.
-    pub fn untransform_point(&self, point: Point3D<f64>) -> Point2D<f64> {
+    pub fn untransform_point(&self, point: Point3D<T, U>) -> Point2D<T, UnknownUnit> {
         //debug_assert!(self.contains(point));
         // get axises and target vector
         let a = self.points[1] - self.points[0];
-        let b = self.points[3] - self.points[0];
+        let b = self.points[self.points.len() - 1] - self.points[0];
         let c = point - self.points[0];
         // get pair-wise dot products
         let a2 = a.dot(a);
@@ -250,19 +492,109 @@ where
         Point2D::new(x, y) / denom
     }
 
+    /// Map the points back into the original rectangle, which was transformed
+    /// by `transform` to produce this polygon or the one it was split from.
+    /// Returns the coordinates normalized to the [0, 1] range within the rectangle,
+    /// or `None` if the transform is not invertible.
+    pub fn unproject_to_rect<V>(
+        &self,
+        rect: &Rect<T, V>,
+        transform: &Transform3D<T, V, U>,
+    ) -> Option<SmallVec<[Point2D<T, UnknownUnit>; 4]>> {
+        let inverse = transform.inverse()?;
+        self.points
+            .iter()
+            .map(|p| {
+                let local = inverse.transform_point3d(*p)?;
+                Some(Point2D::new(
+                    (local.x - rect.origin.x) / rect.size.width,
+                    (local.y - rect.origin.y) / rect.size.height,
+                ))
+            })
+            .collect()
+    }
+
     /// Transform a polygon by an affine transform (preserving straight lines).
-    pub fn transform(&self, transform: &Transform3D<f64>) -> Option<Polygon<A>> {
-        let mut points = [Point3D::origin(); 4];
-        for (out, point) in points.iter_mut().zip(self.points.iter()) {
+    ///
+    /// The normal is transformed with `Plane::transform`, and keeps following
+    /// the winding order of the points. Returns `None` if the transform isn't
+    /// invertible, e.g. when flattening the polygon, if any of the points ends
+    /// up behind the eye, i.e. at `w <= 0`, or if the result is degenerate.
+    pub fn transform<V>(&self, transform: &Transform3D<T, U, V>) -> Option<Polygon<A, T, V>> {
+        let plane = self.plane.transform(transform)?;
+        let mut points = SmallVec::<[Point3D<T, V>; 4]>::with_capacity(self.points.len());
+        for point in &self.points {
+            let homo = transform.transform_point3d_homogeneous(*point);
+            if homo.w <= T::zero() {
+                return None;
+            }
+            points.push(homo.to_point3d()?);
+        }
+
+        let mut poly = Polygon::from_points(points, self.anchor.clone())?;
+        let normal = if plane.normal.dot(poly.plane.normal) < T::zero() {
+            -plane.normal
+        } else {
+            plane.normal
+        };
+        poly.plane = Plane {
+            normal,
+            offset: -poly.points[0].to_vector().dot(normal),
+        };
+        poly.edges = self.edges.clone();
+        Some(poly)
+    }
+
+    /// Transform a polygon that was clipped to the positive hemisphere of
+    /// the transform, clamping `w` of the points on its boundary.
+    ///
+    /// The plane is computed from the transformed points, so unlike
+    /// `transform` this also works for the non-invertible transforms.
+    pub(crate) fn transform_clipped<V>(
+        &self,
+        transform: &Transform3D<T, U, V>,
+    ) -> Option<Polygon<A, T, V>> {
+        let mut points = SmallVec::<[Point3D<T, V>; 4]>::with_capacity(self.points.len());
+        for point in &self.points {
             let mut homo = transform.transform_point3d_homogeneous(*point);
-            homo.w = homo.w.max(f64::approx_epsilon());
-            *out = homo.to_point3d()?;
+            homo.w = homo.w.max(T::approx_epsilon());
+            points.push(homo.to_point3d()?);
         }
 
-        //Note: this code path could be more efficient if we had inverse-transpose
-        //let n4 = transform.transform_point4d(&Point4D::new(0.0, 0.0, T::one(), 0.0));
-        //let normal = Point3D::new(n4.x, n4.y, n4.z);
-        Polygon::from_points(points, self.anchor)
+        let mut poly = Polygon::from_points(points, self.anchor.clone())?;
+        poly.edges = self.edges.clone();
+        Some(poly)
+    }
+
+    /// Translate the polygon by a vector.
+    ///
+    /// The plane offset is computed from the translated points rather than
+    /// adjusted, so that it doesn't lose precision when the polygon moves
+    /// closer to the origin.
+    pub(crate) fn translate(&self, delta: Vector3D<T, U>) -> Self {
+        let points: SmallVec<[Point3D<T, U>; 4]> = self.points.iter().map(|p| *p + delta).collect();
+        let offset = match points.first() {
+            Some(first) => -first.to_vector().dot(self.plane.normal),
+            None => self.plane.offset,
+        };
+        Polygon {
+            points,
+            plane: Plane {
+                normal: self.plane.normal,
+                offset,
+            },
+            anchor: self.anchor.clone(),
+            edges: self.edges.clone(),
+        }
+    }
+
+    /// Get the origin of the edge going from the point with
+    /// the specified index to the next one.
+    pub fn edge_origin(&self, index: usize) -> EdgeOrigin {
+        self.edges
+            .get(index)
+            .cloned()
+            .unwrap_or(EdgeOrigin::Original)
     }
 
     /// Check if all the points are indeed placed on the plane defined by
@@ -272,53 +604,345 @@ where
             .points
             .iter()
             .all(|p| is_zero(self.plane.signed_distance_to(p)));
-        let edges = [
-            self.points[1] - self.points[0],
-            self.points[2] - self.points[1],
-            self.points[3] - self.points[2],
-            self.points[0] - self.points[3],
-        ];
-        let anchor = edges[3].cross(edges[0]);
+        let count = self.points.len();
+        if count < 3 {
+            return false;
+        }
+        let edges: SmallVec<[Vector3D<T, U>; 4]> = (0..count)
+            .map(|i| self.points[(i + 1) % count] - self.points[i])
+            .collect();
+        let anchor = edges[count - 1].cross(edges[0]);
         let is_winding = edges
             .iter()
             .zip(edges[1..].iter())
-            .all(|(a, &b)| a.cross(b).dot(anchor) >= 0.0);
+            .all(|(a, &b)| a.cross(b).dot(anchor) >= T::zero());
         is_planar && is_winding
     }
 
+    /// Get the coordinates of the points in the 2D space of the plane,
+    /// along the axes given by `Plane::basis`. The space is centered on
+    /// the point of the plane closest to the origin.
+    pub fn project_to_2d(&self) -> SmallVec<[Point2D<T, U>; 4]> {
+        let (tangent, bitangent) = self.plane.basis();
+        let center = self.plane.normal * -self.plane.offset;
+        self.points
+            .iter()
+            .map(|p| {
+                let v = p.to_vector() - center;
+                Point2D::new(v.dot(tangent), v.dot(bitangent))
+            })
+            .collect()
+    }
+
+    /// Get the point of the plane with the given coordinates in its 2D space,
+    /// which is the inverse of `project_to_2d`.
+    pub fn unproject_from_2d(&self, point: Point2D<T, U>) -> Point3D<T, U> {
+        let (tangent, bitangent) = self.plane.basis();
+        let center = self.plane.normal * -self.plane.offset;
+        (center + tangent * point.x + bitangent * point.y).to_point()
+    }
+
+    /// Get the direction in which the points go around the plane normal.
+    pub fn winding(&self) -> Winding {
+        if self.is_empty() {
+            return Winding::Degenerate;
+        }
+        if self.area_vector().dot(self.plane.normal) > T::zero() {
+            Winding::CounterClockwise
+        } else {
+            Winding::Clockwise
+        }
+    }
+
+    /// Turn the polygon to face the other way, reversing the order
+    /// of the points and negating the plane equation.
+    pub fn flip(&mut self) {
+        self.reverse_points();
+        self.plane.flip();
+    }
+
+    /// Reverse the order of the points, keeping the edge origins.
+    fn reverse_points(&mut self) {
+        self.points.reverse();
+        if !self.edges.is_empty() {
+            // the edge going from the last point to the first stays last
+            let count = self.points.len();
+            self.edges.resize(count, EdgeOrigin::Original);
+            self.edges[..count - 1].reverse();
+        }
+    }
+
+    /// Make the points wind counter-clockwise around the plane normal,
+    /// and the normal point to the same side as `reference`.
+    /// Returns true if the polygon had to be changed.
+    pub fn normalize_winding(&mut self, reference: Vector3D<T, U>) -> bool {
+        let mut changed = false;
+        if self.winding() == Winding::Clockwise {
+            self.reverse_points();
+            changed = true;
+        }
+        if self.plane.normal.dot(reference) < T::zero() {
+            self.flip();
+            changed = true;
+        }
+        changed
+    }
+
+    /// Check the polygon like `is_valid` does, telling what's wrong.
+    /// Unlike `is_valid`, the polygons without area are rejected too.
+    pub fn validate(&self) -> Result<(), PolygonError<T>> {
+        if self.points.len() < 3 {
+            return Err(PolygonError::ZeroArea);
+        }
+        if !is_zero(self.plane.normal.square_length() - T::one()) {
+            return Err(PolygonError::DenormalizedNormal);
+        }
+        for (vertex, point) in self.points.iter().enumerate() {
+            let distance = self.plane.signed_distance_to(point);
+            if !is_zero(distance) {
+                return Err(PolygonError::NonPlanar { vertex, distance });
+            }
+        }
+        let count = self.points.len();
+        let edges: SmallVec<[Vector3D<T, U>; 4]> = (0..count)
+            .map(|i| self.points[(i + 1) % count] - self.points[i])
+            .collect();
+        // the turns are compared with the normal facing the same way as the
+        // area, since the turn at any single point is zero when the point
+        // lies on the line between its neighbours
+        let normal = self.plane.normal;
+        let reference = if self.area_vector().dot(normal) < T::zero() {
+            -normal
+        } else {
+            normal
+        };
+        if let Some(edge) = (0..count).find(|&i| {
+            edges[(i + count - 1) % count]
+                .cross(edges[i])
+                .dot(reference)
+                < T::zero()
+        }) {
+            return Err(PolygonError::NonConvex { edge });
+        }
+        if self.is_empty() {
+            return Err(PolygonError::ZeroArea);
+        }
+        Ok(())
+    }
+
     /// Check if the polygon doesn't contain any space. This may happen
     /// after a sequence of splits, and such polygons should be discarded.
     pub fn is_empty(&self) -> bool {
-        (self.points[0] - self.points[2]).square_length() < f64::EPSILON
-            || (self.points[1] - self.points[3]).square_length() < f64::EPSILON
+        self.is_degenerate(T::epsilon())
+    }
+
+    pub(crate) fn is_degenerate(&self, degeneracy: T) -> bool {
+        self.points.len() < 3 || self.area_vector().square_length() < degeneracy
+    }
+
+    /// Compute the sum of the fan cross products, which is
+    /// perpendicular to the polygon and twice as long as its area.
+    pub(crate) fn area_vector(&self) -> Vector3D<T, U> {
+        let base = match self.points.first() {
+            Some(&base) => base,
+            None => return Vector3D::zero(),
+        };
+        self.points[1..]
+            .iter()
+            .zip(self.points[2..].iter())
+            .fold(Vector3D::zero(), |sum, (&a, &b)| {
+                sum + (a - base).cross(b - base)
+            })
+    }
+
+    /// Compute the area of the polygon.
+    pub fn area(&self) -> T {
+        self.area_vector().length() / (T::one() + T::one())
+    }
+
+    /// Compute the center of mass of the polygon.
+    ///
+    /// For polygons without any area, this is the average of the points.
+    pub fn centroid(&self) -> Point3D<T, U> {
+        let area = self.area_vector();
+        let three = T::one() + T::one() + T::one();
+        let base = match self.points.first() {
+            Some(&base) => base,
+            None => return Point3D::origin(),
+        };
+        let (sum, weight) = self.points[1..].iter().zip(self.points[2..].iter()).fold(
+            (Vector3D::zero(), T::zero()),
+            |(sum, weight), (&a, &b)| {
+                let w = (a - base).cross(b - base).dot(area);
+                let center = (base.to_vector() + a.to_vector() + b.to_vector()) / three;
+                (sum + center * w, weight + w)
+            },
+        );
+        if weight > T::zero() {
+            (sum / weight).to_point()
+        } else {
+            let count = T::from(self.points.len()).unwrap();
+            let sum = self
+                .points
+                .iter()
+                .fold(Vector3D::zero(), |sum, p| sum + p.to_vector());
+            (sum / count).to_point()
+        }
+    }
+
+    /// Compute the axis-aligned bounding box of the polygon.
+    pub fn bounding_box(&self) -> Box3D<T, U> {
+        Box3D::from_points(self.points.iter())
+    }
+
+    /// Compute the range of depths of the polygon points, which is
+    /// the distance along the view vector away from the viewer.
+    /// Returns the nearest and the farthest depths.
+    pub fn depth_range(&self, view: &Vector3D<T, U>) -> (T, T) {
+        let dir = -view.normalize();
+        self.points
+            .iter()
+            .map(|p| p.to_vector().dot(dir))
+            .fold((T::infinity(), T::neg_infinity()), |(min, max), depth| {
+                (min.min(depth), max.max(depth))
+            })
     }
 
     /// Check if this polygon contains another one.
     pub fn contains(&self, other: &Self) -> bool {
-        //TODO: actually check for inside/outside
-        self.plane.contains(&other.plane)
+        self.contains_with_tolerance(other, EpsilonConfig::default().coplanarity)
+    }
+
+    /// Check if this polygon contains another one, allowing the points
+    /// of `other` to be outside by no more than `tolerance` distance.
+    pub fn contains_with_tolerance(&self, other: &Self, tolerance: T) -> bool {
+        other
+            .points
+            .iter()
+            .all(|p| self.contains_point(p, tolerance))
+    }
+
+    /// Check if a point is on this polygon, allowing it
+    /// to be outside by no more than `tolerance` distance.
+    pub(crate) fn contains_point(&self, point: &Point3D<T, U>, tolerance: T) -> bool {
+        if self.plane.signed_distance_to(point).abs() > tolerance || self.points.len() < 3 {
+            return false;
+        }
+
+        // figure out the winding relative to the plane normal
+        let base = self.points[0];
+        let area = self.points[1..]
+            .iter()
+            .zip(self.points[2..].iter())
+            .fold(Vector3D::zero(), |sum, (&a, &b)| {
+                sum + (a - base).cross(b - base)
+            });
+        let normal = if area.dot(self.plane.normal) < T::zero() {
+            -self.plane.normal
+        } else {
+            self.plane.normal
+        };
+
+        let count = self.points.len();
+        (0..count).all(|i| {
+            let a = self.points[i];
+            let edge = self.points[(i + 1) % count] - a;
+            let edge_len = edge.length();
+            edge.cross(*point - a).dot(normal) >= -tolerance * edge_len
+        })
+    }
+
+    /// Clip the polygon by a plane, leaving only the part in front of it.
+    /// Return None if nothing is left.
+    pub(crate) fn clip_by(&self, plane: &Plane<T, U>) -> Option<Self> {
+        let count = self.points.len();
+        let dists: SmallVec<[T; 4]> = self
+            .points
+            .iter()
+            .map(|p| plane.signed_distance_to(p))
+            .collect();
+        if dists.iter().all(|&d| d >= T::zero()) {
+            return Some(self.clone());
+        }
+
+        let mut points = SmallVec::new();
+        let mut edges = SmallVec::new();
+        for i in 0..count {
+            let (d0, d1) = (dists[i], dists[(i + 1) % count]);
+            let (p0, p1) = (self.points[i], self.points[(i + 1) % count]);
+            if d0 >= T::zero() {
+                points.push(p0);
+                edges.push(self.edge_origin(i));
+            }
+            if (d0 >= T::zero()) != (d1 >= T::zero()) {
+                points.push(p0 + (p1 - p0) * (d0 / (d0 - d1)));
+                // leaving the clip region creates an edge along the plane
+                edges.push(if d1 >= T::zero() {
+                    self.edge_origin(i)
+                } else {
+                    EdgeOrigin::Cut
+                });
+            }
+        }
+
+        let poly = Polygon {
+            points,
+            plane: self.plane.clone(),
+            anchor: self.anchor.clone(),
+            edges,
+        };
+        if poly.is_empty() {
+            None
+        } else {
+            Some(poly)
+        }
+    }
+
+    /// Clip the polygon by the frustum planes.
+    /// Return None if the polygon is completely outside.
+    pub fn clip_to_frustum(&self, frustum: &Frustum<T, U>) -> Option<Self> {
+        let mut poly = self.clip_by(&frustum.planes[0])?;
+        for plane in &frustum.planes[1..] {
+            poly = poly.clip_by(plane)?;
+        }
+        Some(poly)
+    }
+
+    /// Clip the polygon by the planes of the region.
+    /// Return None if the polygon is completely outside.
+    pub fn clip_to_region(&self, region: &ClipRegion<T, U>) -> Option<Self> {
+        let mut poly = self.clone();
+        for plane in &region.planes {
+            poly = poly.clip_by(plane)?;
+        }
+        Some(poly)
+    }
+
+    /// Clip the polygon by the six planes of an axis-aligned box.
+    /// Return None if the polygon is completely outside.
+    pub fn clip_to_box(&self, bounds: &Box3D<T, U>) -> Option<Self> {
+        self.clip_to_region(&ClipRegion::from_box(bounds))
     }
 
     /// Project this polygon onto a 3D vector, returning a line projection.
     /// Note: we can think of it as a projection to a ray placed at the origin.
-    pub fn project_on(&self, vector: &Vector3D<f64>) -> LineProjection {
+    pub fn project_on(&self, vector: &Vector3D<T, U>) -> LineProjection<T> {
         LineProjection {
-            markers: [
-                vector.dot(self.points[0].to_vector()),
-                vector.dot(self.points[1].to_vector()),
-                vector.dot(self.points[2].to_vector()),
-                vector.dot(self.points[3].to_vector()),
-            ],
+            markers: self
+                .points
+                .iter()
+                .map(|p| vector.dot(p.to_vector()))
+                .collect(),
         }
     }
 
     /// Compute the line of intersection with an infinite plane.
-    pub fn intersect_plane(&self, other: &Plane) -> Intersection<Line> {
+    pub fn intersect_plane(&self, other: &Plane<T, U>) -> Intersection<Line<T, U>> {
         if other.are_outside(&self.points) {
             log::debug!("\t\tOutside of the plane");
             return Intersection::Outside;
         }
-        match self.plane.intersect(&other) {
+        match self.plane.intersect(other) {
             Some(line) => Intersection::Inside(line),
             None => {
                 log::debug!("\t\tCoplanar");
@@ -328,12 +952,22 @@ where
     }
 
     /// Compute the line of intersection with another polygon.
-    pub fn intersect(&self, other: &Self) -> Intersection<Line> {
+    pub fn intersect(&self, other: &Self) -> Intersection<Line<T, U>> {
+        self.intersect_with_epsilons(other, &EpsilonConfig::default())
+    }
+
+    /// Compute the line of intersection with another polygon,
+    /// using custom thresholds to detect coplanar polygons.
+    pub fn intersect_with_epsilons(
+        &self,
+        other: &Self,
+        epsilons: &EpsilonConfig<T>,
+    ) -> Intersection<Line<T, U>> {
         if self.plane.are_outside(&other.points) || other.plane.are_outside(&self.points) {
             log::debug!("\t\tOne is completely outside of the other");
             return Intersection::Outside;
         }
-        match self.plane.intersect(&other.plane) {
+        match self.plane.intersect_with_epsilons(&other.plane, epsilons) {
             Some(line) => {
                 let self_proj = self.project_on(&line.dir);
                 let other_proj = other.project_on(&line.dir);
@@ -352,95 +986,151 @@ where
         }
     }
 
-    fn split_impl(
-        &mut self,
-        first: (usize, Point3D<f64>),
-        second: (usize, Point3D<f64>),
-    ) -> (Option<Self>, Option<Self>) {
-        //TODO: can be optimized for when the polygon has a redundant 4th vertex
-        //TODO: can be simplified greatly if only working with triangles
-        log::debug!("\t\tReached complex case [{}, {}]", first.0, second.0);
-        let base = first.0;
-        assert!(base < self.points.len());
-        match second.0 - first.0 {
-            1 => {
-                // rect between the cut at the diagonal
-                let other1 = Polygon {
-                    points: [
-                        first.1,
-                        second.1,
-                        self.points[(base + 2) & 3],
-                        self.points[base],
-                    ],
-                    ..self.clone()
-                };
-                // triangle on the near side of the diagonal
-                let other2 = Polygon {
-                    points: [
-                        self.points[(base + 2) & 3],
-                        self.points[(base + 3) & 3],
-                        self.points[base],
-                        self.points[base],
-                    ],
-                    ..self.clone()
-                };
-                // triangle being cut out
-                self.points = [first.1, self.points[(base + 1) & 3], second.1, second.1];
-                (Some(other1), Some(other2))
+    /// Compute the segment where this polygon intersects another one,
+    /// i.e. the intersection line of their planes, limited to the extents
+    /// of both polygons.
+    ///
+    /// Returns `None` if the polygons are coplanar or don't intersect.
+    pub fn intersection_segment(&self, other: &Self) -> Option<LineSegment<T, U>> {
+        let line = self.plane.intersect(&other.plane)?;
+        let range = (-T::infinity(), T::infinity());
+        let (start, end) = other.clip_line(&line, self.clip_line(&line, range)?)?;
+        Some(LineSegment {
+            start: line.origin + line.dir * start,
+            end: line.origin + line.dir * end,
+        })
+    }
+
+    /// Limit the range of a line on the polygon plane to the parts
+    /// inside of the polygon. The range is given in the units of the line
+    /// direction, starting from its origin.
+    fn clip_line(&self, line: &Line<T, U>, range: (T, T)) -> Option<(T, T)> {
+        let (mut start, mut end) = range;
+        let winding = self.area_vector();
+        let dir_length = line.dir.length();
+        let count = self.points.len();
+        for i in 0..count {
+            let (a, b) = (self.points[i], self.points[(i + 1) % count]);
+            // the line point at `t` is inside if `inward.dot(origin + dir * t - a) >= 0`
+            let inward = winding.cross(b - a);
+            let length = inward.length();
+            if length <= T::zero() {
+                continue;
             }
-            2 => {
-                // rect on the far side
-                let other = Polygon {
-                    points: [
-                        first.1,
-                        self.points[(base + 1) & 3],
-                        self.points[(base + 2) & 3],
-                        second.1,
-                    ],
-                    ..self.clone()
-                };
-                // rect on the near side
-                self.points = [
-                    first.1,
-                    second.1,
-                    self.points[(base + 3) & 3],
-                    self.points[base],
-                ];
-                (Some(other), None)
+            // normalized, so that the parallel edges are found at any scale
+            let inward = inward / length;
+            let rate = inward.dot(line.dir);
+            let dist = inward.dot(line.origin - a);
+            if (rate / dir_length).approx_eq(&T::zero()) {
+                if dist < T::zero() {
+                    return None;
+                }
+            } else if rate > T::zero() {
+                start = start.max(-dist / rate);
+            } else {
+                end = end.min(-dist / rate);
             }
-            3 => {
-                // rect between the cut at the diagonal
-                let other1 = Polygon {
-                    points: [
-                        first.1,
-                        self.points[(base + 1) & 3],
-                        self.points[(base + 3) & 3],
-                        second.1,
-                    ],
-                    ..self.clone()
-                };
-                // triangle on the far side of the diagonal
-                let other2 = Polygon {
-                    points: [
-                        self.points[(base + 1) & 3],
-                        self.points[(base + 2) & 3],
-                        self.points[(base + 3) & 3],
-                        self.points[(base + 3) & 3],
-                    ],
-                    ..self.clone()
-                };
-                // triangle being cut out
-                self.points = [first.1, second.1, self.points[base], self.points[base]];
-                (Some(other1), Some(other2))
+        }
+        if start <= end {
+            Some((start, end))
+        } else {
+            None
+        }
+    }
+
+    /// Compute the overlap with a coplanar polygon.
+    ///
+    /// Returns `None` if the polygons are not coplanar, or don't share any area.
+    pub fn intersect_coplanar(&self, other: &Self) -> Option<Self> {
+        self.intersect_coplanar_with_epsilons(other, &EpsilonConfig::default())
+    }
+
+    /// Compute the overlap with a coplanar polygon,
+    /// using custom thresholds to detect coplanar polygons.
+    ///
+    /// The result is a part of `self`, clipped by the edges of `other`.
+    pub fn intersect_coplanar_with_epsilons(
+        &self,
+        other: &Self,
+        epsilons: &EpsilonConfig<T>,
+    ) -> Option<Self> {
+        match self.plane.classify_with_epsilons(other, epsilons) {
+            Classification::Coplanar => {}
+            _ => return None,
+        }
+        // the edge normals point inwards for the winding order of the points
+        let winding = other.area_vector();
+        let count = other.points.len();
+        let mut poly = self.clone();
+        for i in 0..count {
+            let (a, b) = (other.points[i], other.points[(i + 1) % count]);
+            let inward = winding.cross(b - a);
+            let length = inward.length();
+            if length <= T::zero() {
+                continue;
             }
-            _ => panic!("Unexpected indices {} {}", first.0, second.0),
+            let normal = inward / length;
+            let plane = Plane {
+                normal,
+                offset: -a.to_vector().dot(normal),
+            };
+            poly = poly.clip_by(&plane)?;
+        }
+        if poly.is_degenerate(epsilons.degeneracy) {
+            None
+        } else {
+            Some(poly)
         }
     }
 
+    fn split_impl(
+        &mut self,
+        first: (usize, Point3D<T, U>),
+        second: (usize, Point3D<T, U>),
+        degeneracy: T,
+    ) -> Result<(Option<Self>, Option<Self>), SplitError> {
+        log::debug!("\t\tReached complex case [{}, {}]", first.0, second.0);
+        let count = self.points.len();
+        if !(first.0 < count && first.0 < second.0 && second.0 < first.0 + count) {
+            return Err(SplitError::InvalidCut {
+                first: first.0,
+                second: second.0,
+            });
+        }
+        // the part between the first and the second cut
+        let mut other = (SmallVec::new(), SmallVec::new());
+        let first_edge = self.edge_origin(first.0);
+        push_distinct(&mut other, first.1, first_edge, degeneracy);
+        for i in first.0 + 1..=second.0 {
+            let edge = self.edge_origin(i % count);
+            push_distinct(&mut other, self.points[i % count], edge, degeneracy);
+        }
+        push_distinct(&mut other, second.1, EdgeOrigin::Cut, degeneracy);
+        // the part between the second and the first cut
+        let mut this = (SmallVec::new(), SmallVec::new());
+        let second_edge = self.edge_origin(second.0 % count);
+        push_distinct(&mut this, second.1, second_edge, degeneracy);
+        for i in second.0 + 1..=first.0 + count {
+            let edge = self.edge_origin(i % count);
+            push_distinct(&mut this, self.points[i % count], edge, degeneracy);
+        }
+        push_distinct(&mut this, first.1, EdgeOrigin::Cut, degeneracy);
+
+        self.points = this.0;
+        self.edges = this.1;
+        let other = Polygon {
+            points: other.0,
+            plane: self.plane.clone(),
+            anchor: self.anchor.clone(),
+            edges: other.1,
+        };
+        Ok((Some(other), None))
+    }
+
     /// Split the polygon along the specified `Line`.
     /// Will do nothing if the line doesn't belong to the polygon plane.
     #[deprecated(note = "Use split_with_normal instead")]
-    pub fn split(&mut self, line: &Line) -> (Option<Self>, Option<Self>) {
+    pub fn split(&mut self, line: &Line<T, U>) -> (Option<Self>, Option<Self>) {
         log::debug!("\tSplitting");
         // check if the cut is within the polygon plane first
         if !is_zero(self.plane.normal.dot(line.dir))
@@ -454,21 +1144,16 @@ where
             return (None, None);
         }
         // compute the intersection points for each edge
-        let mut cuts = [None; 4];
-        for ((&b, &a), cut) in self
-            .points
-            .iter()
-            .cycle()
-            .skip(1)
-            .zip(self.points.iter())
-            .zip(cuts.iter_mut())
-        {
-            if let Some(t) = line.intersect_edge(a..b) {
-                if t >= 0.0 && t < 1.0 {
-                    *cut = Some(a + (b - a) * t);
+        let count = self.points.len();
+        let cuts: SmallVec<[Option<Point3D<T, U>>; 4]> = (0..count)
+            .map(|i| {
+                let (a, b) = (self.points[i], self.points[(i + 1) % count]);
+                match line.intersect_edge(a..b) {
+                    Some(t) if (T::zero()..T::one()).contains(&t) => Some(a + (b - a) * t),
+                    _ => None,
                 }
-            }
-        }
+            })
+            .collect();
 
         let first = match cuts.iter().position(|c| c.is_some()) {
             Some(pos) => pos,
@@ -481,37 +1166,68 @@ where
         self.split_impl(
             (first, cuts[first].unwrap()),
             (second, cuts[second].unwrap()),
+            T::epsilon(),
         )
+        .unwrap_or((None, None))
     }
 
     /// Split the polygon along the specified `Line`, with a normal to the split line provided.
     /// This is useful when called by the plane splitter, since the other plane's normal
     /// forms the side direction here, and figuring out the actual line of split isn't needed.
     /// Will do nothing if the line doesn't belong to the polygon plane.
+    ///
+    /// A convex polygon is always split into two convex parts: one of them replaces `self`,
+    /// and the other one is returned as the first value. The second value is always `None`.
     pub fn split_with_normal(
         &mut self,
-        line: &Line,
-        normal: &Vector3D<f64>,
+        line: &Line<T, U>,
+        normal: &Vector3D<T, U>,
     ) -> (Option<Self>, Option<Self>) {
+        self.split_with_epsilons(line, normal, &EpsilonConfig::default())
+    }
+
+    /// Split the polygon along the specified `Line`, with a normal to the split line provided,
+    /// using custom thresholds to weld the cut points with the existing vertices.
+    ///
+    /// The polygon is left as is if it can't be split due to the precision issues.
+    pub fn split_with_epsilons(
+        &mut self,
+        line: &Line<T, U>,
+        normal: &Vector3D<T, U>,
+        epsilons: &EpsilonConfig<T>,
+    ) -> (Option<Self>, Option<Self>) {
+        self.try_split_with_epsilons(line, normal, epsilons)
+            .unwrap_or_else(|e| {
+                log::warn!("Splitting failed: {:?}", e);
+                (None, None)
+            })
+    }
+
+    /// Split the polygon like `split_with_epsilons` does, reporting
+    /// the precision issues as errors. The polygon is unchanged on error.
+    pub fn try_split_with_epsilons(
+        &mut self,
+        line: &Line<T, U>,
+        normal: &Vector3D<T, U>,
+        epsilons: &EpsilonConfig<T>,
+    ) -> Result<(Option<Self>, Option<Self>), SplitError> {
         log::debug!("\tSplitting with normal");
         // figure out which side of the split does each point belong to
-        let mut sides = [0.0; 4];
+        let count = self.points.len();
+        let sides: SmallVec<[T; 4]> = self
+            .points
+            .iter()
+            .map(|point| normal.dot(*point - line.origin))
+            .collect();
         let (mut cut_positive, mut cut_negative) = (None, None);
-        for (side, point) in sides.iter_mut().zip(&self.points) {
-            *side = normal.dot(*point - line.origin);
-        }
         // compute the edge intersection points
-        for (i, ((&side1, point1), (&side0, point0))) in sides[1..]
-            .iter()
-            .chain(iter::once(&sides[0]))
-            .zip(self.points[1..].iter().chain(iter::once(&self.points[0])))
-            .zip(sides.iter().zip(&self.points))
-            .enumerate()
-        {
+        for i in 0..count {
+            let (side0, side1) = (sides[i], sides[(i + 1) % count]);
+            let (point0, point1) = (self.points[i], self.points[(i + 1) % count]);
             // figure out if an edge between 0 and 1 needs to be cut
-            let cut = if side0 < 0.0 && side1 >= 0.0 {
+            let cut = if side0 < T::zero() && side1 >= T::zero() {
                 &mut cut_positive
-            } else if side0 > 0.0 && side1 <= 0.0 {
+            } else if side0 > T::zero() && side1 <= T::zero() {
                 &mut cut_negative
             } else {
                 continue;
@@ -526,94 +1242,450 @@ where
             // sides of intersection are alike, so distances along the [point0, point1] line
             // are proportional to the side vector lengths we just computed: (side0, side1).
             let point =
-                (*point0 * side1.abs() + point1.to_vector() * side0.abs()) / (side0 - side1).abs();
+                (point0 * side1.abs() + point1.to_vector() * side0.abs()) / (side0 - side1).abs();
             if cut.is_some() {
                 // We don't expect that the direction changes more than once, unless
                 // the polygon is close to redundant, and we hit precision issues when
                 // computing the sides.
-                log::warn!("Splitting failed due to precision issues: {:?}", sides);
-                break;
+                log::debug!("\t\tSides of the points: {:?}", sides);
+                return Err(SplitError::TooManyCrossings);
             }
             *cut = Some((i, point));
         }
         // form new polygons
         if let (Some(first), Some(mut second)) = (cut_positive, cut_negative) {
             if second.0 < first.0 {
-                second.0 += 4;
+                second.0 += count;
             }
-            self.split_impl(first, second)
+            self.split_impl(first, second, epsilons.degeneracy)
         } else {
-            (None, None)
+            Ok((None, None))
+        }
+    }
+
+    /// Split the polygon along the specified `LineSegment`, which needs to lie
+    /// on the polygon plane. Will do nothing unless the segment goes all the way
+    /// across the polygon, since partial cuts can't keep the parts convex.
+    ///
+    /// The parts are returned in the same way as `split_with_normal` does.
+    pub fn split_by_segment(
+        &mut self,
+        segment: &LineSegment<T, U>,
+    ) -> (Option<Self>, Option<Self>) {
+        self.split_by_segment_with_epsilons(segment, &EpsilonConfig::default())
+    }
+
+    /// Split the polygon along the specified `LineSegment`, using custom
+    /// thresholds to check the segment is on the plane and reaches the edges.
+    pub fn split_by_segment_with_epsilons(
+        &mut self,
+        segment: &LineSegment<T, U>,
+        epsilons: &EpsilonConfig<T>,
+    ) -> (Option<Self>, Option<Self>) {
+        let line = match segment.line() {
+            Some(line) => line,
+            None => return (None, None),
+        };
+        let on_plane = [segment.start, segment.end]
+            .iter()
+            .all(|p| self.plane.signed_distance_to(p).abs() <= epsilons.coplanarity);
+        if !on_plane {
+            return (None, None);
+        }
+
+        // the cut points need to be within the segment
+        let normal = self.plane.normal.cross(line.dir);
+        let length = (segment.end - segment.start).length();
+        let count = self.points.len();
+        for i in 0..count {
+            let (point0, point1) = (self.points[i], self.points[(i + 1) % count]);
+            let (side0, side1) = (
+                normal.dot(point0 - line.origin),
+                normal.dot(point1 - line.origin),
+            );
+            if (side0 < T::zero()) == (side1 < T::zero()) {
+                continue;
+            }
+            let point =
+                (point0 * side1.abs() + point1.to_vector() * side0.abs()) / (side0 - side1).abs();
+            let along = line.dir.dot(point - line.origin);
+            if along < -epsilons.coplanarity || along > length + epsilons.coplanarity {
+                log::debug!("\tSegment doesn't cross the polygon");
+                return (None, None);
+            }
         }
+
+        self.split_with_epsilons(&line, &normal, epsilons)
     }
 
     /// Cut a polygon with another one.
     ///
     /// Write the resulting polygons in `front` and `back` if the polygon needs to be split.
+    /// At most one polygon is written to each side, so the inline storage is never exceeded.
     pub fn cut(
         &self,
         poly: &Self,
-        front: &mut SmallVec<[Polygon<A>; 2]>,
-        back: &mut SmallVec<[Polygon<A>; 2]>,
+        front: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+        back: &mut SmallVec<[Polygon<A, T, U>; 2]>,
     ) -> PlaneCut {
+        self.cut_with_epsilons(poly, front, back, &EpsilonConfig::default())
+    }
+
+    /// Cut a polygon with another one, using custom thresholds for
+    /// detecting siblings, parallel planes, and empty fragments.
+    ///
+    /// Write the resulting polygons in `front` and `back` if the polygon needs to be split.
+    pub fn cut_with_epsilons(
+        &self,
+        poly: &Self,
+        front: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+        back: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+        epsilons: &EpsilonConfig<T>,
+    ) -> PlaneCut {
+        match self.cut_cow(Cow::Borrowed(poly), front, back, epsilons) {
+            Cut::Sibling(_) => PlaneCut::Sibling,
+            Cut::Done | Cut::Failed(_) => PlaneCut::Cut,
+        }
+    }
+
+    /// Cut a polygon with another one, like `cut_with_epsilons` does,
+    /// moving an owned polygon to its side instead of cloning it when
+    /// it's not split. The polygon is given back if it's a sibling.
+    pub(crate) fn cut_cow<'a>(
+        &self,
+        poly: Cow<'a, Self>,
+        front: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+        back: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+        epsilons: &EpsilonConfig<T>,
+    ) -> Cut<'a, A, T, U> {
         //Note: we treat `self` as a plane, and `poly` as a concrete polygon here
-        let (intersection, dist) = match self.plane.intersect(&poly.plane) {
-            None => {
-                let ndot = self.plane.normal.dot(poly.plane.normal);
-                let dist = self.plane.offset - ndot * poly.plane.offset;
-                (Intersection::Coplanar, dist)
+        match self.plane.classify_with_epsilons(&poly, epsilons) {
+            Classification::Coplanar => return Cut::Sibling(poly),
+            Classification::Front => front.push(poly.into_owned()),
+            Classification::Back => back.push(poly.into_owned()),
+            Classification::Spanning(line) => {
+                let result =
+                    self.plane
+                        .split_spanning(poly.into_owned(), &line, epsilons, front, back);
+                if let Err(e) = result {
+                    return Cut::Failed(e);
+                }
             }
-            Some(_) if self.plane.are_outside(&poly.points[..]) => {
-                //Note: we can't start with `are_outside` because it's subject to FP precision
-                let dist = self.plane.signed_distance_sum_to(&poly);
-                (Intersection::Outside, dist)
+        }
+        Cut::Done
+    }
+
+    /// Returns whether both polygon's planes are parallel.
+    pub fn is_aligned(&self, other: &Self) -> bool {
+        self.plane.normal.dot(other.plane.normal) > T::zero()
+    }
+
+    /// Check if this polygon completely covers another one when both are
+    /// projected along the `view` vector. The depth of the polygons is not considered.
+    pub(crate) fn covers_along(&self, other: &Self, view: &Vector3D<T, U>, epsilon: T) -> bool {
+        let project = view_projection(view);
+        let outline: SmallVec<[Point2D<T, U>; 4]> = self.points.iter().map(&project).collect();
+        let count = outline.len();
+        let area = outline_area(&outline);
+        // edge-on polygons don't cover anything
+        if area.abs() < epsilon {
+            return false;
+        }
+        other.points.iter().map(project).all(|p| {
+            (0..count).all(|i| {
+                let (a, b) = (outline[i], outline[(i + 1) % count]);
+                (b - a).cross(p - a) * area.signum() >= -epsilon
+            })
+        })
+    }
+
+    /// Check if this polygon overlaps another one when both are projected
+    /// along the `view` vector. Polygons merely touching each other
+    /// are not considered overlapping.
+    pub(crate) fn overlaps_along(&self, other: &Self, view: &Vector3D<T, U>, epsilon: T) -> bool {
+        let project = view_projection(view);
+        let a: SmallVec<[Point2D<T, U>; 4]> = self.points.iter().map(&project).collect();
+        let b: SmallVec<[Point2D<T, U>; 4]> = other.points.iter().map(&project).collect();
+        // edge-on polygons don't overlap anything
+        if outline_area(&a).abs() < epsilon || outline_area(&b).abs() < epsilon {
+            return false;
+        }
+        // look for a separating axis among the edge normals
+        let is_separated = |outline: &[Point2D<T, U>]| {
+            (0..outline.len()).any(|i| {
+                let edge = outline[(i + 1) % outline.len()] - outline[i];
+                let axis = Vector2D::new(-edge.y, edge.x).normalize();
+                let range = |points: &[Point2D<T, U>]| {
+                    points
+                        .iter()
+                        .fold((T::infinity(), T::neg_infinity()), |(lo, hi), p| {
+                            let d = p.to_vector().dot(axis);
+                            (lo.min(d), hi.max(d))
+                        })
+                };
+                let (a_min, a_max) = range(&a);
+                let (b_min, b_max) = range(&b);
+                a_max <= b_min + epsilon || b_max <= a_min + epsilon
+            })
+        };
+        !is_separated(&a) && !is_separated(&b)
+    }
+
+    /// Compute the area of the part of another polygon that lies within
+    /// this one, when projected onto the plane of this polygon.
+    pub(crate) fn overlap_area(&self, other: &Self) -> T {
+        let plane = &self.plane;
+        let points = other
+            .points
+            .iter()
+            .map(|&p| p - plane.normal * plane.signed_distance_to(&p));
+        // polygons perpendicular to this one don't cover anything
+        let mut clipped = match Polygon::from_points(points, other.anchor.clone()) {
+            Some(poly) => poly,
+            None => return T::zero(),
+        };
+        let center = self.centroid();
+        let count = self.points.len();
+        for i in 0..count {
+            let (a, b) = (self.points[i], self.points[(i + 1) % count]);
+            let mut inward = plane.normal.cross(b - a);
+            if inward.dot(center - a) < T::zero() {
+                inward = -inward;
             }
-            Some(line) => {
-                //Note: distance isn't relevant here
-                (Intersection::Inside(line), 0.0)
+            if let Some(edge) = Plane::from_point_normal(a, inward) {
+                clipped = match clipped.clip_by(&edge) {
+                    Some(poly) => poly,
+                    None => return T::zero(),
+                };
             }
-        };
+        }
+        clipped.area()
+    }
 
-        match intersection {
-            //Note: we deliberately make the comparison wider than just with T::epsilon().
-            // This is done to avoid mistakenly ordering items that should be on the same
-            // plane but end up slightly different due to the floating point precision.
-            Intersection::Coplanar if is_zero(dist) => PlaneCut::Sibling,
-            Intersection::Coplanar | Intersection::Outside => {
-                if dist > 0.0 {
-                    front.push(poly.clone());
-                } else {
-                    back.push(poly.clone());
+    /// Insert the given points lying in the middle of the polygon edges
+    /// as new vertices, keeping the outline intact.
+    /// Returns true if any points were inserted.
+    pub(crate) fn insert_edge_points(&mut self, points: &[Point3D<T, U>], epsilon: T) -> bool {
+        let count = self.points.len();
+        let mut outline: SmallVec<[Point3D<T, U>; 4]> = SmallVec::new();
+        let mut edges: SmallVec<[EdgeOrigin; 4]> = SmallVec::new();
+        let mut on_edge: SmallVec<[(T, Point3D<T, U>); 2]> = SmallVec::new();
+        for i in 0..count {
+            let (a, b) = (self.points[i], self.points[(i + 1) % count]);
+            let dir = b - a;
+            let length = dir.length();
+            on_edge.clear();
+            if length > epsilon {
+                let dir = dir / length;
+                for &p in points {
+                    let t = (p - a).dot(dir);
+                    if t > epsilon
+                        && t < length - epsilon
+                        && (a + dir * t - p).square_length() <= epsilon * epsilon
+                        && on_edge
+                            .iter()
+                            .all(|&(other, _)| (other - t).abs() > epsilon)
+                    {
+                        on_edge.push((t, p));
+                    }
                 }
+            }
+            on_edge.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap_or(core::cmp::Ordering::Equal));
+            let origin = self.edge_origin(i);
+            outline.push(a);
+            edges.push(origin);
+            for &(_, p) in on_edge.iter() {
+                outline.push(p);
+                edges.push(origin);
+            }
+        }
+        if outline.len() == count {
+            return false;
+        }
+        self.points = outline;
+        self.edges = edges;
+        true
+    }
+
+    /// Merge this polygon with another one lying on the same plane,
+    /// if they share a full edge introduced by a cut and the union is convex.
+    pub(crate) fn merge(&self, other: &Self, epsilon: T) -> Option<Self> {
+        let (n, m) = (self.points.len(), other.points.len());
+        if n < 3
+            || m < 3
+            || !self.is_aligned(other)
+            || (self.plane.offset - other.plane.offset).abs() > epsilon
+        {
+            return None;
+        }
+        let same =
+            |a: Point3D<T, U>, b: Point3D<T, U>| (a - b).square_length() <= epsilon * epsilon;
+        // the shared edge goes in the opposite directions in both polygons
+        let (i, j) = (0..n)
+            .flat_map(|i| (0..m).map(move |j| (i, j)))
+            .find(|&(i, j)| {
+                self.edge_origin(i) == EdgeOrigin::Cut
+                    && other.edge_origin(j) == EdgeOrigin::Cut
+                    && same(self.points[i], other.points[(j + 1) % m])
+                    && same(self.points[(i + 1) % n], other.points[j])
+            })?;
 
-                PlaneCut::Cut
+        let mut points: SmallVec<[Point3D<T, U>; 4]> = SmallVec::new();
+        let mut edges: SmallVec<[EdgeOrigin; 4]> = SmallVec::new();
+        for k in 1..n {
+            points.push(self.points[(i + k) % n]);
+            edges.push(self.edge_origin((i + k) % n));
+        }
+        points.push(self.points[i]);
+        edges.push(other.edge_origin((j + 1) % m));
+        for k in 2..m {
+            points.push(other.points[(j + k) % m]);
+            edges.push(other.edge_origin((j + k) % m));
+        }
+
+        // only the ends of the shared edge can break the convexity,
+        // and they are dropped if the edges around them are collinear
+        let winding = self.area_vector().normalize();
+        for &k in &[n - 1, 0] {
+            let count = points.len();
+            let prev = points[(k + count - 1) % count];
+            let next = points[(k + 1) % count];
+            let (d0, d1) = (points[k] - prev, next - points[k]);
+            let sine = d0.cross(d1).dot(winding) / (d0.length() * d1.length());
+            if sine < -epsilon {
+                return None;
             }
-            Intersection::Inside(line) => {
-                let mut poly = poly.clone();
-                let (res_add1, res_add2) = poly.split_with_normal(&line, &self.plane.normal);
-
-                for sub in iter::once(poly)
-                    .chain(res_add1)
-                    .chain(res_add2)
-                    .filter(|p| !p.is_empty())
-                {
-                    let dist = self.plane.signed_distance_sum_to(&sub);
-                    if dist > 0.0 {
-                        front.push(sub)
-                    } else {
-                        back.push(sub)
-                    }
+            if sine <= epsilon {
+                points.remove(k);
+                edges.remove(k);
+            }
+        }
+
+        Some(Polygon {
+            points,
+            plane: self.plane.clone(),
+            anchor: self.anchor.clone(),
+            edges,
+        })
+    }
+}
+
+/// Return a function projecting points on the plane perpendicular to `view`.
+fn view_projection<T: Float, U>(view: &Vector3D<T, U>) -> impl Fn(&Point3D<T, U>) -> Point2D<T, U> {
+    let (u, v) = orthonormal_basis(&view.normalize());
+    move |p: &Point3D<T, U>| Point2D::new(p.to_vector().dot(u), p.to_vector().dot(v))
+}
+
+/// Compute the doubled signed area of a 2D outline.
+fn outline_area<T: Float, U>(outline: &[Point2D<T, U>]) -> T {
+    let count = outline.len();
+    (0..count).fold(T::zero(), |sum, i| {
+        sum + outline[i]
+            .to_vector()
+            .cross(outline[(i + 1) % count].to_vector())
+    })
+}
+
+/// The parts of a polygon in front of a plane and behind it.
+type SplitParts<A, T, U> = (Vec<Polygon<A, T, U>>, Vec<Polygon<A, T, U>>);
+
+impl<T, U> Plane<T, U>
+where
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    /// Split a polygon by this plane into the parts in front of it
+    /// and behind it. A coplanar polygon goes to the front if it faces
+    /// the same direction as the plane, and to the back otherwise.
+    pub fn split_polygon<A: Clone>(&self, poly: &Polygon<A, T, U>) -> SplitParts<A, T, U> {
+        self.split_polygon_with_epsilons(poly, &EpsilonConfig::default())
+    }
+
+    /// Split a polygon by this plane, using custom thresholds.
+    /// See `split_polygon` for details.
+    pub fn split_polygon_with_epsilons<A: Clone>(
+        &self,
+        poly: &Polygon<A, T, U>,
+        epsilons: &EpsilonConfig<T>,
+    ) -> SplitParts<A, T, U> {
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+        match self.classify_with_epsilons(poly, epsilons) {
+            Classification::Coplanar if self.normal.dot(poly.plane.normal) > T::zero() => {
+                front.push(poly.clone())
+            }
+            Classification::Front => front.push(poly.clone()),
+            Classification::Coplanar | Classification::Back => back.push(poly.clone()),
+            Classification::Spanning(line) => {
+                // the polygon is still put on one side if it can't be split
+                let _ = self.split_spanning(poly.clone(), &line, epsilons, &mut front, &mut back);
+            }
+        }
+        (front, back)
+    }
+
+    /// Split a polygon crossing this plane along the given line,
+    /// dropping the empty pieces.
+    ///
+    /// If the polygon can't be split, it's put whole on the side where
+    /// most of it is, and the error is returned.
+    fn split_spanning<A, F, B>(
+        &self,
+        mut poly: Polygon<A, T, U>,
+        line: &Line<T, U>,
+        epsilons: &EpsilonConfig<T>,
+        front: &mut F,
+        back: &mut B,
+    ) -> Result<(), SplitError>
+    where
+        A: Clone,
+        F: Extend<Polygon<A, T, U>>,
+        B: Extend<Polygon<A, T, U>>,
+    {
+        let (res_add1, res_add2, result) =
+            match poly.try_split_with_epsilons(line, &self.normal, epsilons) {
+                Ok((res_add1, res_add2)) => (res_add1, res_add2, Ok(())),
+                Err(e) => {
+                    log::warn!("Splitting failed: {:?}", e);
+                    (None, None, Err(e))
                 }
+            };
 
-                PlaneCut::Cut
+        for sub in iter::once(poly)
+            .chain(res_add1)
+            .chain(res_add2)
+            .filter(|p| !p.is_degenerate(epsilons.degeneracy))
+        {
+            let dist = self.signed_distance_sum_to(&sub);
+            if dist > T::zero() {
+                front.extend(iter::once(sub))
+            } else {
+                back.extend(iter::once(sub))
             }
         }
+        result
     }
+}
 
-    /// Returns whether both polygon's planes are parallel.
-    pub fn is_aligned(&self, other: &Self) -> bool {
-        self.plane.normal.dot(other.plane.normal) > 0.0
+/// Points of a polygon being built, along with the origins of their edges.
+type Outline<T, U> = (SmallVec<[Point3D<T, U>; 4]>, SmallVec<[EdgeOrigin; 4]>);
+
+/// Push a point into the list, along with the origin of the edge starting at it,
+/// unless it matches the last point. In the latter case, the edge continues
+/// from the last point instead.
+fn push_distinct<T: Float, U>(
+    (points, edges): &mut Outline<T, U>,
+    point: Point3D<T, U>,
+    edge: EdgeOrigin,
+    epsilon: T,
+) {
+    if let Some(last) = points.last() {
+        if (*last - point).square_length() < epsilon {
+            *edges.last_mut().unwrap() = edge;
+            return;
+        }
     }
+    points.push(point);
+    edges.push(edge);
 }
 
 /// The result of a polygon being cut by a plane.
@@ -629,11 +1701,58 @@ pub enum PlaneCut {
     Cut,
 }
 
+/// An error splitting a polygon, caused by the precision issues
+/// with nearly degenerate polygons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SplitError {
+    /// The edges of the polygon cross the split line more than twice.
+    TooManyCrossings,
+    /// The cut edges don't split the polygon into two parts.
+    InvalidCut {
+        /// Index of the first cut edge.
+        first: usize,
+        /// Index of the second cut edge.
+        second: usize,
+    },
+}
+
+/// A problem with a polygon, found by `Polygon::validate`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PolygonError<T = f64> {
+    /// The polygon has less than 3 points, or doesn't contain any space.
+    ZeroArea,
+    /// The normal of the polygon plane isn't of unit length.
+    DenormalizedNormal,
+    /// A point doesn't lie on the polygon plane.
+    NonPlanar {
+        /// Index of the point.
+        vertex: usize,
+        /// Signed distance from the plane to the point.
+        distance: T,
+    },
+    /// The polygon turns the wrong way at the start of an edge,
+    /// so it's either not convex, or its winding is inconsistent.
+    NonConvex {
+        /// Index of the edge, which is the index of its first point.
+        edge: usize,
+    },
+}
+
+/// The result of cutting a polygon, as seen by the splitters.
+pub(crate) enum Cut<'a, A: Clone, T: Copy, U> {
+    /// The polygon lies on the plane, and is given back.
+    Sibling(Cow<'a, Polygon<A, T, U>>),
+    /// The polygon is written to the sides, split if needed.
+    Done,
+    /// The polygon couldn't be split, and is written whole to one side.
+    Failed(SplitError),
+}
+
 #[test]
 fn test_split_precision() {
     // regression test for https://bugzilla.mozilla.org/show_bug.cgi?id=1678454
     let mut polygon = Polygon::<()> {
-        points: [
+        points: smallvec![
             Point3D::new(300.0102, 150.00958, 0.0),
             Point3D::new(606.0, 306.0, 0.0),
             Point3D::new(300.21954, 150.11946, 0.0),
@@ -644,6 +1763,7 @@ fn test_split_precision() {
             offset: 0.0,
         },
         anchor: (),
+        edges: SmallVec::new(),
     };
     let line = Line {
         origin: Point3D::new(3.0690663, -5.8472385, 0.0),
@@ -652,3 +1772,31 @@ fn test_split_precision() {
     let normal = Vector3D::new(0.46474662, -0.8854434, -0.0006389789);
     polygon.split_with_normal(&line, &normal);
 }
+
+#[test]
+fn test_split_precision_error() {
+    // the polygon of the regression test above can't be split
+    let mut polygon = Polygon::<()> {
+        points: smallvec![
+            Point3D::new(300.0102, 150.00958, 0.0),
+            Point3D::new(606.0, 306.0, 0.0),
+            Point3D::new(300.21954, 150.11946, 0.0),
+            Point3D::new(300.08844, 150.05064, 0.0),
+        ],
+        plane: Plane {
+            normal: Vector3D::zero(),
+            offset: 0.0,
+        },
+        anchor: (),
+        edges: SmallVec::new(),
+    };
+    let line = Line {
+        origin: Point3D::new(3.0690663, -5.8472385, 0.0),
+        dir: Vector3D::new(0.8854436, 0.46474677, -0.0),
+    };
+    let normal = Vector3D::new(0.46474662, -0.8854434, -0.0006389789);
+    assert_eq!(
+        polygon.try_split_with_epsilons(&line, &normal, &EpsilonConfig::default()),
+        Err(SplitError::TooManyCrossings)
+    );
+}
@@ -0,0 +1,328 @@
+/*!
+Exact geometric predicates and the splitter using them.
+
+The orientation test follows the adaptive approach of J. R. Shewchuk,
+"Adaptive Precision Floating-Point Arithmetic and Fast Robust Geometric
+Predicates": the determinant is evaluated in floating point first, and
+only recomputed with exact expansion arithmetic when its sign can't be
+trusted according to the error bound.
+*/
+
+use crate::{BspSplitter, EdgeOrigin, EpsilonConfig, PlaneCut, Polygon, Splitter};
+
+use euclid::{approxeq::ApproxEq, Point3D, UnknownUnit, Vector3D};
+use num_traits::Float;
+use smallvec::SmallVec;
+
+use alloc::{vec, vec::Vec};
+use core::{cmp::Ordering, fmt};
+
+/// Half of the machine epsilon, i.e. the relative error of a rounding.
+const EPSILON: f64 = f64::EPSILON * 0.5;
+/// Relative error bound of the floating point orientation determinant.
+const ORIENT_BOUND: f64 = (7.0 + 56.0 * EPSILON) * EPSILON;
+
+/// A number represented exactly as a sum of non-overlapping components,
+/// ordered by increasing magnitude.
+type Expansion = Vec<f64>;
+
+fn two_sum(a: f64, b: f64) -> (f64, f64) {
+    let x = a + b;
+    let bv = x - a;
+    let av = x - bv;
+    (x, (a - av) + (b - bv))
+}
+
+fn two_diff(a: f64, b: f64) -> Expansion {
+    let x = a - b;
+    let bv = a - x;
+    let av = x + bv;
+    let y = (a - av) + (bv - b);
+    vec![y, x]
+}
+
+fn two_product(a: f64, b: f64) -> (f64, f64) {
+    let x = a * b;
+    (x, a.mul_add(b, -x))
+}
+
+/// Add a number to an expansion, eliminating the zero components.
+fn grow(e: &[f64], b: f64) -> Expansion {
+    let mut result = Vec::with_capacity(e.len() + 1);
+    let mut q = b;
+    for &component in e {
+        let (sum, error) = two_sum(q, component);
+        if error != 0.0 {
+            result.push(error);
+        }
+        q = sum;
+    }
+    if q != 0.0 || result.is_empty() {
+        result.push(q);
+    }
+    result
+}
+
+fn sum(e: &[f64], f: &[f64]) -> Expansion {
+    f.iter().fold(e.to_vec(), |acc, &b| grow(&acc, b))
+}
+
+fn negate(e: &[f64]) -> Expansion {
+    e.iter().map(|&c| -c).collect()
+}
+
+fn product(e: &[f64], f: &[f64]) -> Expansion {
+    let mut result = vec![0.0];
+    for &b in f {
+        for &a in e {
+            let (x, y) = two_product(a, b);
+            result = grow(&grow(&result, y), x);
+        }
+    }
+    result
+}
+
+fn sign(e: &[f64]) -> Ordering {
+    let last = e.iter().rev().find(|&&c| c != 0.0).cloned().unwrap_or(0.0);
+    last.partial_cmp(&0.0).unwrap_or(Ordering::Equal)
+}
+
+/// Compute the sign of the determinant `(b - a) x (c - a) . (d - a)`,
+/// which is positive if `d` is on the side of the plane `abc`
+/// that its normal `(b - a) x (c - a)` points to.
+pub(crate) fn orient3d(a: [f64; 3], b: [f64; 3], c: [f64; 3], d: [f64; 3]) -> Ordering {
+    let u = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let v = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let w = [d[0] - a[0], d[1] - a[1], d[2] - a[2]];
+    let det = u[0] * (v[1] * w[2] - v[2] * w[1]) - u[1] * (v[0] * w[2] - v[2] * w[0])
+        + u[2] * (v[0] * w[1] - v[1] * w[0]);
+    let permanent = u[0].abs() * ((v[1] * w[2]).abs() + (v[2] * w[1]).abs())
+        + u[1].abs() * ((v[0] * w[2]).abs() + (v[2] * w[0]).abs())
+        + u[2].abs() * ((v[0] * w[1]).abs() + (v[1] * w[0]).abs());
+    if det.abs() > ORIENT_BOUND * permanent {
+        return det.partial_cmp(&0.0).unwrap_or(Ordering::Equal);
+    }
+
+    let diff = |p: [f64; 3], i: usize| two_diff(p[i], a[i]);
+    let (u, v, w) = (
+        [diff(b, 0), diff(b, 1), diff(b, 2)],
+        [diff(c, 0), diff(c, 1), diff(c, 2)],
+        [diff(d, 0), diff(d, 1), diff(d, 2)],
+    );
+    let minor = |i: usize, j: usize| sum(&product(&v[i], &w[j]), &negate(&product(&v[j], &w[i])));
+    let det = sum(
+        &sum(&product(&u[0], &minor(1, 2)), &product(&u[1], &minor(2, 0))),
+        &product(&u[2], &minor(0, 1)),
+    );
+    sign(&det)
+}
+
+fn to_array<T: Float, U>(p: &Point3D<T, U>) -> [f64; 3] {
+    // conversion of f32 and f64 values is exact
+    [
+        p.x.to_f64().unwrap(),
+        p.y.to_f64().unwrap(),
+        p.z.to_f64().unwrap(),
+    ]
+}
+
+impl<A, T, U> Polygon<A, T, U>
+where
+    A: Clone + fmt::Debug,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    /// Pick three points of the polygon that define its plane best,
+    /// i.e. span the largest triangle.
+    fn basis(&self) -> Option<[Point3D<T, U>; 3]> {
+        let a = *self.points.first()?;
+        let mut best = (T::zero(), None);
+        for (i, b) in self.points.iter().enumerate().skip(1) {
+            for c in &self.points[i + 1..] {
+                let area = (*b - a).cross(*c - a).square_length();
+                if area > best.0 {
+                    best = (area, Some([a, *b, *c]));
+                }
+            }
+        }
+        best.1
+    }
+
+    /// Cut another polygon with this one, same as `cut_with_epsilons`,
+    /// but classifying the points of `poly` with exact predicates against
+    /// the plane going through the points of `self`.
+    ///
+    /// Only the positions of the new vertices are computed approximately.
+    pub(crate) fn cut_exact(
+        &self,
+        poly: &Self,
+        front: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+        back: &mut SmallVec<[Polygon<A, T, U>; 2]>,
+        epsilons: &EpsilonConfig<T>,
+    ) -> PlaneCut {
+        let [a, b, c] = match self.basis() {
+            Some(basis) => basis,
+            None => return self.cut_with_epsilons(poly, front, back, epsilons),
+        };
+        // make the signs follow the direction of the plane normal
+        let flip = (b - a).cross(c - a).dot(self.plane.normal) < T::zero();
+        let (a, b, c) = (to_array(&a), to_array(&b), to_array(&c));
+        let sides: SmallVec<[Ordering; 4]> = poly
+            .points
+            .iter()
+            .map(|p| {
+                let side = orient3d(a, b, c, to_array(p));
+                if flip {
+                    side.reverse()
+                } else {
+                    side
+                }
+            })
+            .collect();
+
+        let is_front = sides.contains(&Ordering::Greater);
+        let is_back = sides.contains(&Ordering::Less);
+        match (is_front, is_back) {
+            (false, false) => return PlaneCut::Sibling,
+            (true, false) => {
+                front.push(poly.clone());
+                return PlaneCut::Cut;
+            }
+            (false, true) => {
+                back.push(poly.clone());
+                return PlaneCut::Cut;
+            }
+            (true, true) => {}
+        }
+
+        let count = poly.points.len();
+        let mut parts = [
+            (Ordering::Greater, SmallVec::new(), SmallVec::new()),
+            (Ordering::Less, SmallVec::new(), SmallVec::new()),
+        ];
+        for i in 0..count {
+            let j = (i + 1) % count;
+            let (p0, p1) = (poly.points[i], poly.points[j]);
+            for &mut (side, ref mut points, ref mut edges) in parts.iter_mut() {
+                if sides[i] == side.reverse() {
+                    continue;
+                }
+                points.push(p0);
+                // leaving the side from the plane creates an edge along it
+                edges.push(
+                    if sides[i] == Ordering::Equal && sides[j] == side.reverse() {
+                        EdgeOrigin::Cut
+                    } else {
+                        poly.edge_origin(i)
+                    },
+                );
+            }
+            if sides[i] != Ordering::Equal && sides[j] == sides[i].reverse() {
+                let d0 = self.plane.signed_distance_to(&p0);
+                let d1 = self.plane.signed_distance_to(&p1);
+                let t = if d0 == d1 {
+                    T::one() / (T::one() + T::one())
+                } else {
+                    (d0 / (d0 - d1)).max(T::zero()).min(T::one())
+                };
+                let point = p0 + (p1 - p0) * t;
+                for &mut (side, ref mut points, ref mut edges) in parts.iter_mut() {
+                    points.push(point);
+                    // leaving the side creates an edge along the plane
+                    edges.push(if side == sides[i] {
+                        EdgeOrigin::Cut
+                    } else {
+                        poly.edge_origin(i)
+                    });
+                }
+            }
+        }
+
+        for (side, points, edges) in parts {
+            let sub = Polygon {
+                points,
+                plane: poly.plane.clone(),
+                anchor: poly.anchor.clone(),
+                edges,
+            };
+            if sub.is_degenerate(epsilons.degeneracy) {
+                continue;
+            }
+            match side {
+                Ordering::Greater => front.push(sub),
+                _ => back.push(sub),
+            }
+        }
+        PlaneCut::Cut
+    }
+}
+
+/// BSP splitter classifying the polygons with exact predicates,
+/// which avoids misclassifying the near-degenerate configurations,
+/// such as a plane grazing the edge of another polygon.
+///
+/// Polygons are only considered coplanar if they are exactly so.
+pub struct RobustSplitter<A: Clone, T = f64, U = UnknownUnit> {
+    inner: BspSplitter<A, T, U>,
+}
+
+impl<A, T, U> RobustSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    /// Create a new robust splitter.
+    pub fn new() -> Self {
+        Self::with_epsilons(EpsilonConfig::default())
+    }
+
+    /// Create a new robust splitter with custom thresholds.
+    /// Only the degeneracy threshold is used, to drop the empty fragments.
+    pub fn with_epsilons(epsilons: EpsilonConfig<T>) -> Self {
+        let mut inner = BspSplitter::with_epsilons(epsilons);
+        inner.exact = true;
+        RobustSplitter { inner }
+    }
+}
+
+impl<A, T, U> RobustSplitter<A, T, U>
+where
+    A: Clone,
+{
+    /// Get the underlying BSP splitter.
+    pub fn inner(&self) -> &BspSplitter<A, T, U> {
+        &self.inner
+    }
+
+    /// Get the underlying BSP splitter for configuration.
+    pub fn inner_mut(&mut self) -> &mut BspSplitter<A, T, U> {
+        &mut self.inner
+    }
+}
+
+impl<A, T, U> Default for RobustSplitter<A, T, U>
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A, T, U> Splitter<A, T, U> for RobustSplitter<A, T, U>
+where
+    A: Clone + fmt::Debug + Default,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn reset(&mut self) {
+        self.inner.reset();
+    }
+
+    fn add(&mut self, polygon: Polygon<A, T, U>) {
+        self.inner.add(polygon);
+    }
+
+    fn sort(&mut self, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        self.inner.sort(view)
+    }
+}
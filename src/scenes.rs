@@ -0,0 +1,109 @@
+//! Scene generators modeled after the common uses of 3D transforms
+//! with `transform-style: preserve-3d` in CSS, for benchmarks and stress tests.
+//!
+//! The sizes are in CSS pixels, and the polygons are anchored by their index
+//! in the returned list. The random scenes are a pure function of the seed.
+
+use crate::{
+    testing::{transformed_rect, Rng},
+    Polygon,
+};
+
+use euclid::{
+    default::{Rect, Transform3D},
+    rect, Angle,
+};
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+/// Cards placed around a vertical ring and facing out of it, as in
+/// `rotateY(angle) translateZ(radius)`, e.g. for an image carousel.
+/// The ring is tilted around the X axis by `tilt` radians, as it's
+/// usually seen from slightly above.
+pub fn carousel(count: usize, radius: f64, tilt: f64) -> Vec<Polygon<usize>> {
+    // the neighbor cards touch each other
+    let size = 2.0 * radius * (PI / count.max(3) as f64).tan();
+    let card: Rect<f64> = rect(-0.5 * size, -0.5 * size, size, size);
+    let tilt = Transform3D::rotation(1.0, 0.0, 0.0, Angle::radians(tilt));
+    (0..count)
+        .filter_map(|i| {
+            let angle = Angle::radians(2.0 * PI * i as f64 / count as f64);
+            let transform = Transform3D::translation(0.0, 0.0, radius)
+                .then(&Transform3D::rotation(0.0, 1.0, 0.0, angle))
+                .then(&tilt);
+            Polygon::from_transformed_rect(card, transform, i)
+        })
+        .collect()
+}
+
+/// Cards stacked on top of each other, each turned around the vertical axis
+/// by a random angle up to `max_angle` radians, so that the neighbors
+/// intersect, as in the animated card decks.
+pub fn card_stack(seed: u64, count: usize, max_angle: f64) -> Vec<Polygon<usize>> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .filter_map(|i| {
+            let angle = rng.range(-max_angle, max_angle);
+            let offset = [
+                rng.range(-10.0, 10.0),
+                rng.range(-10.0, 10.0),
+                4.0 * i as f64,
+            ];
+            transformed_rect(200.0, [0.0, angle, 0.0], offset, i)
+        })
+        .collect()
+}
+
+/// The inside of a cube of the given size, e.g. for a panorama, with each
+/// face made of `tiles` x `tiles` squares, as it's often sliced into images.
+pub fn cube_map(size: f64, tiles: usize) -> Vec<Polygon<usize>> {
+    let tile = size / tiles as f64;
+    let faces = [
+        (0.0, 1.0, 0.0, 0.0),
+        (0.0, 1.0, 0.0, FRAC_PI_2),
+        (0.0, 1.0, 0.0, PI),
+        (0.0, 1.0, 0.0, -FRAC_PI_2),
+        (1.0, 0.0, 0.0, FRAC_PI_2),
+        (1.0, 0.0, 0.0, -FRAC_PI_2),
+    ];
+    let mut polys = Vec::with_capacity(6 * tiles * tiles);
+    for &(x, y, z, angle) in &faces {
+        // the far face, rotated into place
+        let transform = Transform3D::translation(0.0, 0.0, -0.5 * size)
+            .then(&Transform3D::rotation(x, y, z, Angle::radians(angle)));
+        for row in 0..tiles {
+            for column in 0..tiles {
+                let origin = (
+                    column as f64 * tile - 0.5 * size,
+                    row as f64 * tile - 0.5 * size,
+                );
+                let rect = rect(origin.0, origin.1, tile, tile);
+                let anchor = polys.len();
+                polys.extend(Polygon::from_transformed_rect(rect, transform, anchor));
+            }
+        }
+    }
+    polys
+}
+
+/// Layers piled up with the given spacing, each randomly shifted and turned
+/// by up to `jitter` radians, as the layers of a page that are nearly
+/// but not exactly coplanar after a chain of transforms.
+pub fn layer_pile(seed: u64, count: usize, spacing: f64, jitter: f64) -> Vec<Polygon<usize>> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .filter_map(|i| {
+            let angles = [
+                rng.range(-jitter, jitter),
+                rng.range(-jitter, jitter),
+                rng.range(-PI, PI) * jitter,
+            ];
+            let offset = [
+                rng.range(-50.0, 50.0),
+                rng.range(-50.0, 50.0),
+                spacing * i as f64 + rng.range(-jitter, jitter),
+            ];
+            transformed_rect(rng.range(100.0, 300.0), angles, offset, i)
+        })
+        .collect()
+}
@@ -0,0 +1,134 @@
+//! Scene generators for tests, benchmarks, and fuzzing.
+//!
+//! Every generator is a pure function of its parameters, so the parameters
+//! can be produced by a property testing framework like proptest or quickcheck.
+//! The produced polygons are anchored by their index in the returned list.
+
+use crate::{make_grid, Polygon};
+
+use euclid::{
+    default::{Rect, Transform3D},
+    point3, rect, vec3, Angle,
+};
+
+/// A small deterministic random number generator (xorshift64*).
+#[derive(Clone, Debug)]
+pub struct Rng(u64);
+
+impl Rng {
+    /// Create a new generator from a seed.
+    pub fn new(seed: u64) -> Self {
+        Rng(seed.max(1))
+    }
+
+    /// Produce the next random number in the [0, 1) range.
+    pub fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        let value = self.0.wrapping_mul(0x2545_F491_4F6C_DD1D);
+        (value >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Produce the next random number in the given range.
+    pub fn range(&mut self, min: f64, max: f64) -> f64 {
+        min + (max - min) * self.next_f64()
+    }
+}
+
+/// A 3D grid of axis-aligned polygons, where each polygon gets split
+/// by all the polygons of the other two orientations.
+pub fn grid(count: usize) -> Vec<Polygon<usize>> {
+    let mut polys = make_grid(count);
+    for (i, poly) in polys.iter_mut().enumerate() {
+        poly.anchor = i;
+    }
+    polys
+}
+
+/// A rectangle rotated around its center by the given Euler angles,
+/// in radians, and then translated.
+pub fn transformed_rect(
+    size: f64,
+    angles: [f64; 3],
+    offset: [f64; 3],
+    anchor: usize,
+) -> Option<Polygon<usize>> {
+    let rect: Rect<f64> = rect(-0.5 * size, -0.5 * size, size, size);
+    let transform = Transform3D::rotation(1.0, 0.0, 0.0, Angle::radians(angles[0]))
+        .then(&Transform3D::rotation(
+            0.0,
+            1.0,
+            0.0,
+            Angle::radians(angles[1]),
+        ))
+        .then(&Transform3D::rotation(
+            0.0,
+            0.0,
+            1.0,
+            Angle::radians(angles[2]),
+        ))
+        .then_translate(vec3(offset[0], offset[1], offset[2]));
+    Polygon::from_transformed_rect(rect, transform, anchor)
+}
+
+/// Randomly rotated and positioned rectangles, which are likely to intersect.
+pub fn random_rects(seed: u64, count: usize) -> Vec<Polygon<usize>> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .filter_map(|i| {
+            let size = rng.range(10.0, 100.0);
+            let angles = [
+                rng.range(-3.0, 3.0),
+                rng.range(-3.0, 3.0),
+                rng.range(-3.0, 3.0),
+            ];
+            let offset = [
+                rng.range(-50.0, 50.0),
+                rng.range(-50.0, 50.0),
+                rng.range(-50.0, 50.0),
+            ];
+            transformed_rect(size, angles, offset, i)
+        })
+        .collect()
+}
+
+/// A stack of parallel rectangles, each tilted by `tilt` radians
+/// relative to the previous one and separated by `spacing`.
+/// Small values produce nearly coplanar polygons.
+pub fn near_coplanar_stack(count: usize, spacing: f64, tilt: f64) -> Vec<Polygon<usize>> {
+    (0..count)
+        .filter_map(|i| {
+            let angles = [tilt * i as f64, 0.0, 0.0];
+            transformed_rect(100.0, angles, [0.0, 0.0, spacing * i as f64], i)
+        })
+        .collect()
+}
+
+/// Rectangles rotated around a common axis, all intersecting along it.
+pub fn intersecting_fan(count: usize) -> Vec<Polygon<usize>> {
+    (0..count)
+        .filter_map(|i| {
+            let angle = std::f64::consts::PI * i as f64 / count as f64;
+            transformed_rect(100.0, [0.0, angle, 0.0], [0.0; 3], i)
+        })
+        .collect()
+}
+
+/// Long thin triangles of the given width, crossing each other.
+pub fn slivers(count: usize, width: f64) -> Vec<Polygon<usize>> {
+    (0..count)
+        .filter_map(|i| {
+            let t = i as f64 / count as f64;
+            let z = 10.0 * t - 5.0;
+            Polygon::from_points(
+                vec![
+                    point3(-50.0, -width, -z),
+                    point3(50.0, width * t, z),
+                    point3(-50.0, width, -z),
+                ],
+                i,
+            )
+        })
+        .collect()
+}
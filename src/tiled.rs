@@ -0,0 +1,146 @@
+use crate::{BspSplitter, ClipRegion, EpsilonConfig, Polygon};
+
+use euclid::{approxeq::ApproxEq, Rect, Size2D, Transform3D, UnknownUnit, Vector2D, Vector3D};
+use num_traits::Float;
+
+use alloc::vec::Vec;
+use core::{fmt, ops::Range};
+
+/// A splitter dividing the view space into a grid of tiles,
+/// with a separate BSP tree per tile.
+///
+/// Every polygon is clipped by the tiles it overlaps, and the pieces
+/// are split and sorted only against the others in the same tile.
+/// This bounds the number of fragments a polygon can produce by the
+/// contents of a tile, rather than of the whole scene.
+///
+/// The tiles are columns along the Z axis, so the polygons are expected
+/// in the view space, where the grid is laid out in X and Y.
+/// Anything outside of the grid is clipped away.
+pub struct TiledSplitter<A: Clone, T = f64, U = UnknownUnit> {
+    bounds: Rect<T, U>,
+    columns: usize,
+    rows: usize,
+    regions: Vec<ClipRegion<T, U>>,
+    tiles: Vec<BspSplitter<A, T, U>>,
+}
+
+impl<A, T, U> TiledSplitter<A, T, U>
+where
+    A: Clone + fmt::Debug + Default,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    /// Create a new tiled splitter, dividing the `bounds` into
+    /// a grid of `columns` by `rows` tiles.
+    pub fn new(bounds: Rect<T, U>, columns: usize, rows: usize) -> Self {
+        Self::with_epsilons(bounds, columns, rows, EpsilonConfig::default())
+    }
+
+    /// Create a new tiled splitter, with the thresholds
+    /// used by the BSP splitter of every tile.
+    pub fn with_epsilons(
+        bounds: Rect<T, U>,
+        columns: usize,
+        rows: usize,
+        epsilons: EpsilonConfig<T>,
+    ) -> Self {
+        let mut splitter = TiledSplitter {
+            bounds,
+            columns,
+            rows,
+            regions: Vec::with_capacity(columns * rows),
+            tiles: Vec::with_capacity(columns * rows),
+        };
+        for index in 0..columns * rows {
+            let rect = splitter.tile_rect(index);
+            let region = ClipRegion::from_rect(&rect, &Transform3D::identity())
+                .expect("identity is invertible");
+            splitter.regions.push(region);
+            splitter.tiles.push(BspSplitter::with_epsilons(epsilons));
+        }
+        splitter
+    }
+
+    /// Get the number of tiles.
+    pub fn tile_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Get the rectangle covered by the tile with the given index,
+    /// going row by row.
+    pub fn tile_rect(&self, index: usize) -> Rect<T, U> {
+        let size = Size2D::new(
+            self.bounds.size.width / T::from(self.columns).unwrap(),
+            self.bounds.size.height / T::from(self.rows).unwrap(),
+        );
+        let (column, row) = (index % self.columns, index / self.columns);
+        let offset = Vector2D::new(
+            size.width * T::from(column).unwrap(),
+            size.height * T::from(row).unwrap(),
+        );
+        Rect::new(self.bounds.origin + offset, size)
+    }
+
+    /// Get the BSP splitter of a tile.
+    pub fn tile(&self, index: usize) -> &BspSplitter<A, T, U> {
+        &self.tiles[index]
+    }
+
+    /// Get the BSP splitter of a tile for configuration.
+    pub fn tile_mut(&mut self, index: usize) -> &mut BspSplitter<A, T, U> {
+        &mut self.tiles[index]
+    }
+
+    /// Put all the tiles back in their initial state.
+    pub fn reset(&mut self) {
+        for tile in &mut self.tiles {
+            tile.reset();
+        }
+    }
+
+    /// Add a polygon to every tile it overlaps, clipped by the tile.
+    pub fn add(&mut self, poly: Polygon<A, T, U>) {
+        let bounds = poly.bounding_box();
+        let (origin, size) = (self.bounds.origin, self.bounds.size);
+        let columns = span(
+            bounds.min.x - origin.x,
+            bounds.max.x - origin.x,
+            size.width,
+            self.columns,
+        );
+        let rows = span(
+            bounds.min.y - origin.y,
+            bounds.max.y - origin.y,
+            size.height,
+            self.rows,
+        );
+        for row in rows {
+            for column in columns.clone() {
+                let index = row * self.columns + column;
+                self.tiles[index].add_clipped(poly.clone(), &self.regions[index]);
+            }
+        }
+    }
+
+    /// Sort the contents of a single tile against the view vector.
+    pub fn sort_tile(&mut self, index: usize, view: Vector3D<T, U>) -> &[Polygon<A, T, U>] {
+        self.tiles[index].sort(view)
+    }
+
+    /// Sort the contents of every tile against the view vector,
+    /// returning the sorted lists in the order of the tiles.
+    pub fn sort(&mut self, view: Vector3D<T, U>) -> impl Iterator<Item = &[Polygon<A, T, U>]> + '_ {
+        self.tiles.iter_mut().map(move |tile| tile.sort(view))
+    }
+}
+
+/// Get the range of the tiles along an axis of the given length,
+/// which is overlapped by the `min..max` interval.
+fn span<T: Float>(min: T, max: T, length: T, count: usize) -> Range<usize> {
+    let count_t = T::from(count).unwrap();
+    let scale = count_t / length;
+    let clamp = |value: T| value.max(T::zero()).min(count_t);
+    let start = clamp((min * scale).floor());
+    let end = clamp((max * scale).ceil());
+    start.to_usize().unwrap_or(0)..end.to_usize().unwrap_or(0)
+}
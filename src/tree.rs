@@ -0,0 +1,239 @@
+use crate::{EpsilonConfig, PlaneCut, Polygon};
+
+use euclid::approxeq::ApproxEq;
+use num_traits::Float;
+use smallvec::SmallVec;
+
+use alloc::{boxed::Box, vec, vec::Vec};
+use core::fmt;
+
+/// A plane, or a shape lying on a plane, that can be stored in a `BspNode`.
+pub trait BspPlane: Sized {
+    /// Cut another plane with this one.
+    ///
+    /// Returns `PlaneCut::Sibling` if `other` lies on this plane,
+    /// otherwise writes the parts of `other` on each side
+    /// into `front` and `back`.
+    fn cut(
+        &self,
+        other: &Self,
+        front: &mut SmallVec<[Self; 2]>,
+        back: &mut SmallVec<[Self; 2]>,
+    ) -> PlaneCut;
+
+    /// Check if the other plane faces the same direction as this one.
+    fn is_aligned(&self, other: &Self) -> bool;
+}
+
+impl<A, T, U> BspPlane for Polygon<A, T, U>
+where
+    A: Clone + fmt::Debug,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    fn cut(
+        &self,
+        other: &Self,
+        front: &mut SmallVec<[Self; 2]>,
+        back: &mut SmallVec<[Self; 2]>,
+    ) -> PlaneCut {
+        self.cut_with_epsilons(other, front, back, &EpsilonConfig::default())
+    }
+
+    fn is_aligned(&self, other: &Self) -> bool {
+        Polygon::is_aligned(self, other)
+    }
+}
+
+/// A step of the tree traversal.
+enum Visit<'a, P> {
+    /// Choose the order of the node children.
+    Node(&'a BspNode<P>),
+    /// Output the values of the node.
+    Values(&'a BspNode<P>),
+}
+
+/// A node of a BSP tree, which is a tree itself.
+///
+/// The first value inserted into a node defines its plane, the following
+/// values on the same plane are stored next to it, and the rest go
+/// to the `front` and `back` sub-trees, split by the plane if needed.
+///
+/// Unlike `BspSplitter`, this works with any plane type.
+/// A tree of polygons can be converted from and to a `BspSplitter`
+/// with `BspSplitter::to_tree` and `BspSplitter::from_tree`.
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BspNode<P> {
+    pub(crate) values: Vec<P>,
+    pub(crate) front: Option<Box<BspNode<P>>>,
+    pub(crate) back: Option<Box<BspNode<P>>>,
+}
+
+impl<P> BspNode<P> {
+    /// Create a new empty node.
+    pub fn new() -> Self {
+        BspNode {
+            values: Vec::new(),
+            front: None,
+            back: None,
+        }
+    }
+
+    /// Check if the node has no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Get the values lying on the plane of the node,
+    /// in the order they were inserted.
+    pub fn values(&self) -> &[P] {
+        &self.values
+    }
+
+    /// Get the sub-tree in front of the node plane.
+    pub fn front(&self) -> Option<&BspNode<P>> {
+        self.front.as_deref()
+    }
+
+    /// Get the sub-tree behind the node plane.
+    pub fn back(&self) -> Option<&BspNode<P>> {
+        self.back.as_deref()
+    }
+
+    /// Get the length of the longest path from this node to a leaf.
+    pub fn depth(&self) -> usize {
+        if self.values.is_empty() {
+            return 0;
+        }
+        let mut max_depth = 0;
+        let mut stack = vec![(self, 1)];
+        while let Some((node, depth)) = stack.pop() {
+            max_depth = max_depth.max(depth);
+            stack.extend(
+                node.front()
+                    .into_iter()
+                    .chain(node.back())
+                    .map(|n| (n, depth + 1)),
+            );
+        }
+        max_depth
+    }
+}
+
+impl<P: BspPlane> BspNode<P> {
+    /// Insert a value into the sub-tree starting with this node,
+    /// splitting it by the planes of the nodes on the way if needed.
+    pub fn insert(&mut self, value: P) {
+        let mut stack = vec![(self, vec![value])];
+        while let Some((node, values)) = stack.pop() {
+            let mut front_values = Vec::new();
+            let mut back_values = Vec::new();
+            for value in values {
+                let first = match node.values.first() {
+                    Some(first) => first,
+                    None => {
+                        node.values.push(value);
+                        continue;
+                    }
+                };
+                let mut front: SmallVec<[P; 2]> = SmallVec::new();
+                let mut back: SmallVec<[P; 2]> = SmallVec::new();
+                match first.cut(&value, &mut front, &mut back) {
+                    PlaneCut::Sibling => node.values.push(value),
+                    PlaneCut::Cut => {
+                        front_values.extend(front);
+                        back_values.extend(back);
+                    }
+                }
+            }
+
+            let BspNode {
+                ref mut front,
+                ref mut back,
+                ..
+            } = *node;
+            if !back_values.is_empty() {
+                let child = back.get_or_insert_with(|| Box::new(BspNode::new()));
+                stack.push((child, back_values));
+            }
+            if !front_values.is_empty() {
+                let child = front.get_or_insert_with(|| Box::new(BspNode::new()));
+                stack.push((child, front_values));
+            }
+        }
+    }
+
+    /// Build the draw order of this sub-tree into an `out` vector,
+    /// so that the contained planes are sorted back to front according
+    /// to the view vector defined as the `base` plane front direction.
+    pub fn order(&self, base: &P, out: &mut Vec<P>)
+    where
+        P: Clone,
+    {
+        let mut stack = vec![Visit::Node(self)];
+        while let Some(visit) = stack.pop() {
+            match visit {
+                Visit::Node(node) => {
+                    let (former, latter) = match node.values.first() {
+                        None => continue,
+                        Some(first) if base.is_aligned(first) => (node.front(), node.back()),
+                        Some(_) => (node.back(), node.front()),
+                    };
+                    // the stack is processed in reverse
+                    stack.extend(latter.map(Visit::Node));
+                    stack.push(Visit::Values(node));
+                    stack.extend(former.map(Visit::Node));
+                }
+                Visit::Values(node) => out.extend_from_slice(&node.values),
+            }
+        }
+    }
+}
+
+/// Shows the tree indented by the depth, with a line for each value
+/// and the front sub-tree before the back one. The first value of
+/// a node defines its plane.
+impl<P: fmt::Display> fmt::Display for BspNode<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut stack = vec![(self, 0, "root")];
+        while let Some((node, depth, side)) = stack.pop() {
+            let indent = 2 * depth;
+            let count = node.values.len();
+            let plural = if count == 1 { "" } else { "s" };
+            writeln!(f, "{:indent$}{}: {} value{}", "", side, count, plural)?;
+            for value in &node.values {
+                writeln!(f, "{:indent$}  {}", "", value)?;
+            }
+            if let Some(ref back) = node.back {
+                stack.push((back, depth + 1, "back"));
+            }
+            if let Some(ref front) = node.front {
+                stack.push((front, depth + 1, "front"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<P> Default for BspNode<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P> Drop for BspNode<P> {
+    fn drop(&mut self) {
+        // take the children apart, so that deep trees
+        // don't overflow the call stack
+        let mut stack: Vec<Box<BspNode<P>>> = self
+            .front
+            .take()
+            .into_iter()
+            .chain(self.back.take())
+            .collect();
+        while let Some(mut node) = stack.pop() {
+            stack.extend(node.front.take());
+            stack.extend(node.back.take());
+        }
+    }
+}
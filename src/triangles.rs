@@ -0,0 +1,162 @@
+use crate::Polygon;
+
+use euclid::{Point3D, UnknownUnit};
+use num_traits::Float;
+
+use alloc::{collections::BTreeMap, vec::Vec};
+use core::{fmt, ops::Range};
+
+/// Triangulated polygons, ready to be uploaded to the GPU.
+pub struct TriangleBuffer<A, T = f64, U = UnknownUnit> {
+    /// Unique vertices shared by the triangles.
+    pub vertices: Vec<Point3D<T, U>>,
+    /// Vertex indices, three per triangle.
+    pub indices: Vec<u32>,
+    /// Ranges of `indices` covering consecutive polygons
+    /// with the same anchor, in the draw order.
+    pub ranges: Vec<(A, Range<usize>)>,
+}
+
+impl<A: Clone, T: Copy, U> Clone for TriangleBuffer<A, T, U> {
+    fn clone(&self) -> Self {
+        TriangleBuffer {
+            vertices: self.vertices.clone(),
+            indices: self.indices.clone(),
+            ranges: self.ranges.clone(),
+        }
+    }
+}
+
+impl<A: fmt::Debug, T: fmt::Debug, U> fmt::Debug for TriangleBuffer<A, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TriangleBuffer")
+            .field("vertices", &self.vertices)
+            .field("indices", &self.indices)
+            .field("ranges", &self.ranges)
+            .finish()
+    }
+}
+
+impl<A: PartialEq, T: PartialEq, U> PartialEq for TriangleBuffer<A, T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.vertices == other.vertices
+            && self.indices == other.indices
+            && self.ranges == other.ranges
+    }
+}
+
+impl<A, T, U> TriangleBuffer<A, T, U>
+where
+    A: Clone + PartialEq,
+    T: Float,
+{
+    /// Triangulate the convex polygons as fans, keeping their order.
+    pub fn from_polygons(polygons: &[Polygon<A, T, U>]) -> Self {
+        let mut buffer = TriangleBuffer {
+            vertices: Vec::new(),
+            indices: Vec::new(),
+            ranges: Vec::new(),
+        };
+        let mut lookup = BTreeMap::new();
+        let mut fan = Vec::new();
+
+        for poly in polygons {
+            fan.clear();
+            for point in &poly.points {
+                let vertices = &mut buffer.vertices;
+                let key = [
+                    point.x.integer_decode(),
+                    point.y.integer_decode(),
+                    point.z.integer_decode(),
+                ];
+                fan.push(*lookup.entry(key).or_insert_with(|| {
+                    vertices.push(*point);
+                    vertices.len() as u32 - 1
+                }));
+            }
+
+            let start = buffer.indices.len();
+            for i in 2..fan.len() {
+                buffer
+                    .indices
+                    .extend_from_slice(&[fan[0], fan[i - 1], fan[i]]);
+            }
+            let end = buffer.indices.len();
+            match buffer.ranges.last_mut() {
+                Some((anchor, range)) if *anchor == poly.anchor => range.end = end,
+                _ => buffer.ranges.push((poly.anchor.clone(), start..end)),
+            }
+        }
+
+        buffer
+    }
+}
+
+/// Polygons packed into flat arrays, ready to be copied
+/// into a vertex buffer.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FlatOutput<A, P = f32> {
+    /// Coordinates of the points, three per point, going through
+    /// the polygons in the draw order.
+    pub positions: Vec<P>,
+    /// Number of points of each polygon.
+    pub vertex_counts: Vec<u8>,
+    /// Anchor of each polygon.
+    pub anchors: Vec<A>,
+}
+
+impl<A, P> FlatOutput<A, P>
+where
+    A: Clone,
+    P: Float,
+{
+    /// Maximum number of points of a packed polygon.
+    pub const MAX_POINTS: usize = u8::MAX as usize;
+
+    /// Pack the polygons, converting the coordinates to `P`.
+    ///
+    /// The coordinates that can't be represented by `P` become NaN.
+    /// A polygon with more than `MAX_POINTS` points is packed as
+    /// several fans around its first point, with the same anchor.
+    pub fn from_polygons<T: Float, U>(polygons: &[Polygon<A, T, U>]) -> Self {
+        let points = polygons.iter().map(|p| p.points.len()).sum::<usize>();
+        let mut output = FlatOutput {
+            positions: Vec::with_capacity(points * 3),
+            vertex_counts: Vec::with_capacity(polygons.len()),
+            anchors: Vec::with_capacity(polygons.len()),
+        };
+
+        for poly in polygons {
+            let count = poly.points.len();
+            if count <= Self::MAX_POINTS {
+                output.push(&poly.anchor, &[], &poly.points);
+                continue;
+            }
+            // consecutive fans share an edge with the previous one
+            let mut start = 1;
+            loop {
+                let end = (start + Self::MAX_POINTS - 1).min(count);
+                output.push(&poly.anchor, &poly.points[..1], &poly.points[start..end]);
+                if end == count {
+                    break;
+                }
+                start = end - 1;
+            }
+        }
+
+        output
+    }
+
+    fn push<T: Float, U>(&mut self, anchor: &A, apex: &[Point3D<T, U>], rest: &[Point3D<T, U>]) {
+        let convert = |value: T| P::from(value).unwrap_or_else(P::nan);
+        self.vertex_counts.push((apex.len() + rest.len()) as u8);
+        self.anchors.push(anchor.clone());
+        for point in apex.iter().chain(rest) {
+            self.positions.extend_from_slice(&[
+                convert(point.x),
+                convert(point.y),
+                convert(point.z),
+            ]);
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{EpsilonConfig, Plane, Polygon};
+
+use euclid::{approxeq::ApproxEq, Point3D, UnknownUnit, Vector3D};
+use num_traits::Float;
+
+use core::{cmp::Ordering, fmt};
+
+/// A violation of the invariants of the sorted output,
+/// identified by the positions of the offending polygons.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The polygon points don't lie on its plane.
+    NonPlanar(usize),
+    /// The polygon is not convex.
+    NonConvex(usize),
+    /// Two polygons intersect each other.
+    Intersecting(usize, usize),
+    /// The first polygon is drawn before the second one, but it occludes it.
+    WrongOrder(usize, usize),
+}
+
+/// A pair of polygons drawn in the wrong order, found by `verify_order`.
+pub struct OrderViolation<A, T = f64, U = UnknownUnit> {
+    /// Position of the polygon drawn first.
+    pub former: usize,
+    /// Position of the polygon drawn after it, while being behind it.
+    pub latter: usize,
+    /// Anchor of the polygon drawn first.
+    pub former_anchor: A,
+    /// Anchor of the polygon drawn after it.
+    pub latter_anchor: A,
+    /// The part of the former polygon that the latter one covers on the screen,
+    /// unless it's too thin to be represented.
+    pub overlap: Option<Polygon<A, T, U>>,
+}
+
+impl<A: fmt::Debug, T: fmt::Debug, U> fmt::Debug for OrderViolation<A, T, U> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OrderViolation")
+            .field("former", &self.former)
+            .field("latter", &self.latter)
+            .field("former_anchor", &self.former_anchor)
+            .field("latter_anchor", &self.latter_anchor)
+            .field("overlap", &self.overlap)
+            .finish()
+    }
+}
+
+impl<A: PartialEq, T: PartialEq, U> PartialEq for OrderViolation<A, T, U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.former == other.former
+            && self.latter == other.latter
+            && self.former_anchor == other.former_anchor
+            && self.latter_anchor == other.latter_anchor
+            && self.overlap == other.overlap
+    }
+}
+
+/// Check that every pair of the polygons overlapping on the screen is
+/// ordered back to front for the given view vector, pointing towards
+/// the viewer, as the painter's algorithm needs.
+///
+/// Unlike `validate`, this doesn't check the shape of the polygons,
+/// and reports the first violation with the anchors and the overlap.
+/// This is quadratic in the number of polygons, so it's meant for debugging.
+pub fn verify_order<A, T, U>(
+    fragments: &[Polygon<A, T, U>],
+    view: Vector3D<T, U>,
+) -> Result<(), OrderViolation<A, T, U>>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let eps = EpsilonConfig::<T>::default().coplanarity;
+    for (i, first) in fragments.iter().enumerate() {
+        for (j, second) in fragments.iter().enumerate().skip(i + 1) {
+            if is_wrong_order(first, second, &view, eps) {
+                return Err(OrderViolation {
+                    former: i,
+                    latter: j,
+                    former_anchor: first.anchor.clone(),
+                    latter_anchor: second.anchor.clone(),
+                    overlap: covered_part(first, second, &view, eps),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check that the sorted polygons are planar and convex, don't intersect
+/// each other, and are ordered back to front for the given view vector.
+///
+/// This is quadratic in the number of polygons, so it's meant for debugging.
+pub fn validate<A, T, U>(
+    sorted: &[Polygon<A, T, U>],
+    view: Vector3D<T, U>,
+    epsilons: &EpsilonConfig<T>,
+) -> Result<(), ValidationError>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    validate_by(sorted.len(), |i| &sorted[i], view, epsilons)
+}
+
+pub(crate) fn validate_by<'a, A, T, U, F>(
+    count: usize,
+    get: F,
+    view: Vector3D<T, U>,
+    epsilons: &EpsilonConfig<T>,
+) -> Result<(), ValidationError>
+where
+    A: Clone + 'a,
+    T: Float + ApproxEq<T> + fmt::Debug + 'a,
+    U: 'a,
+    F: Fn(usize) -> &'a Polygon<A, T, U>,
+{
+    let eps = epsilons.coplanarity;
+    for i in 0..count {
+        let poly = get(i);
+        if poly
+            .points
+            .iter()
+            .any(|p| poly.plane.signed_distance_to(p).abs() > eps)
+        {
+            return Err(ValidationError::NonPlanar(i));
+        }
+        if !is_convex(poly, eps) {
+            return Err(ValidationError::NonConvex(i));
+        }
+    }
+
+    for i in 0..count {
+        let first = get(i);
+        for j in i + 1..count {
+            let second = get(j);
+            if crosses(first, second, eps)
+                && crosses(second, first, eps)
+                && cuts(first, second, eps)
+            {
+                return Err(ValidationError::Intersecting(i, j));
+            }
+            if is_wrong_order(first, second, &view, eps) {
+                return Err(ValidationError::WrongOrder(i, j));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Determine which of the two polygons has to be drawn first, i.e. is
+/// farther away, as seen along the `view` vector pointing towards the viewer.
+///
+/// Returns `Less` if `a` has to be drawn first, and `Greater` if `b` has to.
+/// Intersecting polygons are split virtually, and ordered by the part that
+/// overlaps the other one on the screen. `Equal` is returned if the order
+/// doesn't matter, or if both orders are wrong and only splitting helps.
+pub fn order_pair<A, T, U>(
+    a: &Polygon<A, T, U>,
+    b: &Polygon<A, T, U>,
+    view: Vector3D<T, U>,
+) -> Ordering
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let eps = EpsilonConfig::<T>::default().coplanarity;
+    if !a.overlaps_along(b, &view, eps) {
+        return Ordering::Equal;
+    }
+    if is_behind(b, a, &view, eps) || is_behind(a, b, &-view, eps) {
+        return Ordering::Greater;
+    }
+    if is_behind(a, b, &view, eps) || is_behind(b, a, &-view, eps) {
+        return Ordering::Less;
+    }
+    if crosses(b, a, eps) {
+        if let Some(order) = order_split(a, b, &view, eps) {
+            return order;
+        }
+    }
+    if crosses(a, b, eps) {
+        if let Some(order) = order_split(b, a, &view, eps) {
+            return order.reverse();
+        }
+    }
+    Ordering::Equal
+}
+
+/// Order the polygons by splitting `b` with the plane of `a`, if only
+/// the parts on one side of it overlap with `a` on the screen.
+fn order_split<A, T, U>(
+    a: &Polygon<A, T, U>,
+    b: &Polygon<A, T, U>,
+    view: &Vector3D<T, U>,
+    eps: T,
+) -> Option<Ordering>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let facing = a.plane.normal.dot(*view);
+    if facing.abs() < eps {
+        return None;
+    }
+    let (front, back) = a.plane.split_polygon(b);
+    let overlaps =
+        |parts: &[Polygon<A, T, U>]| parts.iter().any(|p| a.overlaps_along(p, view, eps));
+    // the parts in front of the plane are nearer if it faces the viewer
+    let (near, far) = if facing > T::zero() {
+        (overlaps(&front), overlaps(&back))
+    } else {
+        (overlaps(&back), overlaps(&front))
+    };
+    match (near, far) {
+        (true, false) => Some(Ordering::Less),
+        (false, true) => Some(Ordering::Greater),
+        _ => None,
+    }
+}
+
+/// Check if the `first` polygon occludes the `second` one, so drawing
+/// it first is wrong. That is the case if they overlap on the screen, and
+/// the second is behind the plane of the first, or the first is in front
+/// of the plane of the second.
+fn is_wrong_order<A, T, U>(
+    first: &Polygon<A, T, U>,
+    second: &Polygon<A, T, U>,
+    view: &Vector3D<T, U>,
+    eps: T,
+) -> bool
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    (is_behind(second, first, view, eps) || is_behind(first, second, &-*view, eps))
+        && first.overlaps_along(second, view, eps)
+}
+
+/// Clip the polygon by the outline of the other one, extruded along the view.
+fn covered_part<A, T, U>(
+    poly: &Polygon<A, T, U>,
+    other: &Polygon<A, T, U>,
+    view: &Vector3D<T, U>,
+    eps: T,
+) -> Option<Polygon<A, T, U>>
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    let count = T::from(other.points.len()).unwrap();
+    let center = other
+        .points
+        .iter()
+        .fold(Point3D::origin(), |sum, p| sum + p.to_vector() / count);
+    let mut part = poly.clone();
+    for (i, &a) in other.points.iter().enumerate() {
+        let b = other.points[(i + 1) % other.points.len()];
+        let mut plane = match Plane::from_point_normal(a, (b - a).cross(*view)) {
+            Some(plane) => plane,
+            // the edge goes along the view
+            None => continue,
+        };
+        if plane.signed_distance_to(&center) < T::zero() {
+            plane.flip();
+        }
+        let (front, _) = plane.split_polygon(&part);
+        part = front.into_iter().next()?;
+        if part.area() < eps {
+            return None;
+        }
+    }
+    Some(part)
+}
+
+/// Check if the polygon has points on both sides of the other's plane.
+fn crosses<A, T, U>(poly: &Polygon<A, T, U>, other: &Polygon<A, T, U>, eps: T) -> bool
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    let (mut front, mut back) = (false, false);
+    for p in &poly.points {
+        let dist = other.plane.signed_distance_to(p);
+        front |= dist > eps;
+        back |= dist < -eps;
+    }
+    front && back
+}
+
+/// Check if the intersection of the polygons, limited to the extents
+/// of both of them, is longer than `eps`.
+fn cuts<A, T, U>(poly: &Polygon<A, T, U>, other: &Polygon<A, T, U>, eps: T) -> bool
+where
+    A: Clone,
+    T: Float + ApproxEq<T> + fmt::Debug,
+{
+    poly.intersection_segment(other)
+        .is_some_and(|segment| (segment.end - segment.start).length() > eps)
+}
+
+/// Check if `poly` is entirely behind the plane of `other`, as seen along
+/// the `view` vector pointing towards the viewer. Touching the plane is allowed.
+fn is_behind<A, T, U>(
+    poly: &Polygon<A, T, U>,
+    other: &Polygon<A, T, U>,
+    view: &Vector3D<T, U>,
+    eps: T,
+) -> bool
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    let facing = other.plane.normal.dot(*view);
+    if facing.abs() < eps {
+        return false;
+    }
+    let mut is_strict = false;
+    for p in &poly.points {
+        let dist = other.plane.signed_distance_to(p) * facing.signum();
+        if dist > eps {
+            return false;
+        }
+        is_strict |= dist < -eps;
+    }
+    is_strict
+}
+
+/// Check if the polygon turns consistently at every vertex.
+fn is_convex<A, T, U>(poly: &Polygon<A, T, U>, eps: T) -> bool
+where
+    A: Clone,
+    T: Float + ApproxEq<T>,
+{
+    let count = poly.points.len();
+    if count < 3 {
+        return false;
+    }
+    let normal = poly.plane.normal;
+    let turn = |i: usize| {
+        let a = poly.points[(i + 1) % count] - poly.points[i];
+        let b = poly.points[(i + 2) % count] - poly.points[(i + 1) % count];
+        a.cross(b).dot(normal)
+    };
+    let sign = (0..count).fold(T::zero(), |sum, i| sum + turn(i)).signum();
+    (0..count).all(|i| turn(i) * sign >= -eps)
+}
@@ -0,0 +1,78 @@
+//! JavaScript bindings, built with `wasm-bindgen`.
+//!
+//! Polygons come in as flat `Float64Array`s of `x, y, z` coordinates
+//! with a `u32` anchor, since wider integers would turn into `BigInt`.
+
+use crate::{BspSplitter, Polygon};
+
+use euclid::default::{Point3D, Vector3D};
+use wasm_bindgen::prelude::*;
+
+/// A BSP splitter exposed to JavaScript.
+#[wasm_bindgen]
+#[derive(Default)]
+pub struct WasmSplitter {
+    splitter: BspSplitter<u32>,
+    point_counts: Vec<u32>,
+    anchors: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl WasmSplitter {
+    /// Create a new splitter.
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remove all the polygons.
+    pub fn reset(&mut self) {
+        self.splitter.reset();
+        self.point_counts.clear();
+        self.anchors.clear();
+    }
+
+    /// Add a polygon given by its flat coordinates.
+    /// Returns false if the polygon is degenerate.
+    pub fn add(&mut self, points: &[f64], anchor: u32) -> bool {
+        let points = points
+            .chunks_exact(3)
+            .map(|c| Point3D::new(c[0], c[1], c[2]));
+        match Polygon::from_points(points, anchor) {
+            Some(poly) => {
+                self.splitter.add(poly);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sort the polygons for the given view direction, returning the flat
+    /// coordinates of all the fragments, from back to front.
+    ///
+    /// Use `point_counts` and `anchors` to tell the fragments apart.
+    pub fn sort(&mut self, view_x: f64, view_y: f64, view_z: f64) -> Vec<f64> {
+        self.point_counts.clear();
+        self.anchors.clear();
+        let mut coordinates = Vec::new();
+        for poly in self.splitter.sort(Vector3D::new(view_x, view_y, view_z)) {
+            for p in poly.points.iter() {
+                coordinates.extend_from_slice(&[p.x, p.y, p.z]);
+            }
+            self.point_counts.push(poly.points.len() as u32);
+            self.anchors.push(poly.anchor);
+        }
+        coordinates
+    }
+
+    /// Number of points in each fragment of the last `sort`.
+    #[wasm_bindgen(js_name = pointCounts)]
+    pub fn point_counts(&self) -> Vec<u32> {
+        self.point_counts.clone()
+    }
+
+    /// Anchor of each fragment of the last `sort`.
+    pub fn anchors(&self) -> Vec<u32> {
+        self.anchors.clone()
+    }
+}
@@ -0,0 +1,40 @@
+#![cfg(feature = "arbitrary")]
+
+use arbitrary::{Arbitrary, Unstructured};
+use euclid::default::Vector3D;
+use plane_split::{verify_order, BspSplitter, Polygon};
+
+fn bytes(seed: u32, len: usize) -> Vec<u8> {
+    let mut state = seed;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            (state >> 16) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn arbitrary_polygons() {
+    let view = Vector3D::new(0.0, 0.0, -1.0);
+    for seed in 0..20 {
+        let data = bytes(seed, 1024);
+        let mut u = Unstructured::new(&data);
+        let mut polygons = Vec::new();
+        while polygons.len() < 12 && !u.is_empty() {
+            // degenerate planes are rejected, skip over them
+            if let Ok(polygon) = Polygon::<u16>::arbitrary(&mut u) {
+                polygons.push(polygon);
+            }
+        }
+        assert!(polygons.iter().all(|p| p.is_valid()));
+
+        let mut splitter = BspSplitter::new();
+        let sorted = splitter.solve(&polygons, view).to_vec();
+        assert!(sorted.iter().all(|p| p.is_valid()));
+        if splitter.split_errors().is_empty() {
+            splitter.validate(view).unwrap();
+            verify_order(&sorted, view).unwrap();
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use euclid::vec3;
+use plane_split::{make_grid, BspSplitter, CachedSplitter, Splitter};
+use std::hash::Hasher;
+
+#[test]
+fn cached_sort() {
+    let polys = make_grid(3);
+    let view = vec3(0.0, 0.0, 1.0);
+    let mut reference = BspSplitter::new();
+    let expected = reference.solve(&polys, view).to_vec();
+
+    let mut splitter = CachedSplitter::new(BspSplitter::new());
+    assert_eq!(splitter.solve(&polys, view), &expected[..]);
+    assert!(!splitter.was_hit());
+    // the same scene again
+    assert_eq!(splitter.solve(&polys, view), &expected[..]);
+    assert!(splitter.was_hit());
+
+    // a different view
+    splitter.solve(&polys, vec3(0.0, 1.0, 0.0));
+    assert!(!splitter.was_hit());
+    splitter.solve(&polys, view);
+    assert!(!splitter.was_hit());
+
+    // a moved polygon
+    let mut moved = polys.clone();
+    moved[0].points[0].x += 1e-9;
+    splitter.solve(&moved, view);
+    assert!(!splitter.was_hit());
+
+    // a different anchor
+    let mut renamed = polys.clone();
+    renamed[1].anchor = 100;
+    splitter.solve(&renamed, view);
+    assert!(!splitter.was_hit());
+
+    // a different order of the same polygons
+    splitter.solve(&polys, view);
+    let reversed: Vec<_> = polys.iter().rev().cloned().collect();
+    splitter.solve(&reversed, view);
+    assert!(!splitter.was_hit());
+
+    // a change of configuration
+    splitter.solve(&polys, view);
+    splitter.inner_mut().set_deterministic(true);
+    splitter.solve(&polys, view);
+    assert!(!splitter.was_hit());
+    splitter.solve(&polys, view);
+    assert!(splitter.was_hit());
+}
+
+/// A hasher giving the same hash to every scene.
+#[derive(Clone, Default)]
+struct Colliding;
+
+impl Hasher for Colliding {
+    fn finish(&self) -> u64 {
+        0
+    }
+
+    fn write(&mut self, _bytes: &[u8]) {}
+}
+
+#[test]
+fn cached_collision() {
+    let polys = make_grid(3);
+    let view = vec3(0.0, 0.0, 1.0);
+    let mut splitter = CachedSplitter::<_, _, _, _, Colliding>::with_hasher(BspSplitter::new());
+    splitter.solve(&polys, view);
+    splitter.solve(&polys, view);
+    assert!(splitter.was_hit());
+
+    // the hash is the same, but the scene isn't
+    let mut moved = polys.clone();
+    moved[0].points[0].x += 0.5;
+    let expected = BspSplitter::new().solve(&moved, view).to_vec();
+    assert_eq!(splitter.solve(&moved, view), &expected[..]);
+    assert!(!splitter.was_hit());
+
+    splitter.solve(&moved, vec3(0.0, 1.0, 0.0));
+    assert!(!splitter.was_hit());
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "capi")]
+
+use plane_split::capi::*;
+
+use std::ptr;
+
+#[test]
+fn capi_roundtrip() {
+    let front = [0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 1.0, 1.0];
+    let back = [0.0, 0.0, 2.0, 1.0, 0.0, 2.0, 1.0, 1.0, 2.0, 0.0, 1.0, 2.0];
+    let degenerate = [0.0; 9];
+    unsafe {
+        let splitter = plane_split_new();
+        for (points, anchor) in [(&front[..], 1), (&back[..], 2)] {
+            let poly = PlaneSplitPolygon {
+                points: points.as_ptr(),
+                point_count: 4,
+                anchor,
+            };
+            assert!(plane_split_add(splitter, &poly));
+        }
+        let poly = PlaneSplitPolygon {
+            points: degenerate.as_ptr(),
+            point_count: 3,
+            anchor: 3,
+        };
+        assert!(!plane_split_add(splitter, &poly));
+
+        assert_eq!(plane_split_sort(splitter, 0.0, 0.0, -1.0), 2);
+        let mut fragment = poly;
+        assert!(plane_split_get(splitter, 0, &mut fragment));
+        assert_eq!(fragment.anchor, 2);
+        assert_eq!(fragment.point_count, 4);
+        let points = std::slice::from_raw_parts(fragment.points, 12);
+        assert_eq!(points, &back[..]);
+        assert!(plane_split_get(splitter, 1, &mut fragment));
+        assert_eq!(fragment.anchor, 1);
+        assert!(!plane_split_get(splitter, 2, &mut fragment));
+
+        plane_split_reset(splitter);
+        assert_eq!(plane_split_sort(splitter, 0.0, 0.0, -1.0), 0);
+        plane_split_free(splitter);
+        plane_split_free(ptr::null_mut());
+    }
+}
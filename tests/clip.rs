@@ -2,13 +2,13 @@ use euclid::{
     default::{Rect, Transform3D},
     point3, rect, vec3, Angle,
 };
-use plane_split::{Clipper, Plane, Polygon};
+use plane_split::{BspSplitter, ClipRegion, Clipper, EdgeOrigin, Frustum, Plane, Polygon};
 
 use std::f64::consts::FRAC_PI_4;
 
 #[test]
 fn clip_in() {
-    let plane = Plane::from_unnormalized(vec3(1.0, 0.0, 1.0), 20.0)
+    let plane: Plane = Plane::from_unnormalized(vec3(1.0, 0.0, 1.0), 20.0)
         .unwrap()
         .unwrap();
     let mut clipper = Clipper::new();
@@ -32,7 +32,7 @@ fn clip_in() {
 
 #[test]
 fn clip_out() {
-    let plane = Plane::from_unnormalized(vec3(1.0, 0.0, 1.0), -20.0)
+    let plane: Plane = Plane::from_unnormalized(vec3(1.0, 0.0, 1.0), -20.0)
         .unwrap()
         .unwrap();
     let mut clipper = Clipper::new();
@@ -55,7 +55,7 @@ fn clip_out() {
 
 #[test]
 fn clip_parallel() {
-    let plane = Plane {
+    let plane: Plane = Plane {
         normal: vec3(0.0, 0.0, 1.0),
         offset: 0.0,
     };
@@ -79,7 +79,7 @@ fn clip_parallel() {
 
 #[test]
 fn clip_repeat() {
-    let plane = Plane::from_unnormalized(vec3(1.0, 0.0, 1.0), 0.0)
+    let plane: Plane = Plane::from_unnormalized(vec3(1.0, 0.0, 1.0), 0.0)
         .unwrap()
         .unwrap();
     let mut clipper = Clipper::new();
@@ -145,3 +145,108 @@ fn clip_near_coplanar() {
     let results2 = clipper.clip_transformed(polygon, &tx, Some(bounds2));
     assert_ne!(0, results2.unwrap().count());
 }
+
+#[test]
+fn clip_to_frustum() {
+    let frustum = Frustum::from_transform(&Transform3D::identity()).unwrap();
+
+    let inside = Polygon::from_rect(rect(-0.5, -0.5, 1.0, 1.0), 0);
+    assert_eq!(inside.clip_to_frustum(&frustum), Some(inside.clone()));
+
+    let partial = Polygon::from_rect(rect(0.0, 0.0, 4.0, 4.0), 0);
+    let clipped = partial.clip_to_frustum(&frustum).unwrap();
+    assert!(clipped.is_valid());
+    assert_eq!(clipped.points.len(), 4);
+    for p in &clipped.points {
+        assert!(p.x >= 0.0 && p.x <= 1.0 && p.y >= 0.0 && p.y <= 1.0);
+    }
+    // the edges along the right and top planes are new
+    let cut_count = (0..4)
+        .filter(|&i| clipped.edge_origin(i) == EdgeOrigin::Cut)
+        .count();
+    assert_eq!(cut_count, 2);
+
+    let transform = Transform3D::translation(0.0, 0.0, 5.0);
+    let outside = Polygon::from_transformed_rect(rect(-0.5, -0.5, 1.0, 1.0), transform, 0).unwrap();
+    assert_eq!(outside.clip_to_frustum(&frustum), None);
+}
+
+#[test]
+fn splitter_clip_frustum() {
+    let frustum = Frustum::from_transform(&Transform3D::identity()).unwrap();
+    let mut splitter = BspSplitter::new();
+    splitter.set_clip_frustum(Some(frustum));
+
+    let transform = Transform3D::translation(0.0, 0.0, 5.0);
+    let polys = [
+        Polygon::from_rect(rect(-2.0f64, -2.0, 4.0, 4.0), 0),
+        Polygon::from_transformed_rect(rect(-0.5, -0.5, 1.0, 1.0), transform, 1).unwrap(),
+    ];
+    let result = splitter.solve(&polys, vec3(0.0, 0.0, 1.0));
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].anchor, 0);
+    assert_eq!(result[0].points.len(), 4);
+    for p in &result[0].points {
+        assert_eq!((p.x.abs(), p.y.abs(), p.z), (1.0, 1.0, 0.0));
+    }
+}
+
+#[test]
+fn clip_to_region() {
+    // a unit square rotated into a diamond, extruded along Z
+    let transform = Transform3D::rotation(0.0, 0.0, 1.0, Angle::radians(FRAC_PI_4));
+    let region = ClipRegion::from_rect(&rect(-0.5, -0.5, 1.0, 1.0), &transform).unwrap();
+    assert_eq!(region.planes.len(), 4);
+    assert!(region.contains(&point3(0.7, 0.0, 3.0)));
+    assert!(!region.contains(&point3(0.5, 0.5, 0.0)));
+
+    let square = Polygon::from_rect(rect(-1.0, -1.0, 2.0, 2.0), 0);
+    let clipped = square.clip_to_region(&region).unwrap();
+    assert_eq!(clipped.points.len(), 4);
+    assert!((clipped.area() - 1.0).abs() < 1e-9);
+
+    let far = Polygon::from_rect(rect(2.0, 2.0, 1.0, 1.0), 1);
+    assert_eq!(far.clip_to_region(&region), None);
+    assert_eq!(
+        square.clip_to_region(&ClipRegion::default()),
+        Some(square.clone())
+    );
+
+    let mut splitter = BspSplitter::new();
+    splitter.add_clipped(square.clone(), &region);
+    splitter.add_clipped(far, &region);
+    let result = splitter.sort(vec3(0.0, 0.0, 1.0));
+    assert_eq!(result, [clipped]);
+
+    // a frustum is a region too
+    let frustum = Frustum::from_transform(&Transform3D::identity()).unwrap();
+    let region = ClipRegion::from(frustum.clone());
+    assert_eq!(
+        square.clip_to_region(&region),
+        square.clip_to_frustum(&frustum)
+    );
+}
+
+#[test]
+fn clip_to_box() {
+    let bounds = euclid::default::Box3D::new(point3(-1.0, -1.0, -1.0), point3(1.0, 1.0, 1.0));
+    // a diamond sticking out of every side makes an octagon
+    let transform = Transform3D::rotation(0.0, 0.0, 1.0, Angle::radians(FRAC_PI_4));
+    let diamond = Polygon::from_transformed_rect(rect(-1.2, -1.2, 2.4, 2.4), transform, 0).unwrap();
+    let clipped = diamond.clip_to_box(&bounds).unwrap();
+    assert!(clipped.is_valid());
+    assert_eq!(clipped.points.len(), 8);
+    for p in &clipped.points {
+        assert!(p.x.abs() <= 1.0 + 1e-9 && p.y.abs() <= 1.0 + 1e-9);
+    }
+
+    let inside = Polygon::from_rect(rect(-0.5, -0.5, 1.0, 1.0), 1);
+    assert_eq!(inside.clip_to_box(&bounds), Some(inside.clone()));
+    let above = Polygon::from_transformed_rect(
+        rect(-0.5, -0.5, 1.0, 1.0),
+        Transform3D::translation(0.0, 0.0, 2.0),
+        2,
+    )
+    .unwrap();
+    assert_eq!(above.clip_to_box(&bounds), None);
+}
@@ -0,0 +1,159 @@
+use euclid::{
+    default::{Rect, Transform3D, Vector3D},
+    point3, rect, vec3, Angle,
+};
+use plane_split::{
+    make_grid, validate, BspSplitter, CompareSplitter, Divergence, EpsilonConfig, GraphSplitter,
+    Heuristic, NaiveSplitter, Polygon, Splitter,
+};
+
+/// A splitter that neither splits nor sorts anything.
+#[derive(Default)]
+struct Passthrough(Vec<Polygon<usize>>);
+
+impl Splitter<usize> for Passthrough {
+    fn reset(&mut self) {
+        self.0.clear();
+    }
+    fn add(&mut self, polygon: Polygon<usize>) {
+        self.0.push(polygon);
+    }
+    fn sort(&mut self, _view: Vector3D<f64>) -> &[Polygon<usize>] {
+        &self.0
+    }
+}
+
+fn make_polygons() -> Vec<Polygon<usize>> {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    [-0.5, 0.0, 0.5]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn compare_same() {
+    let mut splitter = CompareSplitter::new(
+        BspSplitter::new(),
+        BspSplitter::with_heuristic(Heuristic::MinSplits),
+    );
+    let result = splitter.solve(make_polygons(), vec3(0.0, 0.0, -1.0));
+    assert_eq!(result.len(), 5);
+    assert_eq!(splitter.divergences(), &[]);
+}
+
+#[test]
+fn compare_different() {
+    let mut splitter = CompareSplitter::new(BspSplitter::new(), Passthrough::default());
+    splitter.solve(make_polygons(), vec3(0.0, 0.0, -1.0));
+    // the coverage is the same, only the order differs
+    let divergences = splitter.divergences();
+    assert!(!divergences.is_empty());
+    assert!(divergences
+        .iter()
+        .all(|d| matches!(*d, Divergence::Order { .. })));
+}
+
+#[test]
+fn compare_naive() {
+    let mut splitter = CompareSplitter::new(BspSplitter::new(), NaiveSplitter::new());
+    splitter.solve(make_polygons(), vec3(0.0, 0.0, -1.0));
+    assert_eq!(splitter.divergences(), &[]);
+
+    let mut splitter = CompareSplitter::new(BspSplitter::new(), NaiveSplitter::new());
+    splitter.solve(make_grid(2), vec3(1.0, 2.0, -3.0).normalize());
+    assert_eq!(splitter.divergences(), &[]);
+}
+
+#[test]
+fn naive_layers() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = (0..4)
+        .map(|i| {
+            let transform = Transform3D::translation(0.0, 0.0, i as f64);
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+    let mut splitter = NaiveSplitter::new();
+    let anchors: Vec<_> = splitter
+        .solve(&polys, vec3(0.0, 0.0, -1.0))
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![3, 2, 1, 0]);
+}
+
+#[test]
+fn graph_intersecting() {
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = CompareSplitter::new(BspSplitter::new(), GraphSplitter::new());
+    splitter.solve(make_polygons(), view);
+    assert!(splitter
+        .divergences()
+        .iter()
+        .all(|d| matches!(*d, Divergence::Order { .. })));
+
+    let mut splitter = GraphSplitter::new();
+    let result = splitter.solve(make_polygons(), view);
+    assert_eq!(validate(result, view, &EpsilonConfig::default()), Ok(()));
+}
+
+#[test]
+fn graph_fan() {
+    // the polygons all cross each other along the same line
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = (0..8)
+        .map(|i| {
+            let angle = Angle::degrees(22.5 * i as f64 + 5.0);
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, angle);
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+    let view = vec3(0.3, 0.2, -1.0).normalize();
+
+    let mut splitter = GraphSplitter::new();
+    let result = splitter.solve(&polys, view);
+    assert_eq!(validate(result, view, &EpsilonConfig::default()), Ok(()));
+    assert!(result.len() > polys.len());
+}
+
+#[test]
+fn graph_separate() {
+    // the BSP tree splits the second polygon by the plane of the first one,
+    // even though they don't intersect
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let poly0 = Polygon::from_rect(rect, 0);
+    let transform = Transform3D::rotation(1.0, 0.0, 0.0, Angle::frac_pi_2())
+        .then_translate(vec3(0.0, 100.0, 0.0));
+    let poly1 = Polygon::from_transformed_rect(rect, transform, 1).unwrap();
+    let view = vec3(1.0, 1.0, 0.0).normalize();
+
+    let mut bsp = BspSplitter::new();
+    assert_eq!(bsp.solve(&[poly0.clone(), poly1.clone()], view).len(), 3);
+    let mut splitter = GraphSplitter::new();
+    assert_eq!(splitter.solve(&[poly0, poly1], view).len(), 2);
+}
+
+#[test]
+fn graph_cycle() {
+    // three sticks, each one lying above the next one
+    let polys: Vec<_> = (0..3)
+        .map(|i| {
+            let rotation = Transform3D::rotation(0.0, 0.0, 1.0, Angle::degrees(120.0 * i as f64));
+            let points = [(-3.0, -1.3), (3.0, -1.3), (3.0, -0.7), (-3.0, -0.7)]
+                .iter()
+                .map(|&(x, y)| rotation.transform_point3d(point3(x, y, -x / 3.0)).unwrap());
+            Polygon::from_points(points, i).unwrap()
+        })
+        .collect();
+    let view = vec3(0.0, 0.0, -1.0);
+
+    let mut splitter = GraphSplitter::new();
+    let result = splitter.solve(&polys, view);
+    assert_eq!(validate(result, view, &EpsilonConfig::default()), Ok(()));
+    assert!(result.len() > 3);
+}
@@ -0,0 +1,71 @@
+use euclid::{
+    default::{Rect, Transform3D},
+    rect, vec3, Angle,
+};
+use plane_split::{ContextId, ContextSplitter, Polygon, Splitter};
+
+#[test]
+fn flatten_split_placement() {
+    let view = vec3(0.0, 0.0, 1.0);
+    let mut splitter = ContextSplitter::new();
+    // a wall crossing the placement of the nested context
+    let wall = Polygon::from_transformed_rect(
+        rect(-2.0, -1.0, 4.0, 2.0),
+        Transform3D::rotation(0.0, 1.0, 0.0, Angle::degrees(90.0)),
+        0,
+    )
+    .unwrap();
+    splitter.add(wall);
+    let placement = Polygon::from_rect(rect(-1.0, -1.0, 2.0, 2.0), 10);
+    let child = splitter.add_context(ContextId::ROOT, placement);
+    assert_eq!(splitter.context_count(), 2);
+
+    let content: Rect<f64> = rect(-0.5, -0.5, 1.0, 1.0);
+    for &(z, anchor) in &[(0.5, 1), (-0.5, 2)] {
+        let poly =
+            Polygon::from_transformed_rect(content, Transform3D::translation(0.0, 0.0, z), anchor)
+                .unwrap();
+        splitter.add_to(child, poly);
+    }
+
+    let result = splitter.sort(view).to_vec();
+    let anchors: Vec<_> = result.iter().map(|p| p.anchor).collect();
+    assert_eq!(anchors.iter().filter(|&&a| a == 0).count(), 1);
+    // both halves of the placement are followed by the clipped content,
+    // the farther polygon first
+    for (i, _) in anchors.iter().enumerate().filter(|&(_, &a)| a == 10) {
+        assert_eq!(anchors[i + 1..i + 3], [2, 1]);
+        let side = result[i].centroid().x.signum();
+        for poly in &result[i + 1..i + 3] {
+            assert_eq!(poly.centroid().x.signum(), side);
+            assert!(poly.points.iter().all(|p| p.z.abs() < 1e-6));
+        }
+    }
+    assert_eq!(result.len(), 7);
+
+    splitter.reset();
+    assert_eq!(splitter.context_count(), 1);
+    assert!(splitter.sort(view).is_empty());
+}
+
+#[test]
+fn nested_contexts() {
+    let view = vec3(0.0, 0.0, 1.0);
+    let mut splitter = ContextSplitter::new();
+    let outer = splitter.add_context(
+        ContextId::ROOT,
+        Polygon::from_rect(rect(-2.0, -2.0, 4.0, 4.0), 10),
+    );
+    let placement = Polygon::from_transformed_rect(
+        rect(-1.0, -1.0, 2.0, 2.0),
+        Transform3D::translation(0.0, 0.0, 1.0),
+        20,
+    )
+    .unwrap();
+    let inner = splitter.add_context(outer, placement);
+    splitter.add_to(outer, Polygon::from_rect(rect(-2.0, -2.0, 1.0, 1.0), 1));
+    splitter.add_to(inner, Polygon::from_rect(rect(-0.5, -0.5, 1.0, 1.0), 2));
+
+    let anchors: Vec<_> = splitter.sort(view).iter().map(|p| p.anchor).collect();
+    assert_eq!(anchors, [10, 1, 20, 2]);
+}
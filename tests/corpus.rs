@@ -0,0 +1,62 @@
+#![cfg(feature = "dump")]
+
+//! Replays the scene dumps stored in `tests/corpus`, checking that
+//! the sorted output is valid, and that the `BspSplitter` still
+//! produces the recorded order of the anchors.
+
+use plane_split::{
+    validate, verify_order, AutoSplitter, BspSplitter, Dump, EpsilonConfig, GraphSplitter,
+    Heuristic, Polygon, Splitter,
+};
+
+use std::path::Path;
+
+fn anchors(polygons: &[Polygon<usize>]) -> Vec<usize> {
+    polygons.iter().map(|p| p.anchor).collect()
+}
+
+/// Replay the dump with the splitter, and check that the output is in order.
+fn replay<S: Splitter<usize>>(
+    name: &str,
+    kind: &str,
+    dump: &Dump<usize>,
+    mut splitter: S,
+) -> Vec<Polygon<usize>> {
+    let sorted = dump.replay(&mut splitter);
+    if let Err(e) = verify_order(&sorted, dump.view) {
+        panic!(
+            "{} with {}: {:?} over {:?}",
+            name,
+            kind,
+            (e.former, e.latter),
+            (e.former_anchor, e.latter_anchor)
+        );
+    }
+    sorted
+}
+
+#[test]
+fn corpus() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/corpus");
+    let dumps = Dump::<usize>::load_dir(dir).unwrap();
+    assert!(!dumps.is_empty());
+
+    let epsilons = EpsilonConfig::default();
+    for (path, dump) in dumps {
+        let name = path.file_name().unwrap().to_string_lossy();
+        for &heuristic in &[Heuristic::FirstPolygon, Heuristic::MinSplits] {
+            let kind = format!("{:?}", heuristic);
+            let sorted = replay(&name, &kind, &dump, BspSplitter::with_heuristic(heuristic));
+            validate(&sorted, dump.view, &epsilons)
+                .unwrap_or_else(|e| panic!("{} with {}: {:?}", name, kind, e));
+            if heuristic == Heuristic::FirstPolygon && !dump.sorted.is_empty() {
+                assert_eq!(anchors(&sorted), anchors(&dump.sorted), "{}", name);
+            }
+        }
+        let sorted = replay(&name, "auto", &dump, AutoSplitter::new());
+        validate(&sorted, dump.view, &epsilons)
+            .unwrap_or_else(|e| panic!("{} with auto: {:?}", name, e));
+        // the graph only splits the polygons that overlap along the view
+        replay(&name, "graph", &dump, GraphSplitter::new());
+    }
+}
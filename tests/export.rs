@@ -0,0 +1,141 @@
+#![cfg(feature = "debug-export")]
+
+use euclid::{default::Transform3D, rect, vec3, Angle};
+use plane_split::{
+    export::{gltf::GltfExport, obj::ObjExport, svg},
+    BspSplitter, Polygon,
+};
+
+#[test]
+fn obj_export() {
+    let rect = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-0.5, 0.5]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+
+    let mut splitter = BspSplitter::new();
+    let sorted = splitter.solve(&polys, vec3(0.0, 0.0, -1.0)).to_vec();
+    assert_eq!(sorted.len(), 3);
+
+    let (mut obj, mut mtl) = (Vec::new(), Vec::new());
+    ObjExport::new(&polys, &sorted)
+        .with_tree_planes(&splitter, 20.0)
+        .write(&mut obj, &mut mtl, "scene.mtl")
+        .unwrap();
+    let obj = String::from_utf8(obj).unwrap();
+    let mtl = String::from_utf8(mtl).unwrap();
+
+    assert!(obj.starts_with("mtllib scene.mtl\n"));
+    // two input polygons, three fragments, and a plane for each of the three nodes
+    assert_eq!(obj.lines().filter(|l| l.starts_with("f ")).count(), 8);
+    let vertex_count = polys
+        .iter()
+        .chain(&sorted)
+        .map(|p| p.points.len())
+        .sum::<usize>()
+        + 3 * 4;
+    assert_eq!(
+        obj.lines().filter(|l| l.starts_with("v ")).count(),
+        vertex_count
+    );
+    assert_eq!(mtl.lines().filter(|l| l.starts_with("newmtl ")).count(), 5);
+}
+
+#[test]
+fn svg_export() {
+    let rect = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-0.5, 0.5]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+    let sorted = splitter.solve(&polys, view).to_vec();
+
+    let mut out = Vec::new();
+    svg(&sorted, view, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+
+    assert!(out.starts_with("<svg "));
+    assert!(out.ends_with("</svg>\n"));
+    assert_eq!(out.matches("<polygon ").count(), 3);
+    // both fragments of the cut polygon have the cut edge dashed
+    assert_eq!(out.matches("<line ").count(), 2);
+    let labels: Vec<_> = out
+        .lines()
+        .filter_map(|l| l.strip_suffix("</text>"))
+        .map(|l| &l[l.rfind('>').unwrap() + 1..])
+        .collect();
+    let anchors: Vec<_> = sorted.iter().map(|p| p.anchor.to_string()).collect();
+    assert_eq!(labels, anchors);
+}
+
+#[test]
+fn gltf_export() {
+    let rect = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-0.5, 0.5]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+
+    let mut splitter = BspSplitter::new();
+    let sorted = splitter.solve(&polys, vec3(0.0, 0.0, -1.0)).to_vec();
+
+    let mut out = Vec::new();
+    GltfExport::new(&polys, &sorted)
+        .with_tree_planes(&splitter, 20.0)
+        .write(&mut out)
+        .unwrap();
+    let gltf: serde_json::Value = serde_json::from_slice(&out).unwrap();
+
+    assert_eq!(gltf["asset"]["version"], "2.0");
+    // two input polygons, a plane for each of the three nodes, and three fragments
+    let meshes = gltf["meshes"].as_array().unwrap();
+    assert_eq!(meshes.len(), 8);
+    assert_eq!(gltf["scenes"][0]["nodes"].as_array().unwrap().len(), 8);
+    assert_eq!(gltf["materials"].as_array().unwrap().len(), 5);
+    assert_eq!(meshes[7]["primitives"][0]["material"], 4);
+
+    // every quad is made of two triangles
+    let accessors = gltf["accessors"].as_array().unwrap();
+    assert_eq!(accessors.len(), 16);
+    assert!(accessors.iter().skip(1).step_by(2).all(|a| a["count"] == 6));
+
+    let uri = gltf["buffers"][0]["uri"].as_str().unwrap();
+    let data = uri
+        .strip_prefix("data:application/octet-stream;base64,")
+        .unwrap();
+    let length = gltf["buffers"][0]["byteLength"].as_u64().unwrap() as usize;
+    assert_eq!(data.len(), length.div_ceil(3) * 4);
+    // the first value is the X coordinate of the first input point
+    let digit = |c: u8| match c {
+        b'A'..=b'Z' => c - b'A',
+        b'a'..=b'z' => c - b'a' + 26,
+        b'0'..=b'9' => c - b'0' + 52,
+        b'+' => 62,
+        _ => 63,
+    } as u32;
+    let bits = data
+        .bytes()
+        .take(8)
+        .fold(0u64, |bits, c| bits << 6 | digit(c) as u64);
+    let first = (bits >> 16) as u32;
+    assert_eq!(
+        f32::from_le_bytes(first.to_be_bytes()),
+        polys[0].points[0].x as f32
+    );
+}
@@ -0,0 +1,21 @@
+#![cfg(feature = "glam")]
+
+use glam::{DVec3, Vec3A};
+use plane_split::Polygon;
+
+#[test]
+fn glam_roundtrip() {
+    let points = [
+        Vec3A::new(0.0, 0.0, 1.0),
+        Vec3A::X + Vec3A::Z,
+        Vec3A::Y + Vec3A::Z,
+    ];
+    let poly: Polygon<usize, f32> = Polygon::from_glam_points(points, 0).unwrap();
+    assert_eq!(poly.plane.glam_normal::<Vec3A>(), Vec3A::Z);
+    assert_eq!(poly.glam_points::<Vec3A>().collect::<Vec<_>>(), points);
+
+    let points = [DVec3::ZERO, DVec3::Y, DVec3::Z];
+    let poly: Polygon<usize> = Polygon::from_glam_points(points, 0).unwrap();
+    assert_eq!(poly.plane.glam_normal::<DVec3>(), DVec3::X);
+    assert_eq!(poly.glam_points::<DVec3>().collect::<Vec<_>>(), points);
+}
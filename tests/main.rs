@@ -1,32 +1,57 @@
 use euclid::{
     approxeq::ApproxEq,
-    default::{Rect, Size2D, Transform3D},
-    point2, point3, vec3, Angle,
+    default::{Point3D, Rect, Size2D, Transform3D, Vector3D},
+    point2, point3, rect, vec3, Angle,
 };
-use plane_split::{Intersection, Line, LineProjection, NegativeHemisphereError, Plane, Polygon};
+use plane_split::{
+    BspSplitter, Classification, EdgeOrigin, Intersection, Line, LineProjection, LineSegment,
+    NegativeHemisphereError, Plane, Polygon, PolygonError, Winding,
+};
+use smallvec::{smallvec, SmallVec};
+use std::f64::consts::FRAC_PI_2;
 
 #[test]
 fn line_proj_bounds() {
     assert_eq!(
         (-5.0f64, 4.0),
         LineProjection {
-            markers: [-5.0f64, 1.0, 4.0, 2.0]
+            markers: smallvec![-5.0f64, 1.0, 4.0, 2.0]
         }
         .get_bounds()
     );
     assert_eq!(
         (1f64, 4.0),
         LineProjection {
-            markers: [4f64, 3.0, 2.0, 1.0]
+            markers: smallvec![4f64, 3.0, 2.0, 1.0]
         }
         .get_bounds()
     );
 }
 
+#[test]
+fn line_proj_overlap() {
+    let pentagon = LineProjection {
+        markers: smallvec![0.0f64, 2.0, 3.0, 1.5, -1.0],
+    };
+    assert_eq!(pentagon.get_bounds(), (-1.0, 3.0));
+    let other = LineProjection {
+        markers: smallvec![2.5f64, 5.0, 4.0],
+    };
+    assert!(pentagon.intersect(&other));
+    assert_eq!(pentagon.overlap(&other), Some((2.5, 3.0)));
+    assert_eq!(other.overlap(&pentagon), Some((2.5, 3.0)));
+
+    let touching = LineProjection {
+        markers: smallvec![3.0f64, 6.0],
+    };
+    assert!(!pentagon.intersect(&touching));
+    assert_eq!(pentagon.overlap(&touching), None);
+}
+
 #[test]
 fn valid() {
     let poly_a: Polygon<usize> = Polygon {
-        points: [
+        points: smallvec![
             point3(0.0, 0.0, 0.0),
             point3(1.0, 1.0, 1.0),
             point3(1.0, 1.0, 0.0),
@@ -37,10 +62,18 @@ fn valid() {
             offset: -1.0,
         },
         anchor: 0,
+        edges: SmallVec::new(),
     };
     assert!(!poly_a.is_valid()); // points[0] is outside
+    assert_eq!(
+        poly_a.validate(),
+        Err(PolygonError::NonPlanar {
+            vertex: 0,
+            distance: -1.0
+        })
+    );
     let poly_b: Polygon<usize> = Polygon {
-        points: [
+        points: smallvec![
             point3(0.0, 1.0, 0.0),
             point3(1.0, 1.0, 1.0),
             point3(1.0, 1.0, 0.0),
@@ -51,10 +84,12 @@ fn valid() {
             offset: -1.0,
         },
         anchor: 0,
+        edges: SmallVec::new(),
     };
     assert!(!poly_b.is_valid()); // winding is incorrect
+    assert_eq!(poly_b.validate(), Err(PolygonError::NonConvex { edge: 0 }));
     let poly_c: Polygon<usize> = Polygon {
-        points: [
+        points: smallvec![
             point3(0.0, 0.0, 1.0),
             point3(1.0, 0.0, 1.0),
             point3(1.0, 1.0, 1.0),
@@ -65,13 +100,157 @@ fn valid() {
             offset: -1.0,
         },
         anchor: 0,
+        edges: SmallVec::new(),
     };
     assert!(poly_c.is_valid());
+    assert_eq!(poly_c.validate(), Ok(()));
+
+    // the first point is on the line between its neighbours,
+    // and the polygon is dented at the fourth one
+    let poly_e: Polygon<usize> = Polygon {
+        points: smallvec![
+            point3(1.0, 0.0, 1.0),
+            point3(2.0, 0.0, 1.0),
+            point3(2.0, 2.0, 1.0),
+            point3(1.0, 0.5, 1.0),
+            point3(0.0, 2.0, 1.0),
+            point3(0.0, 0.0, 1.0),
+        ],
+        ..poly_c.clone()
+    };
+    assert_eq!(poly_e.validate(), Err(PolygonError::NonConvex { edge: 3 }));
+
+    let poly_d = Polygon {
+        plane: Plane {
+            normal: vec3(0.0, 0.0, 2.0),
+            offset: -2.0,
+        },
+        ..poly_c.clone()
+    };
+    assert_eq!(poly_d.validate(), Err(PolygonError::DenormalizedNormal));
+
+    let points: [Point3D<f64>; 3] = [
+        point3(0.0, 0.0, 0.0),
+        point3(1.0, 0.0, 0.0),
+        point3(2.0, 0.0, 0.0),
+    ];
+    assert_eq!(
+        Polygon::try_new(points.iter().cloned(), 0usize),
+        Err(PolygonError::ZeroArea)
+    );
+    let points = poly_c.points.iter().cloned();
+    assert_eq!(Polygon::try_new(points, 0usize), Ok(poly_c));
+}
+
+#[test]
+fn best_fit() {
+    let points: [Point3D<f64>; 4] = [
+        point3(0.0, 0.0, 1.0),
+        point3(1.0, 0.0, 1.01),
+        point3(1.0, 1.0, 1.0),
+        point3(0.0, 1.0, 1.01),
+    ];
+    let rough = Polygon::from_points(points.iter().cloned(), 0usize).unwrap();
+    assert!(!rough.is_valid());
+
+    let (poly, correction) = Polygon::from_points_best_fit(points.iter().cloned(), 0usize).unwrap();
+    assert!(poly.is_valid());
+    assert!(correction > 0.0 && correction < 0.01);
+    assert!(poly.plane.normal.approx_eq(&vec3(0.0, 0.0, 1.0)));
+
+    let line: [Point3D<f64>; 3] = [
+        point3(0.0, 0.0, 0.0),
+        point3(1.0, 0.0, 0.0),
+        point3(2.0, 0.0, 0.0),
+    ];
+    assert!(Polygon::from_points_best_fit(line.iter().cloned(), 0usize).is_none());
+}
+
+#[test]
+fn winding() {
+    let mut poly: Polygon<usize> = Polygon::from_rect(rect(0.0, 0.0, 1.0, 1.0), 0);
+    assert_eq!(poly.winding(), Winding::CounterClockwise);
+    poly.edges = smallvec![EdgeOrigin::Cut, EdgeOrigin::Original];
+    let original = poly.clone();
+
+    poly.flip();
+    assert_eq!(poly.winding(), Winding::CounterClockwise);
+    assert_eq!(poly.plane.normal, -original.plane.normal);
+    assert!(poly.is_valid());
+    // the edge from the first point to the second is now the last but one
+    assert_eq!(poly.edge_origin(2), EdgeOrigin::Cut);
+    assert_eq!(poly.edge_origin(0), EdgeOrigin::Original);
+
+    assert!(poly.normalize_winding(original.plane.normal));
+    assert_eq!(poly.points, original.points);
+    assert_eq!(poly.plane, original.plane);
+    assert_eq!(poly.edge_origin(0), EdgeOrigin::Cut);
+    assert_eq!(poly.edge_origin(2), EdgeOrigin::Original);
+    assert!(!poly.normalize_winding(original.plane.normal));
+
+    // points going the wrong way around the normal
+    poly.points.reverse();
+    assert_eq!(poly.winding(), Winding::Clockwise);
+    assert!(poly.normalize_winding(original.plane.normal));
+    assert_eq!(poly.winding(), Winding::CounterClockwise);
+    assert_eq!(poly.plane, original.plane);
+}
+
+#[test]
+fn project_2d() {
+    let transform = Transform3D::rotation(1.0, 2.0, 3.0, Angle::radians(0.7))
+        .then_translate(vec3(4.0, -5.0, 6.0));
+    let poly: Polygon<usize> =
+        Polygon::from_transformed_rect(rect(1.0, 2.0, 3.0, 4.0), transform, 0).unwrap();
+
+    let (tangent, bitangent) = poly.plane.basis();
+    assert!(tangent.dot(bitangent).approx_eq(&0.0));
+    assert!(tangent.cross(bitangent).approx_eq(&poly.plane.normal));
+
+    let outline = poly.project_to_2d();
+    // the projection keeps the distances and the winding
+    let edge_3d = (poly.points[1] - poly.points[0]).length();
+    assert!((outline[1] - outline[0]).length().approx_eq(&edge_3d));
+    let area = (outline[1] - outline[0]).cross(outline[2] - outline[1]);
+    assert!(area > 0.0);
+    for (point, &point_2d) in poly.points.iter().zip(outline.iter()) {
+        assert!(poly.unproject_from_2d(point_2d).approx_eq(point));
+    }
+}
+
+#[test]
+fn typed_units() {
+    struct LayoutSpace;
+    struct WorldSpace;
+
+    let rect: euclid::Rect<f64, LayoutSpace> = euclid::rect(0.0, 0.0, 2.0, 1.0);
+    let transform: euclid::Transform3D<f64, LayoutSpace, WorldSpace> =
+        euclid::Transform3D::translation(1.0, 2.0, 3.0);
+    let poly: Polygon<usize, f64, WorldSpace> =
+        Polygon::from_transformed_rect(rect, transform, 0).unwrap();
+    // the points and the plane stay in the space the polygon was built in
+    let points: Vec<euclid::Point3D<f64, WorldSpace>> = poly.points.to_vec();
+    assert_eq!(points[0], euclid::point3(1.0, 2.0, 3.0));
+    let plane: Plane<f64, WorldSpace> = poly.plane.clone();
+    assert!(plane.signed_distance_to(&points[2]).approx_eq(&0.0));
+
+    let other = Polygon::from_points(points.iter().cloned(), 0).unwrap();
+    assert_eq!(other, poly);
+
+    let untyped: Polygon<usize> = poly.clone().to_untyped();
+    assert_eq!(untyped.points, other.clone().to_untyped().points);
+    let back: Polygon<usize, f64, WorldSpace> = Polygon::from_untyped(untyped);
+    assert_eq!(back, other);
+
+    // the splitter works in the same space, without converting anything
+    let mut splitter: BspSplitter<usize, f64, WorldSpace> = BspSplitter::new();
+    let sorted = splitter.solve([&poly], euclid::vec3(0.0, 0.0, 1.0));
+    assert_eq!(sorted, [poly]);
 }
 
 #[test]
 fn empty() {
-    let poly = Polygon::from_points(
+    let poly: Option<Polygon<_>> = Polygon::from_points(
         [
             point3(0.0, 0.0, 1.0),
             point3(0.0, 0.0, 1.0),
@@ -111,10 +290,83 @@ fn from_transformed_rect_perspective() {
     test_transformed(rect, transform);
 }
 
+#[test]
+fn transform() {
+    let plane = Plane {
+        normal: vec3(0.0, 0.0, 1.0),
+        offset: -1.0,
+    };
+    let transform = Transform3D::rotation(1.0, 0.0, 0.0, Angle::radians(0.5))
+        .then_translate(vec3(2.0, 3.0, 4.0));
+    let moved = plane.transform(&transform).unwrap();
+    assert!(moved.normal.length().approx_eq(&1.0));
+    for point in &[point3(0.0, 0.0, 1.0), point3(5.0, -3.0, 1.0)] {
+        let point = transform.transform_point3d(*point).unwrap();
+        assert!(moved.signed_distance_to(&point).approx_eq(&0.0));
+    }
+    let flatten = Transform3D::scale(1.0, 1.0, 0.0);
+    assert_eq!(plane.transform(&flatten), None);
+
+    let rect = rect(-10.0, -5.0, 20.0, 30.0);
+    let poly = Polygon::from_rect(rect, 0).transform(&transform).unwrap();
+    let expected = Polygon::from_transformed_rect(rect, transform, 0).unwrap();
+    assert!(poly.is_valid());
+    assert_eq!(poly.points, expected.points);
+    assert!(poly.plane.normal.dot(expected.plane.normal).approx_eq(&1.0));
+    // non-invertible transforms are rejected
+    assert!(poly.transform(&flatten).is_none());
+}
+
+#[test]
+fn transform_behind_eye() {
+    let rect = rect(-10.0, -10.0, 20.0, 20.0);
+    let poly = Polygon::from_rect(rect, 0);
+    let visible = Transform3D::perspective(400.0).pre_translate(vec3(0.0, 0.0, -100.0));
+    assert!(poly.transform(&visible).unwrap().is_valid());
+    // a point at w <= 0 is rejected instead of being clamped
+    let tilted = Transform3D::rotation(1.0, 0.0, 0.0, Angle::degrees(60.0))
+        .then(&Transform3D::perspective(5.0));
+    let w: Vec<_> = poly
+        .points
+        .iter()
+        .map(|&p| tilted.transform_point3d_homogeneous(p).w)
+        .collect();
+    assert!(w.iter().any(|&w| w > 0.0) && w.iter().any(|&w| w <= 0.0));
+    assert!(poly.transform(&tilted).is_none());
+}
+
+#[test]
+fn from_transformed_rect_with_validity() {
+    let rect = rect(-10.0, -10.0, 20.0, 20.0);
+    let visible = Transform3D::perspective(400.0).pre_translate(vec3(0.0, 0.0, -100.0));
+    let poly = Polygon::from_transformed_rect_with_validity(rect, visible, 0).unwrap();
+    let expected = Polygon::from_transformed_rect(rect, visible, 0).unwrap();
+    assert_eq!(poly.points, expected.points);
+    assert!(poly.edges.is_empty());
+
+    // half of the rectangle is behind the eye
+    let crossing = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(1.5))
+        .then(&Transform3D::perspective(5.0));
+    assert_eq!(Polygon::from_transformed_rect(rect, crossing, 0), None);
+    let poly = Polygon::from_transformed_rect_with_validity(rect, crossing, 0).unwrap();
+    assert_eq!(poly.points.len(), 4);
+    assert!(poly.is_valid());
+    let cuts = (0..poly.points.len())
+        .filter(|&i| poly.edge_origin(i) == EdgeOrigin::Cut)
+        .count();
+    assert_eq!(cuts, 1);
+
+    let behind = Transform3D::perspective(5.0).pre_translate(vec3(0.0, 0.0, 10.0));
+    assert_eq!(
+        Polygon::from_transformed_rect_with_validity(rect, behind, 0),
+        None
+    );
+}
+
 #[test]
 fn untransform_point() {
     let poly: Polygon<usize> = Polygon {
-        points: [
+        points: smallvec![
             point3(0.0, 0.0, 0.0),
             point3(0.5, 1.0, 0.0),
             point3(1.5, 1.0, 0.0),
@@ -125,6 +377,7 @@ fn untransform_point() {
             offset: 0.0,
         },
         anchor: 0,
+        edges: SmallVec::new(),
     };
     assert_eq!(poly.untransform_point(poly.points[0]), point2(0.0, 0.0));
     assert_eq!(poly.untransform_point(poly.points[1]), point2(1.0, 0.0));
@@ -132,9 +385,25 @@ fn untransform_point() {
     assert_eq!(poly.untransform_point(poly.points[3]), point2(0.0, 1.0));
 }
 
+#[test]
+fn contains() {
+    let poly = Polygon::from_rect(rect(0.0, 0.0, 10.0, 10.0), 0);
+    let inner = Polygon::from_rect(rect(2.0, 2.0, 5.0, 5.0), 0);
+    let overlap = Polygon::from_rect(rect(5.0, 5.0, 10.0, 10.0), 0);
+    assert!(poly.contains(&poly));
+    assert!(poly.contains(&inner));
+    assert!(!inner.contains(&poly));
+    assert!(!poly.contains(&overlap));
+    assert!(poly.contains_with_tolerance(&Polygon::from_rect(rect(0.0, 0.0, 10.5, 10.0), 0), 1.0));
+
+    let transform = Transform3D::translation(0.0, 0.0, 1.0);
+    let above = Polygon::from_transformed_rect(rect(2.0, 2.0, 5.0, 5.0), transform, 0).unwrap();
+    assert!(!poly.contains(&above));
+}
+
 #[test]
 fn are_outside() {
-    let plane = Plane {
+    let plane: Plane = Plane {
         normal: vec3(0.0, 0.0, 1.0),
         offset: -1.0,
     };
@@ -143,10 +412,227 @@ fn are_outside() {
     assert!(!plane.are_outside(&[point3(0.0, 0.0, 1.0), point3(0.0, 0.0, -1.0),]));
 }
 
+#[test]
+fn classify() {
+    let plane = Plane {
+        normal: vec3(0.0, 0.0, 1.0),
+        offset: -1.0,
+    };
+    let rect = rect(-10.0, -10.0, 20.0, 20.0);
+    let at = |z| Polygon::from_transformed_rect(rect, Transform3D::translation(0.0, 0.0, z), 0);
+    let tilted = Polygon::from_transformed_rect(
+        rect,
+        Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(0.5))
+            .then_translate(vec3(0.0, 0.0, 1.0)),
+        0,
+    )
+    .unwrap();
+
+    assert!(matches!(
+        plane.classify(&at(2.0).unwrap()),
+        Classification::Front
+    ));
+    assert!(matches!(
+        plane.classify(&at(0.0).unwrap()),
+        Classification::Back
+    ));
+    assert!(matches!(
+        plane.classify(&at(1.0).unwrap()),
+        Classification::Coplanar
+    ));
+    match plane.classify(&tilted) {
+        Classification::Spanning(line) => {
+            assert!(line.dir.cross(vec3(0.0, 1.0, 0.0)).length().approx_eq(&0.0));
+            assert!(plane.signed_distance_to(&line.origin).approx_eq(&0.0));
+        }
+        other => panic!("Unexpected {:?}", other),
+    }
+
+    let (front, back) = plane.split_polygon(&tilted);
+    assert_eq!((front.len(), back.len()), (1, 1));
+    assert!(front[0]
+        .points
+        .iter()
+        .all(|p| plane.signed_distance_to(p) > -1.0e-6));
+    assert!(back[0]
+        .points
+        .iter()
+        .all(|p| plane.signed_distance_to(p) < 1.0e-6));
+    let (front, back) = plane.split_polygon(&at(1.0).unwrap());
+    assert_eq!((front.len(), back.len()), (1, 0));
+}
+
+#[test]
+fn intersect_coplanar() {
+    let poly = Polygon::from_rect(rect(0.0, 0.0, 10.0, 10.0), 0);
+    let overlap = Polygon::from_rect(rect(5.0, 5.0, 10.0, 10.0), 1);
+    let result = poly.intersect_coplanar(&overlap).unwrap();
+    assert_eq!(result.anchor, 0);
+    assert_eq!(result.points.len(), 4);
+    for point in &result.points {
+        assert!(point.x >= 5.0 && point.x <= 10.0);
+        assert!(point.y >= 5.0 && point.y <= 10.0);
+    }
+    let cuts = (0..result.points.len())
+        .filter(|&i| result.edge_origin(i) == EdgeOrigin::Cut)
+        .count();
+    assert_eq!(cuts, 2);
+    assert_eq!(poly.intersect_coplanar(&poly).unwrap().points, poly.points);
+
+    let apart = Polygon::from_rect(rect(20.0, 0.0, 10.0, 10.0), 1);
+    assert_eq!(poly.intersect_coplanar(&apart), None);
+    let transform = Transform3D::translation(0.0, 0.0, 1.0);
+    let above = Polygon::from_transformed_rect(rect(5.0, 5.0, 10.0, 10.0), transform, 1).unwrap();
+    assert_eq!(poly.intersect_coplanar(&above), None);
+}
+
+#[test]
+fn split_by_segment() {
+    let poly: Polygon<_> = Polygon::from_rect(rect(0.0, 0.0, 10.0, 10.0), 0);
+    let segment = |x0, y0, x1, y1| LineSegment {
+        start: point3(x0, y0, 0.0),
+        end: point3(x1, y1, 0.0),
+    };
+
+    let mut across = poly.clone();
+    let (other, none) = across.split_by_segment(&segment(5.0, -1.0, 5.0, 11.0));
+    assert!(none.is_none());
+    let other = other.unwrap();
+    assert_eq!(across.points.len() + other.points.len(), 8);
+    for part in &[&across, &other] {
+        assert!(part.is_valid());
+        assert!(part.points.iter().all(|p| p.x <= 5.0) || part.points.iter().all(|p| p.x >= 5.0));
+    }
+
+    // ending inside of the polygon
+    let mut partial = poly.clone();
+    assert_eq!(
+        partial.split_by_segment(&segment(5.0, -1.0, 5.0, 5.0)),
+        (None, None)
+    );
+    assert_eq!(partial, poly);
+    // the line crosses the polygon, but the segment doesn't
+    let mut outside = poly.clone();
+    assert_eq!(
+        outside.split_by_segment(&segment(5.0, 20.0, 5.0, 30.0)),
+        (None, None)
+    );
+    let mut off_plane = poly.clone();
+    let segment = LineSegment {
+        start: point3(5.0, -1.0, 1.0),
+        end: point3(5.0, 11.0, 1.0),
+    };
+    assert_eq!(off_plane.split_by_segment(&segment), (None, None));
+}
+
+#[test]
+fn intersection_segment() {
+    let poly = Polygon::from_rect(rect(0.0, 0.0, 10.0, 10.0), 0);
+    let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(FRAC_PI_2))
+        .then_translate(vec3(5.0, 0.0, 0.0));
+    let crossing =
+        Polygon::from_transformed_rect(rect(-5.0, 2.0, 10.0, 20.0), transform, 1).unwrap();
+    let segment = poly.intersection_segment(&crossing).unwrap();
+    let (lo, hi) = if segment.start.y < segment.end.y {
+        (segment.start, segment.end)
+    } else {
+        (segment.end, segment.start)
+    };
+    assert!(lo.approx_eq(&point3(5.0, 2.0, 0.0)));
+    assert!(hi.approx_eq(&point3(5.0, 10.0, 0.0)));
+
+    let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(FRAC_PI_2))
+        .then_translate(vec3(20.0, 0.0, 0.0));
+    let apart = Polygon::from_transformed_rect(rect(-5.0, 2.0, 10.0, 20.0), transform, 1).unwrap();
+    assert_eq!(poly.intersection_segment(&apart), None);
+    assert_eq!(poly.intersection_segment(&poly), None);
+}
+
+#[test]
+fn intersection_segment_scale() {
+    // the same scene gives the same segment at any scale
+    for &scale in &[1.0e-4, 1.0, 1.0e4] {
+        let poly = Polygon::from_rect(rect(0.0, 0.0, 10.0, 10.0).scale(scale, scale), 0);
+        let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(FRAC_PI_2))
+            .then_translate(vec3(5.0, 0.0, 0.0))
+            .then_scale(scale, scale, scale);
+        let crossing =
+            Polygon::from_transformed_rect(rect(-5.0, 2.0, 10.0, 20.0), transform, 1).unwrap();
+        let segment = poly.intersection_segment(&crossing).unwrap();
+        let (lo, hi) = if segment.start.y < segment.end.y {
+            (segment.start, segment.end)
+        } else {
+            (segment.end, segment.start)
+        };
+        assert!((lo / scale).approx_eq_eps(&point3(5.0, 2.0, 0.0), &point3(1e-6, 1e-6, 1e-6)));
+        assert!((hi / scale).approx_eq_eps(&point3(5.0, 10.0, 0.0), &point3(1e-6, 1e-6, 1e-6)));
+    }
+}
+
+#[test]
+fn measurements() {
+    let poly: Polygon<_> = Polygon::from_rect(rect(0.0, 0.0, 4.0, 2.0), 0);
+    assert_eq!(poly.area(), 8.0);
+    assert_eq!(poly.centroid(), point3(2.0, 1.0, 0.0));
+    let bounds = poly.bounding_box();
+    assert_eq!(
+        (bounds.min, bounds.max),
+        (point3(0.0, 0.0, 0.0), point3(4.0, 2.0, 0.0))
+    );
+
+    let triangle: Polygon<_> = Polygon::from_points(
+        vec![
+            point3(0.0, 0.0, 0.0),
+            point3(3.0, 0.0, 0.0),
+            point3(0.0, 3.0, 3.0),
+        ],
+        0,
+    )
+    .unwrap();
+    assert!(triangle.centroid().approx_eq(&point3(1.0, 1.0, 1.0)));
+    let (near, far) = triangle.depth_range(&vec3(0.0, 0.0, -2.0));
+    assert_eq!((near, far), (0.0, 3.0));
+}
+
+#[test]
+fn three_planes() {
+    let plane =
+        |normal, offset| -> Plane { Plane::from_unnormalized(normal, offset).unwrap().unwrap() };
+    let x = plane(vec3(1.0, 0.0, 0.0), -1.0);
+    let y = plane(vec3(0.0, 2.0, 0.0), 4.0);
+    let slope = plane(vec3(1.0, 1.0, 1.0), -3.0);
+    let point = Plane::intersect_3(&x, &y, &slope).unwrap();
+    assert!(point.approx_eq(&point3(1.0, -2.0, 4.0)));
+    let x2 = plane(vec3(-1.0, 0.0, 0.0), 5.0);
+    assert_eq!(Plane::intersect_3(&x, &y, &x2), None);
+}
+
+#[test]
+fn line_queries() {
+    let line: Line = Line {
+        origin: point3(1.0, 1.0, 0.0),
+        dir: vec3(0.0, 0.0, 1.0),
+    };
+    let point = point3(4.0, 5.0, 7.0);
+    assert_eq!(line.project_point(&point), point3(1.0, 1.0, 7.0));
+    assert!(line.distance_to_point(&point).approx_eq(&5.0));
+
+    let plane = Plane {
+        normal: vec3(0.0, 0.0, 1.0),
+        offset: -3.0,
+    };
+    assert_eq!(line.intersect_plane(&plane), Some(point3(1.0, 1.0, 3.0)));
+    let wall = Plane {
+        normal: vec3(1.0, 0.0, 0.0),
+        offset: 0.0,
+    };
+    assert_eq!(line.intersect_plane(&wall), None);
+}
+
 #[test]
 fn intersect() {
     let poly_a: Polygon<usize> = Polygon {
-        points: [
+        points: smallvec![
             point3(0.0, 0.0, 1.0),
             point3(1.0, 0.0, 1.0),
             point3(1.0, 1.0, 1.0),
@@ -157,10 +643,11 @@ fn intersect() {
             offset: -1.0,
         },
         anchor: 0,
+        edges: SmallVec::new(),
     };
     assert!(poly_a.is_valid());
     let poly_b: Polygon<usize> = Polygon {
-        points: [
+        points: smallvec![
             point3(0.5, 0.0, 2.0),
             point3(0.5, 1.0, 2.0),
             point3(0.5, 1.0, 0.0),
@@ -171,6 +658,7 @@ fn intersect() {
             offset: -0.5,
         },
         anchor: 0,
+        edges: SmallVec::new(),
     };
     assert!(poly_b.is_valid());
 
@@ -193,7 +681,7 @@ fn intersect() {
     assert!(poly_b.plane.normal.dot(intersection.dir).approx_eq(&0.0));
 
     let poly_c: Polygon<usize> = Polygon {
-        points: [
+        points: smallvec![
             point3(0.0, -1.0, 2.0),
             point3(0.0, -1.0, 0.0),
             point3(0.0, 0.0, 0.0),
@@ -204,10 +692,11 @@ fn intersect() {
             offset: 0.0,
         },
         anchor: 0,
+        edges: SmallVec::new(),
     };
     assert!(poly_c.is_valid());
     let poly_d: Polygon<usize> = Polygon {
-        points: [
+        points: smallvec![
             point3(0.0, 0.0, 0.5),
             point3(1.0, 0.0, 0.5),
             point3(1.0, 1.0, 0.5),
@@ -218,6 +707,7 @@ fn intersect() {
             offset: -0.5,
         },
         anchor: 0,
+        edges: SmallVec::new(),
     };
     assert!(poly_d.is_valid());
 
@@ -225,27 +715,44 @@ fn intersect() {
     assert!(poly_a.intersect(&poly_d).is_outside());
 }
 
-fn test_cut(poly_base: &Polygon<usize>, extra_count: u8, line: Line) {
+fn test_cut(poly_base: &Polygon<usize>, point_counts: &[usize], line: Line) {
     assert!(line.is_valid());
 
     let normal = poly_base.plane.normal.cross(line.dir).normalize();
     let mut poly = poly_base.clone();
     let (extra1, extra2) = poly.split_with_normal(&line, &normal);
     assert!(poly.is_valid() && poly_base.contains(&poly));
-    assert_eq!(extra_count > 0, extra1.is_some());
-    assert_eq!(extra_count > 1, extra2.is_some());
+    assert_eq!(point_counts.len() > 1, extra1.is_some());
+    assert!(extra2.is_none());
+    let mut counts = vec![poly.points.len()];
+    let is_split = extra1.is_some();
+    check_cut_edges(&poly, &line, &normal, is_split);
     if let Some(extra) = extra1 {
         assert!(extra.is_valid() && poly_base.contains(&extra));
+        check_cut_edges(&extra, &line, &normal, true);
+        counts.push(extra.points.len());
     }
-    if let Some(extra) = extra2 {
-        assert!(extra.is_valid() && poly_base.contains(&extra));
+    counts.sort();
+    assert_eq!(counts, point_counts);
+}
+
+fn check_cut_edges(poly: &Polygon<usize>, line: &Line, normal: &Vector3D<f64>, is_split: bool) {
+    let count = poly.points.len();
+    let cut_edges: Vec<_> = (0..count)
+        .filter(|&i| poly.edge_origin(i) == EdgeOrigin::Cut)
+        .collect();
+    assert_eq!(cut_edges.len(), is_split as usize);
+    for i in cut_edges {
+        for p in &[poly.points[i], poly.points[(i + 1) % count]] {
+            assert!(normal.dot(*p - line.origin).abs() < 1e-6);
+        }
     }
 }
 
 #[test]
 fn split() {
     let poly: Polygon<usize> = Polygon {
-        points: [
+        points: smallvec![
             point3(0.0, 1.0, 0.0),
             point3(1.0, 1.0, 0.0),
             point3(1.0, 1.0, 1.0),
@@ -256,12 +763,13 @@ fn split() {
             offset: -1.0,
         },
         anchor: 0,
+        edges: SmallVec::new(),
     };
 
     // non-intersecting line
     test_cut(
         &poly,
-        0,
+        &[4],
         Line {
             origin: point3(0.0, 1.0, 0.5),
             dir: vec3(0.0, 1.0, 0.0),
@@ -271,7 +779,7 @@ fn split() {
     // simple cut (diff=2)
     test_cut(
         &poly,
-        1,
+        &[4, 4],
         Line {
             origin: point3(0.0, 1.0, 0.5),
             dir: vec3(1.0, 0.0, 0.0),
@@ -281,7 +789,7 @@ fn split() {
     // complex cut (diff=1, wrapped)
     test_cut(
         &poly,
-        2,
+        &[3, 5],
         Line {
             origin: point3(0.0, 1.0, 0.5),
             dir: vec3(0.5f64.sqrt(), 0.0, -0.5f64.sqrt()),
@@ -291,7 +799,7 @@ fn split() {
     // complex cut (diff=1, non-wrapped)
     test_cut(
         &poly,
-        2,
+        &[3, 5],
         Line {
             origin: point3(0.5, 1.0, 0.0),
             dir: vec3(0.5f64.sqrt(), 0.0, 0.5f64.sqrt()),
@@ -301,7 +809,7 @@ fn split() {
     // complex cut (diff=3)
     test_cut(
         &poly,
-        2,
+        &[3, 5],
         Line {
             origin: point3(0.5, 1.0, 0.0),
             dir: vec3(-0.5f64.sqrt(), 0.0, 0.5f64.sqrt()),
@@ -311,7 +819,7 @@ fn split() {
     // perfect diagonal
     test_cut(
         &poly,
-        1,
+        &[3, 3],
         Line {
             origin: point3(0.0, 1.0, 0.0),
             dir: vec3(0.5f64.sqrt(), 0.0, 0.5f64.sqrt()),
@@ -319,6 +827,42 @@ fn split() {
     );
 }
 
+#[test]
+fn split_pentagon() {
+    let poly = Polygon::from_points(
+        [
+            point3(0.0, 0.0, 0.0),
+            point3(2.0, 0.0, 0.0),
+            point3(3.0, 1.0, 0.0),
+            point3(1.0, 2.0, 0.0),
+            point3(-1.0, 1.0, 0.0),
+        ],
+        0usize,
+    )
+    .unwrap();
+    assert!(poly.is_valid());
+
+    // cut through the middle, crossing the 2nd and the 5th edges
+    test_cut(
+        &poly,
+        &[4, 5],
+        Line {
+            origin: point3(0.0, 0.5, 0.0),
+            dir: vec3(1.0, 0.0, 0.0),
+        },
+    );
+
+    // cut through the 1st vertex and the 3rd edge, without duplicating the vertex
+    test_cut(
+        &poly,
+        &[4, 4],
+        Line {
+            origin: point3(0.0, 0.0, 0.0),
+            dir: vec3(0.5f64.sqrt(), 0.5f64.sqrt(), 0.0),
+        },
+    );
+}
+
 #[test]
 fn plane_unnormalized() {
     let zero_vec = vec3(0.0000001, 0.0, 0.0);
@@ -329,7 +873,7 @@ fn plane_unnormalized() {
     plane = Plane::from_unnormalized(zero_vec, -0.5);
     assert_eq!(plane, Err(NegativeHemisphereError));
 
-    let plane = Plane::from_unnormalized(vec3(-3.0, 4.0, 0.0), 2.0)
+    let plane: Plane = Plane::from_unnormalized(vec3(-3.0, 4.0, 0.0), 2.0)
         .unwrap()
         .unwrap();
     let expected = Plane {
@@ -339,3 +883,70 @@ fn plane_unnormalized() {
     assert!(plane.normal.approx_eq(&expected.normal));
     assert!(plane.offset.approx_eq(&expected.offset));
 }
+
+#[test]
+fn plane_construction() {
+    let plane: Plane = Plane::from_points(
+        point3(0.0, 0.0, 1.0),
+        point3(1.0, 0.0, 1.0),
+        point3(0.0, 1.0, 1.0),
+    )
+    .unwrap();
+    assert!(plane.normal.approx_eq(&vec3(0.0, 0.0, 1.0)));
+    assert!(plane
+        .signed_distance_to(&point3(5.0, 5.0, 3.0))
+        .approx_eq(&2.0));
+    assert_eq!(
+        Plane::<f64>::from_points(
+            point3(0.0, 0.0, 0.0),
+            point3(1.0, 1.0, 1.0),
+            point3(2.0, 2.0, 2.0),
+        ),
+        None
+    );
+
+    let mut plane: Plane =
+        Plane::from_point_normal(point3(0.0, 0.0, 1.0), vec3(0.0, 0.0, 4.0)).unwrap();
+    assert!(plane
+        .signed_distance_to(&point3(0.0, 0.0, 3.0))
+        .approx_eq(&2.0));
+    plane.flip();
+    assert!(plane
+        .signed_distance_to(&point3(0.0, 0.0, 3.0))
+        .approx_eq(&-2.0));
+    assert_eq!(
+        Plane::<f64>::from_point_normal(point3(1.0, 0.0, 0.0), vec3(0.0, 0.0, 0.0)),
+        None
+    );
+
+    let scaled: Plane = Plane {
+        normal: vec3(0.0, 3.0, 4.0),
+        offset: 10.0,
+    };
+    let plane = scaled.normalize().unwrap();
+    assert!(plane.normal.approx_eq(&vec3(0.0, 0.6, 0.8)));
+    assert!(plane.offset.approx_eq(&2.0));
+}
+
+#[test]
+fn plane_contains() {
+    let plane: Plane =
+        Plane::from_point_normal(point3(0.0, 0.0, 1.0), vec3(0.0, 0.0, 1.0)).unwrap();
+    assert!(plane.contains(&plane));
+    // the orientation doesn't matter
+    let mut flipped = plane.clone();
+    flipped.flip();
+    assert!(plane.contains(&flipped));
+    // the planes off by a rounding are the same
+    let other = Plane {
+        normal: vec3(0.0, 0.0, 1.0),
+        offset: -1.0 - 1e-9,
+    };
+    assert_ne!(plane, other);
+    assert!(plane.contains(&other));
+
+    let shifted = Plane::from_point_normal(point3(0.0, 0.0, 1.1), vec3(0.0, 0.0, 1.0)).unwrap();
+    assert!(!plane.contains(&shifted));
+    let tilted = Plane::from_point_normal(point3(0.0, 0.0, 1.0), vec3(0.0, 0.1, 1.0)).unwrap();
+    assert!(!plane.contains(&tilted));
+}
@@ -0,0 +1,22 @@
+#![cfg(feature = "mint")]
+
+use plane_split::{Plane, Polygon};
+
+#[test]
+fn mint_roundtrip() {
+    let points = [
+        mint::Point3::from([0.0, 0.0, 1.0]),
+        mint::Point3::from([1.0, 0.0, 1.0]),
+        mint::Point3::from([0.0, 1.0, 1.0]),
+    ];
+    let poly: Polygon<usize> = Polygon::from_mint_points(points, 0).unwrap();
+    let plane =
+        Plane::from_point_normal(poly.points[0], mint::Vector3::from([0.0, 0.0, 2.0]).into())
+            .unwrap();
+    assert_eq!(poly.plane, plane);
+    let back: Vec<[f64; 3]> = poly.mint_points().map(Into::into).collect();
+    assert_eq!(
+        back,
+        vec![[0.0, 0.0, 1.0], [1.0, 0.0, 1.0], [0.0, 1.0, 1.0]]
+    );
+}
@@ -0,0 +1,34 @@
+#![cfg(feature = "bytemuck")]
+
+use euclid::vec3;
+use plane_split::{make_grid, BspSplitter, PodPolygon, Polygon};
+
+#[test]
+fn pod_roundtrip() {
+    let mut splitter = BspSplitter::new();
+    let sorted = splitter.solve(make_grid(2), vec3(0.0, 0.0, 1.0));
+    let pods = PodPolygon::from_polygons(sorted, |&a| a as u32).unwrap();
+    assert_eq!(pods.len(), sorted.len());
+
+    let bytes: &[u8] = bytemuck::cast_slice(&pods);
+    assert_eq!(bytes.len(), pods.len() * std::mem::size_of::<PodPolygon>());
+    let back: &[PodPolygon] = bytemuck::cast_slice(bytes);
+    for (pod, poly) in back.iter().zip(sorted) {
+        let converted = pod.to_polygon();
+        assert_eq!(converted.anchor as usize, poly.anchor);
+        assert_eq!(converted.points.len(), poly.points.len());
+        for (a, b) in converted.points.iter().zip(&poly.points) {
+            assert_eq!(a.to_array(), b.cast::<f32>().to_array());
+        }
+        assert_eq!(converted.plane.normal, poly.plane.normal.cast());
+    }
+
+    let circle: Vec<_> = (0..9)
+        .map(|i| {
+            let angle = i as f64 * std::f64::consts::PI * 2.0 / 9.0;
+            euclid::point3(angle.cos(), angle.sin(), 0.0)
+        })
+        .collect();
+    let poly: Polygon<u32> = Polygon::from_points(circle, 0).unwrap();
+    assert_eq!(PodPolygon::from_polygon(&poly, 0), None);
+}
@@ -0,0 +1,76 @@
+#![cfg(feature = "robust")]
+
+use euclid::{
+    default::{Rect, Transform3D},
+    point3, rect, vec3, Angle,
+};
+use plane_split::{EdgeOrigin, Polygon, RobustSplitter, Splitter};
+use std::f64::consts::FRAC_PI_4;
+
+#[test]
+fn robust_rotation() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4, 0.0, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(anchor, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, anchor).unwrap()
+        })
+        .collect();
+
+    let mut splitter = RobustSplitter::new();
+    let result = splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    let ids: Vec<_> = result.iter().map(|poly| poly.anchor).collect();
+    assert_eq!(&ids, &[2, 1, 0, 1, 2]);
+}
+
+#[test]
+fn robust_grazing() {
+    // a plane going through the Z axis at an angle
+    let wall: Polygon<usize> = Polygon::from_points(
+        vec![
+            point3(0.0, 0.0, -5.0),
+            point3(0.0, 0.0, 5.0),
+            point3(3.0, -10.0, 5.0),
+            point3(3.0, -10.0, -5.0),
+        ],
+        0,
+    )
+    .unwrap();
+    // touches the wall plane with a single vertex
+    let grazing = Polygon::from_points(
+        vec![
+            point3(2.25, -7.5, 0.0),
+            point3(10.0, 0.0, 0.0),
+            point3(10.0, -10.0, 0.0),
+        ],
+        1,
+    )
+    .unwrap();
+    // crosses the wall plane
+    let crossing = Polygon::from_points(
+        vec![
+            point3(-5.0, -5.0, 1.0),
+            point3(5.0, -5.0, 1.0),
+            point3(5.0, 5.0, 1.0),
+            point3(-5.0, 5.0, 1.0),
+        ],
+        2,
+    )
+    .unwrap();
+
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = RobustSplitter::new();
+    let result = splitter.solve(vec![wall, grazing, crossing], view);
+    let ids: Vec<_> = result.iter().map(|poly| poly.anchor).collect();
+    assert_eq!(ids.iter().filter(|&&id| id == 1).count(), 1);
+    assert_eq!(ids.iter().filter(|&&id| id == 2).count(), 2);
+    for poly in result.iter().filter(|poly| poly.anchor == 2) {
+        let cuts = (0..poly.points.len())
+            .filter(|&i| poly.edge_origin(i) == EdgeOrigin::Cut)
+            .count();
+        assert_eq!(cuts, 1);
+    }
+    assert_eq!(splitter.inner().validate(view), Ok(()));
+}
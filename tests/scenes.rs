@@ -0,0 +1,59 @@
+#![cfg(feature = "bench-scenes")]
+
+use euclid::vec3;
+use plane_split::{scenes, BspSplitter, Polygon};
+
+fn check(polys: &[Polygon<usize>], expected_count: usize) -> usize {
+    assert_eq!(polys.len(), expected_count);
+    assert!(polys
+        .iter()
+        .enumerate()
+        .all(|(i, p)| p.anchor == i && p.is_valid()));
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+    splitter.solve(polys, view);
+    assert_eq!(splitter.validate(view), Ok(()));
+    splitter.stats().cuts_performed
+}
+
+#[test]
+fn carousel() {
+    let polys = scenes::carousel(8, 300.0, 0.0);
+    check(&polys, 8);
+    for poly in &polys {
+        let center = poly.centroid();
+        assert!((center.x.hypot(center.z) - 300.0).abs() < 1e-6);
+        // the cards face out of the ring
+        assert!(poly.plane.normal.dot(center.to_vector()) > 0.0);
+    }
+    check(&scenes::carousel(12, 300.0, 0.3), 12);
+}
+
+#[test]
+fn card_stack() {
+    let polys = scenes::card_stack(3, 10, 0.5);
+    assert!(check(&polys, 10) > 0);
+    assert_eq!(polys, scenes::card_stack(3, 10, 0.5));
+}
+
+#[test]
+fn cube_map() {
+    let polys = scenes::cube_map(1000.0, 2);
+    check(&polys, 24);
+    assert!(polys.iter().flat_map(|p| p.points.iter()).all(|p| p
+        .to_vector()
+        .to_array()
+        .iter()
+        .all(|c| c.abs() < 500.0 + 1e-6)));
+    // every tile lies on a face of the cube
+    assert!(polys
+        .iter()
+        .all(|p| (p.plane.offset.abs() - 500.0).abs() < 1e-6));
+}
+
+#[test]
+fn layer_pile() {
+    let polys = scenes::layer_pile(5, 20, 0.1, 0.001);
+    check(&polys, 20);
+    assert_eq!(polys, scenes::layer_pile(5, 20, 0.1, 0.001));
+}
@@ -0,0 +1,144 @@
+#![cfg(feature = "serde")]
+
+use euclid::{default::Rect, point3, rect, vec3};
+use plane_split::{Line, Plane, Polygon};
+
+#[test]
+fn polygon_roundtrip() {
+    let rect: Rect<f64> = rect(-1.0, -2.0, 3.0, 4.0);
+    let poly = Polygon::from_rect(rect, 5usize);
+    let json = serde_json::to_string(&poly).unwrap();
+    let other: Polygon<usize> = serde_json::from_str(&json).unwrap();
+    assert_eq!(poly, other);
+}
+
+#[test]
+fn plane_line_roundtrip() {
+    let plane: Plane = Plane {
+        normal: vec3(0.0, 0.0, 1.0),
+        offset: -2.0,
+    };
+    let json = serde_json::to_string(&plane).unwrap();
+    assert_eq!(plane, serde_json::from_str(&json).unwrap());
+
+    let line = Line {
+        origin: point3(1.0, 2.0, 3.0),
+        dir: vec3(1.0, 0.0, 0.0),
+    };
+    let json = serde_json::to_string(&line).unwrap();
+    let other: Line = serde_json::from_str(&json).unwrap();
+    assert!(line.matches(&other));
+}
+
+#[test]
+fn tree_roundtrip() {
+    use euclid::{default::Transform3D, Angle};
+    use plane_split::{BspNode, BspSplitter};
+
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+    for (i, &angle) in [-0.5, 0.0, 0.5].iter().enumerate() {
+        let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+        splitter.add(Polygon::from_transformed_rect(rect, transform, i).unwrap());
+    }
+    let json = serde_json::to_string(&splitter.to_tree()).unwrap();
+    let tree: BspNode<Polygon<usize>> = serde_json::from_str(&json).unwrap();
+    let expected = splitter.sort(view).to_vec();
+
+    let mut cached = BspSplitter::from_tree(tree);
+    assert_eq!(cached.sort(view), &expected[..]);
+}
+
+#[cfg(feature = "dump")]
+#[test]
+fn dump_replay() {
+    use euclid::{default::Transform3D, Angle};
+    use plane_split::{BspSplitter, Dump};
+
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let mut dump = Dump::new(vec3(0.0, 0.0, -1.0));
+    for (i, &angle) in [-0.5, 0.0, 0.5].iter().enumerate() {
+        let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+        dump.add(&Polygon::from_transformed_rect(rect, transform, i).unwrap());
+    }
+
+    let mut data = Vec::new();
+    dump.to_writer(&mut data).unwrap();
+    let loaded = Dump::from_reader(&data[..]).unwrap();
+    assert_eq!(dump, loaded);
+
+    let mut splitter = BspSplitter::new();
+    let expected = splitter.solve(&dump.polygons, dump.view).to_vec();
+    assert_eq!(loaded.replay(&mut splitter), expected);
+}
+
+#[cfg(feature = "dump")]
+#[test]
+fn dump_minimize() {
+    use euclid::default::Transform3D;
+    use plane_split::Dump;
+
+    let rect: Rect<f64> = rect(-1.37, -2.51, 3.2, 4.9);
+    let mut dump = Dump::new(vec3(0.0, 0.0, -1.0));
+    for i in 0..20 {
+        let transform = Transform3D::translation(0.0, 0.0, i as f64 + 0.25);
+        dump.add(&Polygon::from_transformed_rect(rect, transform, i).unwrap());
+    }
+
+    let mut calls = 0;
+    let minimal = dump.minimize(|d| {
+        calls += 1;
+        let anchors = d.polygons.iter().map(|p| p.anchor).collect::<Vec<_>>();
+        anchors.contains(&3) && anchors.contains(&11)
+    });
+    assert_eq!(minimal.polygons.len(), 2);
+    assert_eq!(minimal.polygons[0].anchor, 3);
+    assert_eq!(minimal.polygons[1].anchor, 11);
+    for poly in &minimal.polygons {
+        assert!(poly.is_valid());
+        assert!(poly.points.iter().all(|p| p.round() == *p));
+    }
+    assert!(calls < 100);
+}
+
+#[cfg(feature = "dump")]
+#[test]
+fn dump_diff() {
+    use euclid::{default::Transform3D, Angle};
+    use plane_split::{BspSplitter, Dump};
+
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let mut dump = Dump::new(vec3(0.0, 0.0, -1.0));
+    for (i, &angle) in [-0.5, 0.0, 0.5].iter().enumerate() {
+        let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+        dump.add(&Polygon::from_transformed_rect(rect, transform, i).unwrap());
+    }
+    let sorted = dump.replay(&mut BspSplitter::new());
+    dump.set_sorted(&sorted);
+    assert!(dump.diff(&dump).is_empty());
+
+    // dumps without the sorted fragments can still be loaded
+    let json = r#"{"polygons":[],"view":[0.0,0.0,1.0]}"#;
+    let empty: Dump<usize> = Dump::from_reader(json.as_bytes()).unwrap();
+    assert!(empty.sorted.is_empty());
+
+    let mut other = dump.clone();
+    other.polygons.remove(1);
+    other.sorted[0].points.rotate_left(1);
+    let last = other.sorted.len() - 1;
+    let fragment = other.sorted.remove(last);
+    other.sorted.insert(1, fragment);
+    let removed = other.sorted.remove(2);
+    let diff = dump.diff(&other);
+    assert!(!diff.view_changed);
+    assert_eq!(diff.removed_polygons, vec![1]);
+    assert!(diff.added_polygons.is_empty());
+    assert_eq!(diff.removed_fragments, vec![1]);
+    assert!(diff.added_fragments.is_empty());
+    assert_eq!(diff.moved_fragments, vec![(last, 1)]);
+    assert_eq!(diff.coverage.len(), 1);
+    let (anchor, before, after) = diff.coverage[0];
+    assert_eq!(anchor, removed.anchor);
+    assert!((before - after - removed.area()).abs() < 1e-6);
+}
@@ -1,15 +1,27 @@
 use euclid::{
     default::{Rect, Transform3D},
-    rect, vec3, Angle,
+    point3, rect, vec3, Angle, UnknownUnit,
 };
 use plane_split::PlaneCut;
-use plane_split::{make_grid, BspSplitter, Polygon};
-use std::f64::consts::FRAC_PI_4;
+use plane_split::{
+    estimate_fragments, make_grid, order_pair, validate, verify_order, AutoSplitter, BspSplitter,
+    EpsilonConfig, FlatOutput, Heuristic, Polygon, ProbeResult, ProfileEvent, Profiler, Sidedness,
+    SortOptions, SortOrder, Splitter, TraversalOrder, TriangleBuffer, ValidationError,
+};
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    f64::consts::{FRAC_PI_2, FRAC_PI_4, PI},
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
 
 fn grid_impl(count: usize, splitter: &mut BspSplitter<usize>) {
     let polys = make_grid(count);
     let result = splitter.solve(&polys, vec3(0.0, 0.0, 1.0));
     assert_eq!(result.len(), count + count * count + count * count * count);
+    assert_eq!(splitter.validate(vec3(0.0, 0.0, 1.0)), Ok(()));
+    assert_eq!(verify_order(splitter.sorted(), vec3(0.0, 0.0, 1.0)), Ok(()));
 }
 
 #[test]
@@ -17,6 +29,31 @@ fn grid_bsp() {
     grid_impl(2, &mut BspSplitter::new());
 }
 
+#[test]
+fn grid_bsp_owned() {
+    let mut splitter = BspSplitter::new();
+    let result = splitter.solve(make_grid(2), vec3(0.0, 0.0, 1.0));
+    assert_eq!(result.len(), 2 + 2 * 2 + 2 * 2 * 2);
+}
+
+#[test]
+fn grid_budget() {
+    let count = 4;
+    let polys = make_grid(count);
+    let mut splitter = BspSplitter::new();
+    splitter.set_budget(Some(20));
+    let result = splitter.solve(&polys, vec3(0.0, 0.0, 1.0));
+    // every polygon added past the budget stays whole
+    assert!(result.len() < count + count * count + count * count * count);
+    assert!(result.len() <= 20 + polys.len());
+    assert!(splitter.budget_overflow() > 0);
+
+    splitter.reset();
+    splitter.set_budget(None);
+    splitter.solve(&polys, vec3(0.0, 0.0, 1.0));
+    assert_eq!(splitter.budget_overflow(), 0);
+}
+
 fn sort_rotation(splitter: &mut BspSplitter<usize>) {
     let transform0: Transform3D<f64> =
         Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(-FRAC_PI_4));
@@ -44,7 +81,35 @@ fn rotation_bsp() {
     sort_rotation(&mut BspSplitter::new());
 }
 
-fn sort_trivial(splitter: &mut BspSplitter<usize>) {
+#[test]
+fn rotation_bsp_indices() {
+    let mut splitter = BspSplitter::new();
+    sort_rotation(&mut splitter);
+    let ids = splitter.sort_indices(vec3(0.0, 0.0, -1.0)).to_vec();
+    let anchors: Vec<_> = ids.iter().map(|&id| splitter.fragment(id).anchor).collect();
+    assert_eq!(&anchors, &[2, 1, 0, 1, 2]);
+}
+
+#[test]
+fn rotation_bsp_f32() {
+    let rect: Rect<f32> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4 as f32, 0.0, FRAC_PI_4 as f32]
+        .iter()
+        .enumerate()
+        .map(|(anchor, &angle)| {
+            let transform: Transform3D<f32> =
+                Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, anchor).unwrap()
+        })
+        .collect();
+
+    let mut splitter = BspSplitter::new();
+    let result = splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    let ids: Vec<_> = result.iter().map(|poly| poly.anchor).collect();
+    assert_eq!(&ids, &[2, 1, 0, 1, 2]);
+}
+
+fn sort_trivial<S: Splitter<usize>>(splitter: &mut S) {
     let anchors: Vec<_> = (0usize..10).collect();
     let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
     let polys: Vec<_> = anchors
@@ -82,16 +147,485 @@ fn sort_external(splitter: &mut BspSplitter<usize>) {
     assert_eq!(anchors, vec![1, 0, 1]);
 }
 
+#[test]
+fn sort_from_point() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys = [
+        Polygon::from_rect(rect, 0),
+        Polygon::from_transformed_rect(rect, Transform3D::translation(0.0, 0.0, 10.0), 1).unwrap(),
+        Polygon::from_transformed_rect(rect, Transform3D::translation(0.0, 0.0, 5.0), 2).unwrap(),
+    ];
+    let mut splitter = BspSplitter::new();
+    for poly in &polys {
+        splitter.add(poly.clone());
+    }
+
+    let anchors: Vec<_> = splitter
+        .sort_from_point(point3(0.0, 0.0, 20.0))
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![0, 2, 1]);
+
+    let anchors: Vec<_> = splitter
+        .sort_from_point(point3(0.0, 0.0, -20.0))
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![1, 2, 0]);
+}
+
+#[test]
+fn cull_occluded() {
+    let make_poly = |rect: Rect<f64>, z: f64, anchor: usize| {
+        Polygon::from_transformed_rect(rect, Transform3D::translation(0.0, 0.0, z), anchor).unwrap()
+    };
+    let polys = [
+        make_poly(rect(-10.0, -10.0, 20.0, 20.0), 0.0, 0),
+        make_poly(rect(-5.0, -5.0, 10.0, 10.0), 5.0, 1),
+        make_poly(rect(0.0, 0.0, 20.0, 20.0), 10.0, 2),
+    ];
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+
+    let anchors: Vec<_> = splitter
+        .solve(&polys, view)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![2, 1, 0]);
+
+    // the predicate can capture the state of the renderer
+    let opaque_layers = [0];
+    let options = SortOptions {
+        cull_occluded: true,
+        opaque: &|anchor| opaque_layers.contains(anchor),
+        ..SortOptions::default()
+    };
+    let anchors: Vec<_> = splitter
+        .sort_with_options(view, &options)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![2, 0]);
+
+    let options = SortOptions {
+        order: SortOrder::FrontToBack,
+        ..options
+    };
+    let anchors: Vec<_> = splitter
+        .sort_with_options(view, &options)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![0, 2]);
+
+    let options = SortOptions {
+        cull_occluded: false,
+        ..options
+    };
+    let anchors: Vec<_> = splitter
+        .sort_with_options(view, &options)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![0, 1, 2]);
+}
+
+#[test]
+fn cull_backfaces() {
+    // the sides of a box, with the faces pointing out of it
+    let rect: Rect<f64> = rect(-1.0, -1.0, 2.0, 2.0);
+    let polys: Vec<_> =
+        [0.0, PI, FRAC_PI_2, -FRAC_PI_2]
+            .iter()
+            .enumerate()
+            .map(|(i, &angle)| {
+                let transform = Transform3D::translation(0.0, 0.0, 1.0)
+                    .then(&Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle)));
+                Polygon::from_transformed_rect(rect, transform, i).unwrap()
+            })
+            .collect();
+    let view = vec3(0.1, 0.0, 1.0);
+    let mut splitter = BspSplitter::new();
+    splitter.solve(&polys, view);
+
+    let options = SortOptions {
+        cull_backfaces: true,
+        ..SortOptions::default()
+    };
+    let mut anchors: Vec<_> = splitter
+        .sort_with_options(view, &options)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    anchors.sort();
+    let mut front: Vec<_> = polys
+        .iter()
+        .filter(|p| p.plane.normal.dot(view) > 0.0)
+        .map(|p| p.anchor)
+        .collect();
+    front.sort();
+    assert_eq!(front.len(), 2);
+    assert_eq!(anchors, front);
+
+    // the predicate can capture the state of the renderer
+    let double_sided_layers: HashSet<usize> = [1].iter().cloned().collect();
+    let options = SortOptions {
+        sidedness: &|anchor| {
+            if double_sided_layers.contains(anchor) {
+                Sidedness::DoubleSided
+            } else {
+                Sidedness::SingleSided
+            }
+        },
+        ..options
+    };
+    let culled: Vec<_> = splitter
+        .sort_with_options(view, &options)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(culled.len(), 3);
+    // the order of the remaining fragments is kept
+    let all: Vec<_> = splitter.sort(view).iter().map(|p| p.anchor).collect();
+    let expected: Vec<_> = all
+        .into_iter()
+        .filter(|&a| a == 1 || front.contains(&a))
+        .collect();
+    assert_eq!(culled, expected);
+}
+
+#[test]
+fn sidedness() {
+    let rect: Rect<f64> = rect(-1.0, -1.0, 2.0, 2.0);
+    let polys: Vec<_> =
+        [0.0, PI, FRAC_PI_2, -FRAC_PI_2]
+            .iter()
+            .enumerate()
+            .map(|(i, &angle)| {
+                let transform = Transform3D::translation(0.0, 0.0, 1.0)
+                    .then(&Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle)));
+                Polygon::from_transformed_rect(rect, transform, i).unwrap()
+            })
+            .collect();
+    let view = vec3(0.1, 0.0, 1.0);
+    let mut splitter = BspSplitter::new();
+    splitter.solve(&polys, view);
+    assert_eq!(splitter.sort(view).len(), 4);
+
+    // the sidedness only matters when culling the back faces
+    let options = SortOptions {
+        sidedness: &|_| Sidedness::SingleSided,
+        ..SortOptions::default()
+    };
+    assert_eq!(splitter.sort_with_options(view, &options).len(), 4);
+
+    let options = SortOptions {
+        cull_backfaces: true,
+        sidedness: &|_| Sidedness::DoubleSided,
+        ..SortOptions::default()
+    };
+    assert_eq!(splitter.sort_with_options(view, &options).len(), 4);
+
+    // single-sided by default
+    assert_eq!(Sidedness::default(), Sidedness::SingleSided);
+    let options = SortOptions {
+        cull_backfaces: true,
+        ..SortOptions::default()
+    };
+    assert_eq!(splitter.sort_with_options(view, &options).len(), 2);
+    assert_eq!(splitter.sort(view).len(), 4);
+}
+
+#[test]
+fn drop_small() {
+    let polys = [
+        Polygon::from_rect(rect(0.0, 0.0, 10.0, 10.0), 0),
+        Polygon::from_transformed_rect(
+            rect(0.0, 0.0, 0.5, 0.5),
+            Transform3D::translation(0.0, 0.0, 1.0),
+            1,
+        )
+        .unwrap(),
+        // perpendicular to the view, so it has no projected area
+        Polygon::from_transformed_rect(
+            rect(0.0, 0.0, 10.0, 10.0),
+            Transform3D::rotation(0.0, 1.0, 0.0, Angle::frac_pi_2())
+                .then_translate(vec3(20.0, 0.0, 0.0)),
+            2,
+        )
+        .unwrap(),
+    ];
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+    splitter.solve(&polys, view);
+
+    let options = SortOptions {
+        min_world_area: 1.0,
+        ..SortOptions::default()
+    };
+    let mut anchors: Vec<_> = splitter
+        .sort_with_options(view, &options)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    anchors.sort();
+    assert_eq!(anchors, vec![0, 2]);
+    assert_eq!(splitter.dropped_small_count(), 1);
+
+    let options = SortOptions {
+        min_projected_area: 1.0,
+        viewport_scale: 4.0,
+        ..SortOptions::default()
+    };
+    let mut anchors: Vec<_> = splitter
+        .sort_with_options(view, &options)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    anchors.sort();
+    assert_eq!(anchors, vec![0, 1]);
+    assert_eq!(splitter.dropped_small_count(), 1);
+
+    splitter.sort_with_options(view, &SortOptions::default());
+    assert_eq!(splitter.dropped_small_count(), 0);
+}
+
+#[test]
+fn sort_merged() {
+    let wall = Polygon::from_transformed_rect(
+        rect(-1.0, -1.0, 2.0, 2.0),
+        Transform3D::rotation(0.0, 1.0, 0.0, Angle::frac_pi_2()),
+        1,
+    )
+    .unwrap();
+    let floor = Polygon::from_rect(rect(-1.0, -1.0, 2.0, 2.0), 0);
+    let mut splitter: BspSplitter<usize> = BspSplitter::new();
+    splitter.add(wall);
+    splitter.add(floor);
+
+    // the wall is seen edge-on, so the floor halves can be merged across it
+    let view = vec3(0.0, 0.0, -1.0);
+    assert_eq!(splitter.sort(view).len(), 3);
+    let result = splitter.sort_merged(view);
+    assert_eq!(result.len(), 2);
+    let merged = result.iter().find(|p| p.anchor == 0).unwrap();
+    assert_eq!(merged.points.len(), 4);
+    assert!((merged.area() - 4.0).abs() < 1e-9);
+    assert!(merged.is_valid());
+
+    // otherwise the wall is drawn between the halves
+    let view = vec3(1.0, 0.0, -1.0);
+    let anchors: Vec<_> = splitter
+        .sort_merged(view)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![0, 1, 0]);
+}
+
+#[test]
+fn repair_t_junctions() {
+    let wall = Polygon::from_transformed_rect(
+        rect(-1.0, -1.0, 2.0, 2.0),
+        Transform3D::rotation(0.0, 1.0, 0.0, Angle::frac_pi_2()),
+        1,
+    )
+    .unwrap();
+    // only splits the right half of the floor
+    let half_wall = Polygon::from_transformed_rect(
+        rect(0.0, -1.0, 1.0, 2.0),
+        Transform3D::rotation(1.0, 0.0, 0.0, Angle::frac_pi_2()),
+        2,
+    )
+    .unwrap();
+    let floor = Polygon::from_rect(rect(-1.0, -1.0, 2.0, 2.0), 0);
+    let mut splitter = BspSplitter::new();
+    let view = vec3(0.0, 0.0, -1.0);
+    splitter.solve(&[wall, half_wall, floor], view);
+
+    let options = SortOptions {
+        repair_t_junctions: true,
+        ..SortOptions::default()
+    };
+    let result = splitter.sort_with_options(view, &options);
+    let floor: Vec<_> = result.iter().filter(|p| p.anchor == 0).collect();
+    assert_eq!(floor.len(), 3);
+    let left = floor.iter().find(|p| p.centroid().x < 0.0).unwrap();
+    assert_eq!(left.points.len(), 5);
+    assert!(left.points.contains(&point3(0.0, 0.0, 0.0)));
+    assert!(left.is_valid());
+    for poly in floor.iter().filter(|p| p.centroid().x > 0.0) {
+        assert_eq!(poly.points.len(), 4);
+    }
+}
+
+#[test]
+fn vertex_grid() {
+    let solve = |offset: f64| {
+        let polys = [
+            Polygon::from_transformed_rect(
+                rect(-1.0, -1.0, 2.0, 2.0),
+                Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(0.3))
+                    .then_translate(vec3(offset, 0.0, 0.0)),
+                0,
+            )
+            .unwrap(),
+            Polygon::from_transformed_rect(
+                rect(-1.0, -1.0, 2.0, 2.0),
+                Transform3D::rotation(1.0, 0.0, 0.0, Angle::radians(0.7))
+                    .then_translate(vec3(0.0, offset, 0.0)),
+                1,
+            )
+            .unwrap(),
+        ];
+        let view = vec3(0.0, 0.0, -1.0);
+        let mut splitter = BspSplitter::new();
+        splitter.solve(&polys, view);
+        let options = SortOptions {
+            vertex_grid: 1.0 / 256.0,
+            ..SortOptions::default()
+        };
+        splitter
+            .sort_with_options(view, &options)
+            .iter()
+            .map(|p| (p.anchor, p.points.clone()))
+            .collect::<Vec<_>>()
+    };
+
+    let result = solve(0.0);
+    assert_eq!(result.len(), 3);
+    assert_eq!(result, solve(1e-9));
+    for (_, points) in &result {
+        for p in points {
+            assert_eq!(p.x * 256.0, (p.x * 256.0).round());
+        }
+    }
+}
+
 #[test]
 fn trivial_bsp() {
     sort_trivial(&mut BspSplitter::new());
 }
 
+#[test]
+fn trivial_auto() {
+    let mut splitter = AutoSplitter::new();
+    sort_trivial(&mut splitter);
+    assert!(!splitter.used_bsp());
+}
+
+#[test]
+fn overlapping_auto() {
+    // the bounds are disjoint, but the polygons overlap along the view,
+    // and the centroid of the far one is nearer to the viewer
+    let far: Polygon<_> = Polygon::from_points(
+        vec![
+            point3(0.5, 0.0, -10.0),
+            point3(0.5, 1.0, -10.0),
+            point3(0.5, 1.0, 100.0),
+            point3(0.5, 0.0, 100.0),
+        ],
+        0,
+    )
+    .unwrap();
+    let near = Polygon::from_rect(rect(2.0, 0.0, 1.0, 1.0), 1);
+    let view = vec3(1.0, 0.0, 1.0);
+    let mut splitter = AutoSplitter::new();
+    let anchors: Vec<_> = splitter
+        .solve(&[far.clone(), near.clone()], view)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert!(!splitter.used_bsp());
+    assert_eq!(anchors, vec![0, 1]);
+    let anchors: Vec<_> = splitter
+        .solve(&[near, far], view)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![0, 1]);
+}
+
+#[test]
+fn intersecting_auto() {
+    let wall = Polygon::from_transformed_rect(
+        rect(-1.0, -1.0, 2.0, 2.0),
+        Transform3D::rotation(0.0, 1.0, 0.0, Angle::frac_pi_2()),
+        1,
+    )
+    .unwrap();
+    let floor = Polygon::from_rect(rect(-1.0, -1.0, 2.0, 2.0), 0);
+    let mut splitter = AutoSplitter::new();
+    let result = splitter.solve(&[wall, floor], vec3(0.0, 0.0, -1.0));
+    assert_eq!(result.len(), 3);
+    assert!(splitter.used_bsp());
+}
+
 #[test]
 fn external_bsp() {
     sort_external(&mut BspSplitter::new());
 }
 
+#[test]
+fn epsilon_coplanarity() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let transform = Transform3D::translation(0.0, 0.0, 0.01);
+    let polys = [
+        Polygon::from_rect(rect, 0),
+        Polygon::from_transformed_rect(rect, transform, 1).unwrap(),
+    ];
+    let view = vec3(0.0, 0.0, -1.0);
+
+    // by default, the polygons are far enough apart to be ordered by depth
+    let mut splitter = BspSplitter::new();
+    let anchors: Vec<_> = splitter
+        .solve(&polys, view)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![1, 0]);
+
+    // with a coarse threshold, they are treated as siblings
+    let mut splitter = BspSplitter::with_epsilons(EpsilonConfig {
+        coplanarity: 0.1,
+        ..EpsilonConfig::default()
+    });
+    let anchors: Vec<_> = splitter
+        .solve(&polys, view)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![0, 1]);
+}
+
+#[test]
+fn sibling_order() {
+    let polys: [Polygon<_>; 3] = [
+        Polygon::from_rect(rect(-10.0, -10.0, 20.0, 20.0), 1),
+        Polygon::from_rect(rect(-5.0, -5.0, 20.0, 20.0), 0),
+        Polygon::from_rect(rect(0.0, 0.0, 20.0, 20.0), 2),
+    ];
+    let view = vec3(0.0, 0.0, 1.0);
+    let mut splitter = BspSplitter::new();
+
+    let anchors: Vec<_> = splitter
+        .solve(&polys, view)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![1, 0, 2]);
+
+    splitter.set_sibling_order(|a: &usize, b: &usize| b.cmp(a));
+    let anchors: Vec<_> = splitter
+        .solve(&polys, view)
+        .iter()
+        .map(|p| p.anchor)
+        .collect();
+    assert_eq!(anchors, vec![2, 1, 0]);
+}
+
 #[test]
 fn test_cut() {
     use smallvec::SmallVec;
@@ -135,4 +669,1120 @@ fn test_cut() {
     assert_eq!(poly.cut(&poly2, &mut front, &mut back), PlaneCut::Cut);
     assert_eq!(back.len(), 1);
     assert!(front.is_empty());
+
+    back.clear();
+
+    // test splitting, which produces a single fragment per side
+    let transform = Transform3D::rotation(1.0, 0.0, 0.0, Angle::radians(2.0 * FRAC_PI_4));
+    let poly3 = Polygon::from_transformed_rect(rect, transform, 0).unwrap();
+    assert_eq!(poly.cut(&poly3, &mut front, &mut back), PlaneCut::Cut);
+    assert_eq!((front.len(), back.len()), (1, 1));
+    assert!(!front.spilled() && !back.spilled());
+}
+
+#[test]
+fn stats() {
+    let wall = Polygon::from_transformed_rect(
+        rect(-1.0, -1.0, 2.0, 2.0),
+        Transform3D::rotation(0.0, 1.0, 0.0, Angle::frac_pi_2()),
+        1,
+    )
+    .unwrap();
+    let floor = Polygon::from_rect(rect(-1.0, -1.0, 2.0, 2.0), 0);
+    let mut splitter = BspSplitter::new();
+
+    splitter.solve(&[wall, floor], vec3(0.0, 0.0, -1.0));
+    let stats = splitter.stats();
+    assert_eq!(stats.input_polys, 2);
+    assert_eq!(stats.output_polys, 3);
+    assert_eq!(stats.cuts_performed, 1);
+    assert_eq!(stats.tree_depth, 2);
+    assert_eq!(stats.node_count, 3);
+    assert!(stats.allocations > 0);
+
+    splitter.reset();
+    let stats = splitter.stats();
+    assert_eq!(stats.input_polys, 0);
+    assert_eq!(stats.cuts_performed, 0);
+}
+
+#[test]
+fn traverse() {
+    let wall = Polygon::from_transformed_rect(
+        rect(-1.0, -1.0, 2.0, 2.0),
+        Transform3D::rotation(0.0, 1.0, 0.0, Angle::frac_pi_2()),
+        1,
+    )
+    .unwrap();
+    let floor = Polygon::from_rect(rect(-1.0, -1.0, 2.0, 2.0), 0);
+    let mut splitter = BspSplitter::new();
+    splitter.add(wall.clone());
+    splitter.add(floor);
+
+    let nodes: Vec<_> = splitter.traverse(TraversalOrder::PreOrder).collect();
+    assert_eq!(nodes.len(), 3);
+    assert_eq!(nodes[0].depth(), 0);
+    assert_eq!(nodes[0].plane(), &wall.plane);
+    assert_eq!(nodes[0].polygons().count(), 1);
+    for node in &nodes[1..] {
+        assert_eq!(node.depth(), 1);
+        assert_eq!(node.polygons().next().unwrap().anchor, 0);
+        assert_eq!(node.fragment_ids().len(), 1);
+    }
+    let (back, front) = (nodes[1].id(), nodes[2].id());
+    let order = |order| -> Vec<_> { splitter.traverse(order).map(|node| node.id()).collect() };
+    assert_eq!(
+        order(TraversalOrder::InOrder),
+        vec![back, nodes[0].id(), front]
+    );
+    assert_eq!(
+        order(TraversalOrder::PostOrder),
+        vec![back, front, nodes[0].id()]
+    );
+
+    // skipping the sub-trees of the root
+    let mut visited = 0;
+    splitter.visit(|_| {
+        visited += 1;
+        false
+    });
+    assert_eq!(visited, 1);
+}
+
+#[test]
+fn subtree_bounds() {
+    // a chain of layers, each on the front side of the previous one
+    let mut splitter = BspSplitter::new();
+    for i in 0..8 {
+        let transform = Transform3D::translation(0.0, 0.0, i as f64);
+        let rect = rect(i as f64 * 10.0, 0.0, 5.0, 5.0);
+        splitter.add(Polygon::from_transformed_rect(rect, transform, i).unwrap());
+    }
+    for node in splitter.traverse(TraversalOrder::PreOrder) {
+        let bounds = node.bounds();
+        let depth = node.depth() as f64;
+        assert_eq!(bounds.min, point3(depth * 10.0, 0.0, depth));
+        assert_eq!(bounds.max, point3(75.0, 5.0, 7.0));
+    }
+
+    // the sub-trees outside of the region are skipped as a whole
+    let mut visited = 0;
+    splitter.visit(|node| {
+        visited += 1;
+        node.bounds().min.x < 25.0
+    });
+    assert_eq!(visited, 4);
+    let hit = splitter.raycast(point3(72.0, 2.0, -20.0), vec3(0.0, 0.0, 1.0));
+    assert_eq!(hit.unwrap().anchor, 7);
+    assert_eq!(
+        splitter.raycast(point3(77.0, 2.0, -20.0), vec3(0.0, 0.0, 1.0)),
+        None
+    );
+
+    // the bounds shrink when the fragments are removed
+    splitter.remove(|&anchor| anchor >= 6);
+    let root = splitter.traverse(TraversalOrder::PreOrder).next().unwrap();
+    assert_eq!(root.bounds().max, point3(55.0, 5.0, 5.0));
+}
+
+#[test]
+fn deep_tree() {
+    let count = 1000;
+    let mut splitter: BspSplitter<_> = BspSplitter::new();
+    assert_eq!(splitter.depth(), 0);
+    for i in 0..count {
+        let z = i as f64;
+        let poly = Polygon::from_points(
+            vec![
+                point3(0.0, 0.0, z),
+                point3(1.0, 0.0, z),
+                point3(1.0, 1.0, z),
+                point3(0.0, 1.0, z),
+            ],
+            i,
+        )
+        .unwrap();
+        splitter.add(poly);
+    }
+    // every plane is parallel to the others, so the tree is a single chain
+    assert_eq!(splitter.depth(), count);
+    assert_eq!(splitter.node_count(), count);
+    assert_eq!(splitter.fragment_count(), count);
+
+    let result = splitter.sort(vec3(0.0, 0.0, -1.0));
+    let anchors: Vec<_> = result.iter().map(|poly| poly.anchor).collect();
+    let expected: Vec<_> = (0..count).rev().collect();
+    assert_eq!(anchors, expected);
+}
+
+#[test]
+fn heuristic_min_splits() {
+    // a small wall, which plane cuts through all the floors
+    let mut polys: Vec<Polygon<_>> = vec![Polygon::from_points(
+        vec![
+            point3(0.0, 0.0, 0.0),
+            point3(0.0, 1.0, 0.0),
+            point3(0.0, 1.0, 1.0),
+            point3(0.0, 0.0, 1.0),
+        ],
+        0,
+    )
+    .unwrap()];
+    for i in 1..5 {
+        let z = 1.0 + i as f64;
+        polys.push(
+            Polygon::from_points(
+                vec![
+                    point3(-1.0, -1.0, z),
+                    point3(1.0, -1.0, z),
+                    point3(1.0, 1.0, z),
+                    point3(-1.0, 1.0, z),
+                ],
+                i,
+            )
+            .unwrap(),
+        );
+    }
+
+    let mut splitter = BspSplitter::new();
+    assert_eq!(splitter.solve(&polys, vec3(0.0, 0.0, -1.0)).len(), 9);
+
+    let mut splitter = BspSplitter::with_heuristic(Heuristic::MinSplits);
+    let result = splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    let anchors: Vec<_> = result.iter().map(|poly| poly.anchor).collect();
+    assert_eq!(anchors, [4, 3, 2, 1, 0]);
+}
+
+#[test]
+fn deterministic() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4, 0.0, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+
+    let mut splitter = BspSplitter::new();
+    splitter.set_deterministic(true);
+    let forward = splitter.solve(&polys, vec3(0.0, 0.0, -1.0)).to_vec();
+    let backward = splitter
+        .solve(polys.iter().rev(), vec3(0.0, 0.0, -1.0))
+        .to_vec();
+    assert_eq!(forward, backward);
+}
+
+#[test]
+fn validate_order() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4, 0.0, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+    let view = vec3(0.0, 0.0, -1.0);
+    let epsilons = EpsilonConfig::default();
+    assert_eq!(
+        validate(&polys, view, &epsilons),
+        Err(ValidationError::Intersecting(0, 1))
+    );
+
+    let mut splitter = BspSplitter::new();
+    let mut sorted = splitter.solve(&polys, view).to_vec();
+    assert_eq!(splitter.validate(view), Ok(()));
+    assert_eq!(validate(&sorted, view, &epsilons), Ok(()));
+
+    assert_eq!(verify_order(&sorted, view), Ok(()));
+
+    sorted.reverse();
+    assert_eq!(
+        validate(&sorted, view, &epsilons),
+        Err(ValidationError::WrongOrder(0, 1))
+    );
+    let violation = verify_order(&sorted, view).unwrap_err();
+    assert_eq!((violation.former, violation.latter), (0, 1));
+    assert_eq!(violation.former_anchor, sorted[0].anchor);
+    assert_eq!(violation.latter_anchor, sorted[1].anchor);
+    let overlap = violation.overlap.unwrap();
+    assert!(overlap.area() > 0.0 && overlap.area() <= sorted[0].area() + 1e-6);
+    assert_eq!(overlap.plane, sorted[0].plane);
+}
+
+#[test]
+fn pair_order() {
+    let view = vec3(0.0, 0.0, -1.0);
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let near = Polygon::from_rect(rect, 0);
+    let far =
+        Polygon::from_transformed_rect(rect, Transform3D::translation(0.0, 0.0, 1.0), 1).unwrap();
+    let aside =
+        Polygon::from_transformed_rect(rect, Transform3D::translation(30.0, 0.0, 1.0), 2).unwrap();
+    assert_eq!(order_pair(&near, &far, view), Ordering::Greater);
+    assert_eq!(order_pair(&far, &near, view), Ordering::Less);
+    assert_eq!(order_pair(&near, &aside, view), Ordering::Equal);
+
+    // crossing each other's planes, but only the far part of the second one
+    // is over the first one on the screen
+    let triangle = Polygon::from_points(
+        vec![
+            point3(-5.0, 0.0, 0.0),
+            point3(5.0, 0.0, 0.0),
+            point3(5.0, 10.0, 0.0),
+        ],
+        0,
+    )
+    .unwrap();
+    let slope = Polygon::from_points(
+        vec![
+            point3(-5.0, 8.0, -5.0),
+            point3(5.0, 8.0, 5.0),
+            point3(5.0, 10.0, 5.0),
+            point3(-5.0, 10.0, -5.0),
+        ],
+        1,
+    )
+    .unwrap();
+    assert_eq!(order_pair(&triangle, &slope, view), Ordering::Greater);
+    assert_eq!(order_pair(&slope, &triangle, view), Ordering::Less);
+
+    // intersecting polygons can't be ordered
+    let rotated = |angle: f64| {
+        let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+        Polygon::from_transformed_rect(rect, transform, 0).unwrap()
+    };
+    assert_eq!(
+        order_pair(&rotated(0.0), &rotated(FRAC_PI_4), view),
+        Ordering::Equal
+    );
+    let sorted = BspSplitter::new()
+        .solve(&[rotated(0.0), rotated(FRAC_PI_4)], view)
+        .to_vec();
+    for (i, a) in sorted.iter().enumerate() {
+        for b in &sorted[i + 1..] {
+            assert_ne!(order_pair(a, b, view), Ordering::Greater);
+        }
+    }
+}
+
+#[test]
+fn provenance() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4, 0.0, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+
+    for &heuristic in &[Heuristic::FirstPolygon, Heuristic::MinSplits] {
+        let mut splitter = BspSplitter::with_heuristic(heuristic);
+        splitter.set_track_provenance(true);
+        for poly in &polys {
+            splitter.add(poly.clone());
+        }
+        let ids = splitter.sort_indices(vec3(0.0, 0.0, -1.0)).to_vec();
+        assert_eq!(ids.len(), 5);
+        let mut cut_count = 0;
+        for id in ids {
+            let info = splitter.fragment_info(id).unwrap();
+            assert_eq!(info.source_anchor, splitter.fragment(id).anchor);
+            for &plane in &info.cut_planes {
+                assert_ne!(splitter.plane_source(plane).anchor, info.source_anchor);
+            }
+            cut_count += info.cut_planes.len();
+        }
+        // two polygons are split in half by the plane of the root
+        assert_eq!(cut_count, 4);
+    }
+
+    let mut splitter = BspSplitter::new();
+    splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    let ids = splitter.sort_indices(vec3(0.0, 0.0, -1.0)).to_vec();
+    assert!(ids.iter().all(|&id| splitter.fragment_info(id).is_none()));
+}
+
+#[test]
+fn provenance_late() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4, 0.0, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+    let view = vec3(0.0, 0.0, -1.0);
+
+    let mut splitter = BspSplitter::new();
+    splitter.solve(&polys, view);
+    // turned on with the fragments already in the tree
+    splitter.set_track_provenance(true);
+    // removing the root polygon rebuilds the tree
+    splitter.remove(|&a| a == 0);
+    // and the new polygon reuses the storage of the removed fragments
+    let transform = Transform3D::translation(0.0, 0.0, 5.0);
+    splitter.add(Polygon::from_transformed_rect(rect, transform, 3).unwrap());
+
+    let ids = splitter.sort_indices(view).to_vec();
+    assert!(ids.len() >= 3);
+    for id in ids {
+        let info = splitter.fragment_info(id).unwrap();
+        assert_eq!(info.source_anchor, splitter.fragment(id).anchor);
+        for &plane in &info.cut_planes {
+            assert_ne!(splitter.plane_source(plane).anchor, info.source_anchor);
+        }
+    }
+}
+
+#[test]
+fn fragment_keys() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4, 0.0, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i * 10).unwrap()
+        })
+        .collect();
+
+    for &heuristic in &[Heuristic::FirstPolygon, Heuristic::MinSplits] {
+        let mut splitter = BspSplitter::with_heuristic(heuristic);
+        splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+        let mut keys: Vec<_> = splitter.sorted_keys().collect();
+        assert_eq!(keys.len(), 5);
+        for &(anchor, key) in &keys {
+            assert_eq!(anchor, key.source as usize * 10);
+        }
+        keys.sort_by_key(|&(_, key)| key);
+        keys.dedup();
+        assert_eq!(keys.len(), 5);
+        // two polygons are split in half, and their fragments are numbered
+        let splits: Vec<_> = keys.iter().map(|(_, key)| key.split).collect();
+        assert_eq!(splits.iter().filter(|&&split| split == 1).count(), 2);
+
+        let id = splitter.sort_indices(vec3(0.0, 0.0, -1.0))[0];
+        assert_eq!(
+            splitter.fragment_key(id),
+            splitter.sorted_keys().next().unwrap().1
+        );
+    }
+}
+
+#[test]
+fn unproject_fragments() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let transforms: Vec<Transform3D<f64>> = [-FRAC_PI_4, FRAC_PI_4]
+        .iter()
+        .map(|&angle| Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle)))
+        .collect();
+    let polys: Vec<_> = transforms
+        .iter()
+        .enumerate()
+        .map(|(i, transform)| Polygon::from_transformed_rect(rect, *transform, i).unwrap())
+        .collect();
+
+    let mut splitter = BspSplitter::new();
+    let result = splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    assert_eq!(result.len(), 3);
+    let mut areas = [0.0; 2];
+    for poly in result {
+        let uv = poly
+            .unproject_to_rect(&rect, &transforms[poly.anchor])
+            .unwrap();
+        assert_eq!(uv.len(), poly.points.len());
+        for p in &uv {
+            assert!(p.x > -1e-6 && p.x < 1.0 + 1e-6 && p.y > -1e-6 && p.y < 1.0 + 1e-6);
+        }
+        areas[poly.anchor] += (0..uv.len())
+            .map(|i| uv[i].to_vector().cross(uv[(i + 1) % uv.len()].to_vector()))
+            .sum::<f64>()
+            .abs()
+            / 2.0;
+    }
+    // the fragments of each polygon cover the whole rectangle
+    for area in &areas {
+        assert!((area - 1.0).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn sort_triangulated() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4, 0.0, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+
+    let mut splitter = BspSplitter::new();
+    for poly in &polys {
+        splitter.add(poly.clone());
+    }
+    let buffer = splitter.sort_triangulated(vec3(0.0, 0.0, -1.0));
+    let fragments = splitter.sort(vec3(0.0, 0.0, -1.0));
+    let triangle_count: usize = fragments.iter().map(|p| p.points.len() - 2).sum();
+    assert_eq!(buffer.indices.len(), 3 * triangle_count);
+    assert!(buffer.vertices.len() < fragments.iter().map(|p| p.points.len()).sum());
+    assert!(buffer
+        .indices
+        .iter()
+        .all(|&i| (i as usize) < buffer.vertices.len()));
+    let anchors: Vec<_> = buffer.ranges.iter().map(|&(a, _)| a).collect();
+    assert_eq!(anchors, [2, 1, 0, 1, 2]);
+    assert_eq!(buffer.ranges.last().unwrap().1.end, buffer.indices.len());
+
+    // coplanar neighbors are merged into a single range, sharing the vertices
+    let polys = [
+        Polygon::from_rect(euclid::rect(0.0, 0.0, 1.0, 1.0), 0),
+        Polygon::from_rect(euclid::rect(1.0, 0.0, 1.0, 1.0), 0),
+    ];
+    let sorted = splitter.solve(&polys[..], vec3(0.0, 0.0, 1.0));
+    let buffer = TriangleBuffer::from_polygons(sorted);
+    assert_eq!(buffer.vertices.len(), 6);
+    assert_eq!(buffer.ranges, [(0, 0..12)]);
+}
+
+#[test]
+fn sort_batched() {
+    let square = |x: f64, z: f64, anchor: usize| -> Polygon<usize> {
+        Polygon::from_points(
+            vec![
+                point3(x, 0.0, z),
+                point3(x + 1.0, 0.0, z),
+                point3(x + 1.0, 1.0, z),
+                point3(x, 1.0, z),
+            ],
+            anchor,
+        )
+        .unwrap()
+    };
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+
+    // the last square doesn't overlap the middle one, so it can be moved
+    let polys = [
+        square(0.0, 3.0, 0),
+        square(5.0, 2.0, 1),
+        square(2.0, 1.0, 0),
+    ];
+    splitter.solve(&polys, view);
+    let ranges = splitter.sort_batched(view);
+    let anchors: Vec<_> = splitter.sorted().iter().map(|p| p.anchor).collect();
+    assert_eq!(anchors, [0, 0, 1]);
+    assert_eq!(ranges, [(0, 0..2), (1, 2..3)]);
+
+    // the last square is covered by the middle one, so the order is kept
+    let polys = [
+        square(0.0, 3.0, 0),
+        square(2.5, 2.0, 1),
+        square(2.0, 1.0, 0),
+    ];
+    splitter.solve(&polys, view);
+    let ranges = splitter.sort_batched(view);
+    let anchors: Vec<_> = splitter.sorted().iter().map(|p| p.anchor).collect();
+    assert_eq!(anchors, [0, 1, 0]);
+    assert_eq!(ranges, [(0, 0..1), (1, 1..2), (0, 2..3)]);
+}
+
+#[test]
+fn sort_multiple_views() {
+    let mut splitter = BspSplitter::new();
+    for poly in make_grid(2) {
+        splitter.add(poly);
+    }
+    let views = [
+        vec3(1.0, 1.0, 1.0),
+        vec3(-1.0, -1.0, -1.0),
+        vec3(1.0, -0.5, 0.25),
+    ];
+    let mut results = vec![Vec::new(); views.len()];
+    for (view, result) in views.iter().zip(results.iter_mut()) {
+        splitter.sort_into(*view, result);
+    }
+    let node_count = splitter.node_count();
+
+    for (view, result) in views.iter().zip(results.iter()) {
+        assert_eq!(splitter.sort(*view), &result[..]);
+        assert_eq!(splitter.validate(*view), Ok(()));
+    }
+    // the tree is not rebuilt for each view
+    assert_eq!(splitter.node_count(), node_count);
+    assert_ne!(results[0], results[1]);
+}
+
+#[test]
+fn remove() {
+    let mut splitter = BspSplitter::new();
+    sort_rotation(&mut splitter);
+    let node_count = splitter.node_count();
+
+    // the polygon doesn't define any node planes
+    splitter.remove(|&anchor| anchor == 2);
+    let ids: Vec<_> = splitter
+        .sort(vec3(0.0, 0.0, -1.0))
+        .iter()
+        .map(|poly| poly.anchor)
+        .collect();
+    assert_eq!(&ids, &[1, 0, 1]);
+    assert_eq!(splitter.fragment_count(), 3);
+    assert_eq!(splitter.node_count(), node_count);
+
+    // the root plane is gone, so the whole tree is rebuilt
+    splitter.remove(|&anchor| anchor == 0);
+    let ids: Vec<_> = splitter
+        .sort(vec3(0.0, 0.0, -1.0))
+        .iter()
+        .map(|poly| poly.anchor)
+        .collect();
+    assert_eq!(&ids, &[1, 1]);
+    assert_eq!(splitter.fragment_count(), 2);
+    assert_eq!(splitter.validate(vec3(0.0, 0.0, -1.0)), Ok(()));
+
+    // the tree keeps working for new polygons
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(FRAC_PI_4));
+    splitter.add(Polygon::from_transformed_rect(rect, transform, 2).unwrap());
+    let ids: Vec<_> = splitter
+        .sort(vec3(0.0, 0.0, -1.0))
+        .iter()
+        .map(|poly| poly.anchor)
+        .collect();
+    assert_eq!(&ids, &[2, 1, 1, 2]);
+    assert_eq!(splitter.validate(vec3(0.0, 0.0, -1.0)), Ok(()));
+}
+
+#[test]
+fn remove_reuses_storage() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+    sort_rotation(&mut splitter);
+    let mut allocations = None;
+    for frame in 0..100 {
+        let angle = if frame % 2 == 0 {
+            FRAC_PI_4
+        } else {
+            -FRAC_PI_4
+        };
+        let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+        splitter.remove(|&anchor| anchor == 0 || anchor == 2);
+        splitter.add(Polygon::from_transformed_rect(rect, transform, 2).unwrap());
+        splitter.add(Polygon::from_rect(rect, 0));
+        splitter.sort(view);
+        assert_eq!(splitter.validate(view), Ok(()));
+        assert!(splitter.sorted_keys().all(|(_, key)| key.source < 3));
+        // the storage stops growing after the first frame
+        let count = splitter.stats().allocations;
+        assert_eq!(*allocations.get_or_insert(count), count);
+    }
+}
+
+#[test]
+fn update() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+    // the polygon 3 comes first, so it defines the plane of the polygon 1
+    for &(anchor, x, z) in &[(0, 0.0, 1.0), (3, 50.0, 2.0), (1, 0.0, 2.0), (2, 0.0, 3.0)] {
+        let transform = Transform3D::translation(x, 0.0, z);
+        splitter.add(Polygon::from_transformed_rect(rect, transform, anchor).unwrap());
+    }
+    splitter.sort(view);
+    let node_count = splitter.node_count();
+
+    // moving within the plane keeps the structure of the tree
+    let transform = Transform3D::translation(5.0, 0.0, 2.0);
+    let moved = Polygon::from_transformed_rect(rect, transform, 1).unwrap();
+    assert!(splitter.update(1, moved.clone()));
+    assert_eq!(splitter.node_count(), node_count);
+    assert_eq!(splitter.fragment_count(), 4);
+    assert!(splitter.sort(view).contains(&moved));
+
+    // the polygon defining the plane of a node is re-inserted
+    let transform = Transform3D::translation(60.0, 0.0, 2.0);
+    let moved = Polygon::from_transformed_rect(rect, transform, 3).unwrap();
+    assert!(!splitter.update(3, moved.clone()));
+    assert_eq!(splitter.fragment_count(), 4);
+    assert!(splitter.sort(view).contains(&moved));
+
+    // crossing the plane of the last polygon needs a re-insertion
+    let transform = Transform3D::translation(0.0, 0.0, 4.0);
+    let moved = Polygon::from_transformed_rect(rect, transform, 1).unwrap();
+    assert!(!splitter.update(1, moved.clone()));
+    let ids: Vec<_> = splitter.sort(view).iter().map(|poly| poly.anchor).collect();
+    assert_eq!(&ids, &[1, 2, 3, 0]);
+    assert_eq!(splitter.fragment_count(), 4);
+}
+
+#[test]
+fn update_reuses_storage() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+    for (anchor, z) in [1.0, 2.0, 3.0].iter().enumerate() {
+        let transform = Transform3D::translation(0.0, 0.0, *z);
+        splitter.add(Polygon::from_transformed_rect(rect, transform, anchor).unwrap());
+    }
+    splitter.sort(view);
+
+    let id_of = |splitter: &mut BspSplitter<usize>, anchor: usize| {
+        let ids = splitter.sort_indices(view).to_vec();
+        ids.into_iter()
+            .find(|&id| splitter.fragment(id).anchor == anchor)
+            .unwrap()
+    };
+    let id = id_of(&mut splitter, 2);
+
+    // the polygon defines a node plane, so the sub-tree is rebuilt every time
+    let mut allocations = None;
+    for frame in 0..100 {
+        let z = if frame % 2 == 0 { 4.0 } else { 2.0 };
+        let transform = Transform3D::translation(0.0, 0.0, z);
+        let moved = Polygon::from_transformed_rect(rect, transform, 1).unwrap();
+        assert!(!splitter.update(1, moved));
+        assert_eq!(splitter.sort(view).len(), 3);
+        // the re-inserted fragment keeps its slot
+        assert_eq!(id_of(&mut splitter, 2), id);
+        assert_eq!(splitter.fragment_count(), 3);
+        let count = splitter.stats().allocations;
+        assert_eq!(*allocations.get_or_insert(count), count);
+    }
+}
+
+#[test]
+fn uncertain_cuts() {
+    // a tilted wall far from the origin
+    let wall: Polygon<_> = Polygon::from_points(
+        vec![
+            point3(8.0e6 - 8.0, -10.0, -6.0e6 + 6.0),
+            point3(8.0e6 + 8.0, -10.0, -6.0e6 - 6.0),
+            point3(8.0e6 + 8.0, 10.0, -6.0e6 - 6.0),
+            point3(8.0e6 - 8.0, 10.0, -6.0e6 + 6.0),
+        ],
+        0,
+    )
+    .unwrap();
+    // sticking out of the wall by a distance below the precision
+    let (near, far) = (2.0e-9, 4.0e-9);
+    let spike = Polygon::from_points(
+        vec![
+            point3(8.0e6 + 0.6 * near, -10.0, -6.0e6 + 0.8 * near),
+            point3(8.0e6 + 0.6 * far, -10.0, -6.0e6 + 0.8 * far),
+            point3(8.0e6 + 0.6 * far, 10.0, -6.0e6 + 0.8 * far),
+            point3(8.0e6 + 0.6 * near, 10.0, -6.0e6 + 0.8 * near),
+        ],
+        1,
+    )
+    .unwrap();
+    // clearly in front of the wall
+    let panel = Polygon::from_points(
+        vec![
+            point3(8.0e6 - 2.0, -10.0, -6.0e6 + 14.0),
+            point3(8.0e6 + 14.0, -10.0, -6.0e6 + 2.0),
+            point3(8.0e6 + 14.0, 10.0, -6.0e6 + 2.0),
+            point3(8.0e6 - 2.0, 10.0, -6.0e6 + 14.0),
+        ],
+        2,
+    )
+    .unwrap();
+
+    let mut splitter = BspSplitter::new();
+    splitter.set_check_uncertainty(true);
+    splitter.add(wall.clone());
+    splitter.add(panel.clone());
+    assert_eq!(splitter.uncertain_cuts(), &[]);
+    splitter.add(spike.clone());
+    assert_eq!(splitter.uncertain_cuts(), &[(0, 1)]);
+
+    splitter.reset();
+    assert_eq!(splitter.uncertain_cuts(), &[]);
+}
+
+#[test]
+fn recentering() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4, 0.0, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(anchor, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle))
+                .then_translate(vec3(0.0, 1.0e7, 0.0));
+            Polygon::from_transformed_rect(rect, transform, anchor).unwrap()
+        })
+        .collect();
+
+    let mut splitter = BspSplitter::new();
+    splitter.set_recentering(true);
+    let result = splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    let ids: Vec<_> = result.iter().map(|poly| poly.anchor).collect();
+    assert_eq!(&ids, &[2, 1, 0, 1, 2]);
+    for poly in result {
+        assert!(poly.is_valid());
+        for point in &poly.points {
+            assert!((point.y - 1.0e7).abs() <= 10.0);
+        }
+    }
+    assert_eq!(splitter.origin(), point3(0.0, 1.0e7, 0.0));
+    assert_eq!(splitter.validate(vec3(0.0, 0.0, -1.0)), Ok(()));
+
+    splitter.reset();
+    assert_eq!(splitter.origin(), point3(0.0, 0.0, 0.0));
+}
+
+#[test]
+fn raycast() {
+    let mut splitter = BspSplitter::new();
+    sort_rotation(&mut splitter);
+
+    // hits the polygon rotated towards the ray origin first
+    let hit = splitter
+        .raycast(point3(5.0, 0.0, -20.0), vec3(0.0, 0.0, 1.0))
+        .unwrap();
+    assert_eq!(hit.anchor, 2);
+    assert!((hit.point.z + 5.0).abs() < 1.0e-6);
+    assert!((hit.t - 15.0).abs() < 1.0e-6);
+
+    let hit = splitter
+        .raycast(point3(-5.0, 0.0, -20.0), vec3(0.0, 0.0, 1.0))
+        .unwrap();
+    assert_eq!(hit.anchor, 0);
+    // from the other side
+    let hit = splitter
+        .raycast(point3(-5.0, 0.0, 20.0), vec3(0.0, 0.0, -1.0))
+        .unwrap();
+    assert_eq!(hit.anchor, 2);
+    // passing by
+    assert_eq!(
+        splitter.raycast(point3(0.0, 20.0, -20.0), vec3(0.0, 0.0, 1.0)),
+        None
+    );
+    // pointing away
+    assert_eq!(
+        splitter.raycast(point3(5.0, 0.0, -20.0), vec3(0.0, 0.0, -1.0)),
+        None
+    );
+}
+
+#[test]
+fn snapshot() {
+    let view = vec3(0.0, 0.0, -1.0);
+    let floor = Polygon::from_rect(rect(-1.0, -1.0, 2.0, 2.0), 0);
+    let wall = Polygon::from_transformed_rect(
+        rect(-1.0, -1.0, 2.0, 2.0),
+        Transform3D::rotation(0.0, 1.0, 0.0, Angle::frac_pi_2()),
+        1,
+    )
+    .unwrap();
+    let mut splitter = BspSplitter::new();
+    splitter.add(floor);
+    let snapshot = splitter.snapshot();
+    let base = splitter.clone();
+
+    splitter.add(wall);
+    assert_eq!(splitter.sort(view).len(), 3);
+
+    splitter.restore(&snapshot);
+    let mut base = base;
+    assert_eq!(splitter.sort(view), base.sort(view));
+    assert_eq!(splitter.sort(view).len(), 1);
+    assert_eq!(splitter.stats().input_polys, 1);
+}
+
+#[test]
+fn add_all() {
+    let polys = make_grid(3);
+    let view = vec3(0.0, 0.0, 1.0);
+    let mut splitter = BspSplitter::new();
+    let expected = splitter.solve(&polys, view).to_vec();
+
+    splitter.reset();
+    Splitter::add_all(&mut splitter, polys.iter().cloned());
+    assert_eq!(splitter.sort(view), &expected[..]);
+
+    let mut extended = BspSplitter::new();
+    extended.extend(polys);
+    assert_eq!(extended.sort(view), &expected[..]);
+}
+
+#[test]
+fn reuse_storage() {
+    let polys = make_grid(3);
+    let view = vec3(0.0, 0.0, 1.0);
+    let mut splitter = BspSplitter::with_capacity(polys.len());
+    let expected = splitter.solve(&polys, view).to_vec();
+    let node_count = splitter.stats().node_count;
+
+    splitter.reset();
+    assert_eq!(splitter.pool_size(), node_count - 1);
+    assert_eq!(splitter.solve(&polys, view), &expected[..]);
+    assert_eq!(splitter.stats().allocations, 0);
+    assert_eq!(splitter.pool_size(), 0);
+}
+
+#[test]
+fn shared_anchors() {
+    let names: Vec<Rc<str>> = vec!["left".into(), "middle".into(), "right".into()];
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-FRAC_PI_4, 0.0, FRAC_PI_4]
+        .iter()
+        .zip(&names)
+        .map(|(&angle, name)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, Rc::clone(name)).unwrap()
+        })
+        .collect();
+
+    let mut splitter = BspSplitter::new();
+    let view = vec3(0.0, 0.0, -1.0);
+    let count = splitter.solve(&polys, view).len();
+    assert_eq!(count, 5);
+    let lengths = splitter.map_anchors(view, |name| name.len());
+    let expected: Vec<_> = splitter.sorted().iter().map(|p| p.anchor.len()).collect();
+    assert_eq!(
+        lengths.iter().map(|p| p.anchor).collect::<Vec<_>>(),
+        expected
+    );
+    // the options are copied even though the anchors are not `Copy`
+    let options = SortOptions {
+        cull_occluded: true,
+        opaque: &|name: &Rc<str>| &**name == "middle",
+        ..SortOptions::default()
+    };
+    let copy = options;
+    assert_eq!(
+        splitter.sort_with_options(view, &options).len(),
+        splitter.sort_with_options(view, &copy).len()
+    );
+    // the fragments share the names of their sources
+    drop(polys);
+    assert!(names.iter().any(|name| Rc::strong_count(name) > 2));
+}
+
+#[test]
+fn on_split() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [0.0, -FRAC_PI_4, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+
+    let splits = Arc::new(Mutex::new(Vec::new()));
+    let mut splitter = BspSplitter::new();
+    let recorded = Arc::clone(&splits);
+    splitter.on_split(move |&anchor, plane| {
+        recorded.lock().unwrap().push((anchor, plane.clone()));
+    });
+    splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+
+    let splits = splits.lock().unwrap();
+    assert_eq!(splits.len(), splitter.stats().cuts_performed);
+    let anchors: Vec<_> = splits.iter().map(|&(anchor, _)| anchor).collect();
+    assert_eq!(anchors, vec![1, 2]);
+    assert!(splits.iter().all(|(_, plane)| *plane == polys[0].plane));
+}
+
+struct EventLog(Arc<Mutex<Vec<ProfileEvent>>>);
+
+impl Profiler for EventLog {
+    fn event(&mut self, event: ProfileEvent) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+#[test]
+fn profiler() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [0.0, FRAC_PI_4]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut splitter = BspSplitter::new();
+    splitter.set_profiler(Some(Box::new(EventLog(Arc::clone(&events)))));
+    splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            ProfileEvent::InsertStart,
+            ProfileEvent::InsertEnd,
+            ProfileEvent::InsertStart,
+            ProfileEvent::Cut,
+            ProfileEvent::InsertEnd,
+            ProfileEvent::SortStart,
+            ProfileEvent::SortEnd,
+        ]
+    );
+
+    events.lock().unwrap().clear();
+    let mut splitter = BspSplitter::with_heuristic(Heuristic::MinSplits);
+    splitter.set_profiler(Some(Box::new(EventLog(Arc::clone(&events)))));
+    splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    let events = events.lock().unwrap();
+    assert_eq!(events.first(), Some(&ProfileEvent::InsertStart));
+    assert_eq!(events.last(), Some(&ProfileEvent::SortEnd));
+    assert_eq!(
+        events.iter().filter(|&&e| e == ProfileEvent::Cut).count(),
+        1
+    );
+}
+
+#[test]
+fn probe() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let rotated = |angle: f64, anchor: usize| {
+        let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+        Polygon::from_transformed_rect(rect, transform, anchor).unwrap()
+    };
+    let moved =
+        Polygon::from_transformed_rect(rect, Transform3D::translation(0.0, 0.0, 20.0), 3).unwrap();
+
+    let mut splitter = BspSplitter::new();
+    assert_eq!(splitter.probe(&rotated(0.0, 0)), ProbeResult::NoCut);
+    splitter.add(rotated(0.0, 0));
+    splitter.add(rotated(FRAC_PI_4, 1));
+    assert_eq!(splitter.probe(&rotated(0.0, 2)), ProbeResult::Coplanar);
+    assert_eq!(splitter.probe(&moved), ProbeResult::NoCut);
+    // cut in half by the first polygon, which also cuts the second one
+    assert_eq!(
+        splitter.probe(&rotated(-FRAC_PI_4, 2)),
+        ProbeResult::WouldSplit(1)
+    );
+
+    let count = splitter.stats().cuts_performed;
+    assert_eq!(splitter.sort(vec3(0.0, 0.0, -1.0)).len(), 3);
+    assert_eq!(splitter.stats().cuts_performed, count);
+}
+
+#[test]
+fn estimate() {
+    let polys = make_grid(2);
+    let estimate = estimate_fragments(&polys);
+    assert!(estimate >= BspSplitter::new().solve(&polys, vec3(0.0, 0.0, 1.0)).len());
+    assert_eq!(estimate, 24);
+
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let stack: Vec<_> = (0..4)
+        .map(|i| {
+            let transform = Transform3D::translation(0.0, 0.0, i as f64);
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+    assert_eq!(estimate_fragments(&stack), 4);
+    assert_eq!(estimate_fragments::<usize, f64, UnknownUnit>(&[]), 0);
+}
+
+#[test]
+fn correlate() {
+    let rect: Rect<f64> = rect(-1.0, -1.0, 2.0, 2.0);
+    let scene = |shift: f64| {
+        let offset = Transform3D::translation(shift, 0.0, 0.0);
+        vec![
+            Polygon::from_transformed_rect(rect, offset, 0).unwrap(),
+            Polygon::from_transformed_rect(
+                rect,
+                Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(FRAC_PI_2)).then(&offset),
+                1,
+            )
+            .unwrap(),
+        ]
+    };
+    let view = vec3(0.1, 0.2, 1.0);
+    let mut splitter = BspSplitter::new();
+    let previous = splitter.solve(scene(0.0), view).to_vec();
+    assert_eq!(previous.len(), 3);
+
+    // a slightly moved scene keeps the correspondence
+    let current = splitter.solve(scene(0.05), view).to_vec();
+    let map = splitter.correlate(&previous);
+    assert_eq!(map.len(), current.len());
+    for (poly, index) in current.iter().zip(&map) {
+        let other = &previous[index.unwrap()];
+        assert_eq!(other.anchor, poly.anchor);
+        assert_eq!(
+            other.centroid().x.signum(),
+            (poly.centroid().x - 0.05).signum()
+        );
+    }
+
+    // the anchors have to match, and the perpendicular
+    // polygons with the same anchor don't overlap
+    let renamed: Vec<_> = previous.iter().map(|p| p.map_anchor(|&a| a + 1)).collect();
+    let map = splitter.correlate(&renamed);
+    assert_eq!(map, vec![None; current.len()]);
+}
+
+#[test]
+fn sort_flat() {
+    let mut splitter = BspSplitter::new();
+    let polys = make_grid(2);
+    let view = vec3(0.0, 0.0, 1.0);
+    let sorted = splitter.solve(&polys, view).to_vec();
+    let flat = splitter.sort_flat(view);
+    assert_eq!(
+        flat.anchors,
+        sorted.iter().map(|p| p.anchor).collect::<Vec<_>>()
+    );
+    assert_eq!(
+        flat.vertex_counts,
+        sorted
+            .iter()
+            .map(|p| p.points.len() as u8)
+            .collect::<Vec<_>>()
+    );
+    let positions: Vec<f32> = sorted
+        .iter()
+        .flat_map(|p| p.points.iter())
+        .flat_map(|p| vec![p.x as f32, p.y as f32, p.z as f32])
+        .collect();
+    assert_eq!(flat.positions, positions);
+
+    // the full precision is kept on request
+    let flat = FlatOutput::<_, f64>::from_polygons(&sorted);
+    assert_eq!(flat.positions[..3], sorted[0].points[0].to_array());
+}
+
+#[test]
+fn flat_output_limits() {
+    let circle = (0..300).map(|i| {
+        let angle = i as f64 * std::f64::consts::TAU / 300.0;
+        point3(angle.cos(), angle.sin(), 0.0)
+    });
+    let mut poly: Polygon<usize> = Polygon::from_points(circle, 0usize).unwrap();
+    assert_eq!(poly.points.len(), 300);
+
+    // the oversize polygon is packed as fans sharing the first point
+    let flat = FlatOutput::<_, f64>::from_polygons(&[poly.clone()]);
+    assert_eq!(flat.vertex_counts, vec![255, 47]);
+    assert_eq!(flat.anchors, vec![0, 0]);
+    let second = &flat.positions[255 * 3..];
+    assert_eq!(second[..3], poly.points[0].to_array());
+    assert_eq!(second[3..6], poly.points[254].to_array());
+    assert_eq!(second[second.len() - 3..], poly.points[299].to_array());
+
+    // the coordinates out of the `f32` range don't abort
+    poly.points[1].x = 1e300;
+    let flat = FlatOutput::<_, f32>::from_polygons(&[poly.clone()]);
+    assert_eq!(flat.positions[3], f32::INFINITY);
+    assert_eq!(flat.positions[4], poly.points[1].y as f32);
 }
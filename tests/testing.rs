@@ -0,0 +1,31 @@
+#![cfg(feature = "testing")]
+
+use euclid::vec3;
+use plane_split::{testing, BspSplitter, Polygon};
+
+fn check(polys: &[Polygon<usize>], expected_count: usize) {
+    assert_eq!(polys.len(), expected_count);
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+    let result = splitter.solve(polys, view);
+    assert!(result.len() >= polys.len());
+    assert_eq!(splitter.validate(view), Ok(()));
+}
+
+#[test]
+fn generators() {
+    check(&testing::grid(2), 6);
+    check(&testing::random_rects(5, 10), 10);
+    check(&testing::near_coplanar_stack(10, 0.01, 0.001), 10);
+    check(&testing::intersecting_fan(6), 6);
+    check(&testing::slivers(5, 0.1), 5);
+}
+
+#[test]
+fn rng_deterministic() {
+    assert_eq!(testing::random_rects(7, 3), testing::random_rects(7, 3));
+    let mut rng = testing::Rng::new(0);
+    assert!((0..100)
+        .map(|_| rng.next_f64())
+        .all(|x| (0.0..1.0).contains(&x)));
+}
@@ -0,0 +1,42 @@
+use euclid::{default::Transform3D, rect, vec3, Angle};
+use plane_split::{Polygon, TiledSplitter};
+
+#[test]
+fn tiled_split() {
+    let mut splitter = TiledSplitter::<usize>::new(rect(0.0, 0.0, 2.0, 2.0), 2, 2);
+    assert_eq!(splitter.tile_count(), 4);
+    assert_eq!(splitter.tile_rect(3), rect(1.0, 1.0, 1.0, 1.0));
+
+    // a floor covering the whole grid and beyond
+    splitter.add(Polygon::from_rect(rect(-1.0, -1.0, 4.0, 4.0), 0));
+    // a tilted card crossing the floor within the first tile
+    let transform = Transform3D::rotation(1.0, 0.0, 0.0, Angle::degrees(60.0))
+        .then_translate(vec3(0.5, 0.5, 0.0));
+    let card = Polygon::from_transformed_rect(rect(-0.2, -0.2, 0.4, 0.4), transform, 1).unwrap();
+    splitter.add(card);
+    // a polygon outside of the grid
+    splitter.add(Polygon::from_rect(rect(5.0, 5.0, 1.0, 1.0), 2));
+
+    let view = vec3(0.0, 0.0, 1.0);
+    let sorted: Vec<Vec<_>> = splitter.sort(view).map(|tile| tile.to_vec()).collect();
+    assert_eq!(sorted.len(), 4);
+
+    let anchors: Vec<_> = sorted[0].iter().map(|p| p.anchor).collect();
+    assert_eq!(anchors.len(), 3);
+    assert_eq!(anchors.iter().filter(|&&a| a == 1).count(), 2);
+    for (index, tile) in sorted.iter().enumerate().skip(1) {
+        // the other tiles only have the clipped floor
+        assert_eq!(tile.len(), 1);
+        assert_eq!(tile[0].anchor, 0);
+        assert!((tile[0].area() - 1.0).abs() < 1e-9);
+        let rect = splitter.tile_rect(index);
+        for p in &tile[0].points {
+            assert!(rect.min().x <= p.x && p.x <= rect.max().x);
+            assert!(rect.min().y <= p.y && p.y <= rect.max().y);
+        }
+    }
+
+    assert_eq!(splitter.sort_tile(0, view).len(), 3);
+    splitter.reset();
+    assert!(splitter.sort(view).all(|tile| tile.is_empty()));
+}
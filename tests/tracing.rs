@@ -0,0 +1,83 @@
+#![cfg(feature = "tracing")]
+
+use euclid::{default::Transform3D, rect, vec3, Angle};
+use plane_split::{BspSplitter, Polygon};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Metadata, Subscriber,
+};
+
+use std::{
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// Span names along with their fields, in the order of creation.
+type Spans = Arc<Mutex<Vec<(&'static str, Vec<(String, String)>)>>>;
+
+struct Recorder(Spans);
+
+struct Fields<'a>(&'a mut Vec<(String, String)>);
+
+impl Visit for Fields<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        let value = format!("{:?}", value).trim_matches('"').to_string();
+        match self.0.iter_mut().find(|(name, _)| name == field.name()) {
+            Some(entry) => entry.1 = value,
+            None => self.0.push((field.name().to_string(), value)),
+        }
+    }
+}
+
+impl Subscriber for Recorder {
+    fn enabled(&self, _: &Metadata<'_>) -> bool {
+        true
+    }
+    fn new_span(&self, span: &span::Attributes<'_>) -> span::Id {
+        let mut spans = self.0.lock().unwrap();
+        let mut fields = Vec::new();
+        span.record(&mut Fields(&mut fields));
+        spans.push((span.metadata().name(), fields));
+        span::Id::from_u64(spans.len() as u64)
+    }
+    fn record(&self, id: &span::Id, values: &span::Record<'_>) {
+        let mut spans = self.0.lock().unwrap();
+        values.record(&mut Fields(&mut spans[id.into_u64() as usize - 1].1));
+    }
+    fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+    fn event(&self, _: &Event<'_>) {}
+    fn enter(&self, _: &span::Id) {}
+    fn exit(&self, _: &span::Id) {}
+}
+
+fn field<'a>(fields: &'a [(String, String)], name: &str) -> &'a str {
+    &fields.iter().find(|(n, _)| n == name).unwrap().1
+}
+
+#[test]
+fn spans() {
+    let spans = Spans::default();
+    let subscriber = Recorder(spans.clone());
+    tracing::subscriber::with_default(subscriber, || {
+        let rect = rect(-10.0, -10.0, 20.0, 20.0);
+        let mut splitter = BspSplitter::new();
+        for (i, &angle) in [-0.5, 0.5].iter().enumerate() {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            splitter.add(Polygon::from_transformed_rect(rect, transform, i).unwrap());
+        }
+        splitter.sort(vec3(0.0, 0.0, -1.0));
+    });
+
+    let spans = spans.lock().unwrap();
+    let names = spans.iter().map(|&(name, _)| name).collect::<Vec<_>>();
+    assert_eq!(names, ["add", "add", "cut", "sort"]);
+    assert_eq!(field(&spans[1].1, "source"), "1");
+    let cut = &spans[2].1;
+    assert_eq!(field(cut, "source"), "1");
+    assert_eq!(field(cut, "depth"), "0");
+    assert_eq!(field(cut, "classification"), "spanning");
+    assert_eq!(field(cut, "fragments"), "2");
+    let sort = &spans[3].1;
+    assert_eq!(field(sort, "fragments"), "3");
+    assert_eq!(field(sort, "sorted"), "3");
+}
@@ -0,0 +1,151 @@
+use euclid::{
+    default::{Rect, Transform3D},
+    point3, rect, vec3, Angle,
+};
+use plane_split::{BspNode, BspPlane, BspSplitter, PlaneCut, Polygon};
+use smallvec::SmallVec;
+
+/// A horizontal plane at the given height, facing up.
+#[derive(Clone, Debug, PartialEq)]
+struct Level(i32);
+
+impl BspPlane for Level {
+    fn cut(
+        &self,
+        other: &Self,
+        front: &mut SmallVec<[Self; 2]>,
+        back: &mut SmallVec<[Self; 2]>,
+    ) -> PlaneCut {
+        if other.0 == self.0 {
+            return PlaneCut::Sibling;
+        }
+        if other.0 > self.0 {
+            front.push(other.clone());
+        } else {
+            back.push(other.clone());
+        }
+        PlaneCut::Cut
+    }
+
+    fn is_aligned(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[test]
+fn custom_plane() {
+    let mut tree = BspNode::new();
+    assert_eq!(tree.depth(), 0);
+    for &height in &[2, 5, 1, 3, 5] {
+        tree.insert(Level(height));
+    }
+    assert_eq!(tree.values(), &[Level(2)]);
+    assert_eq!(tree.front().unwrap().values(), &[Level(5), Level(5)]);
+    assert_eq!(tree.depth(), 3);
+
+    // looking down, so the planes above are drawn first
+    let mut out = Vec::new();
+    tree.order(&Level(0), &mut out);
+    let heights: Vec<_> = out.iter().map(|level| level.0).collect();
+    assert_eq!(heights, vec![5, 5, 3, 2, 1]);
+}
+
+#[test]
+fn polygon_plane() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let polys: Vec<_> = [-0.5, 0.0, 0.5]
+        .iter()
+        .enumerate()
+        .map(|(i, &angle)| {
+            let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+            Polygon::from_transformed_rect(rect, transform, i).unwrap()
+        })
+        .collect();
+    let view = vec3(0.0, 0.0, -1.0);
+
+    let mut tree = BspNode::new();
+    for poly in &polys {
+        tree.insert(poly.clone());
+    }
+    // the base plane faces away from the viewer
+    let base = Polygon::from_points(
+        vec![
+            point3(0.0, 0.0, 0.0),
+            point3(1.0, 0.0, 0.0),
+            point3(0.0, 1.0, 0.0),
+        ],
+        0,
+    )
+    .unwrap();
+    assert!(base.plane.normal.dot(view) < 0.0);
+    let mut out = Vec::new();
+    tree.order(&base, &mut out);
+
+    let mut splitter = BspSplitter::new();
+    let expected = splitter.solve(&polys, view);
+    assert_eq!(out, expected);
+}
+
+#[test]
+fn splitter_roundtrip() {
+    let rect: Rect<f64> = rect(-10.0, -10.0, 20.0, 20.0);
+    let view = vec3(0.0, 0.0, -1.0);
+    let mut splitter = BspSplitter::new();
+    for (i, &angle) in [-0.5, 0.0, 0.5].iter().enumerate() {
+        let transform = Transform3D::rotation(0.0, 1.0, 0.0, Angle::radians(angle));
+        splitter.add(Polygon::from_transformed_rect(rect, transform, i).unwrap());
+    }
+    let tree = splitter.to_tree();
+    assert!(tree.depth() > 1);
+    let expected = splitter.sort(view).to_vec();
+
+    let mut cached = BspSplitter::from_tree(tree);
+    assert_eq!(cached.sort(view), &expected[..]);
+}
+
+#[test]
+fn display() {
+    let rect: Rect<f64> = rect(0.0, 0.0, 1.0, 1.0);
+    let transform = Transform3D::translation(0.0, 0.0, 5.0);
+    let poly = Polygon::from_transformed_rect(rect, transform, 3).unwrap();
+    assert_eq!(poly.to_string(), "anchor=3, n=(0,0,1), o=-5, 4 pts");
+
+    let mut tree = BspNode::new();
+    tree.insert(poly);
+    tree.insert(Polygon::from_rect(rect, 1));
+    tree.insert(Polygon::from_transformed_rect(rect, transform, 4).unwrap());
+    let expected = [
+        "root: 2 values",
+        "  anchor=3, n=(0,0,1), o=-5, 4 pts",
+        "  anchor=4, n=(0,0,1), o=-5, 4 pts",
+        "  back: 1 value",
+        "    anchor=1, n=(0,0,1), o=0, 4 pts",
+    ];
+    assert_eq!(tree.to_string(), expected.join("\n") + "\n");
+}
+
+#[test]
+fn splitter_display() {
+    let rect: Rect<f64> = rect(0.0, 0.0, 1.0, 1.0);
+    let transform = Transform3D::translation(0.0, 0.0, 5.0);
+    let polys = [
+        Polygon::from_transformed_rect(rect, transform, 3).unwrap(),
+        Polygon::from_rect(rect, 1),
+        Polygon::from_transformed_rect(rect, transform, 4).unwrap(),
+    ];
+    let expected = [
+        "root: n=(0,0,1), o=-5, fragments 0 2",
+        "  back: n=(0,0,1), o=0, fragments 1",
+    ];
+
+    let mut splitter = BspSplitter::new();
+    assert_eq!(splitter.to_string(), "root: empty\n");
+    splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    assert_eq!(splitter.to_string(), expected.join("\n") + "\n");
+
+    // the planes are shown where the polygons are, not relative to the origin
+    let mut splitter = BspSplitter::new();
+    splitter.set_recentering(true);
+    splitter.solve(&polys, vec3(0.0, 0.0, -1.0));
+    assert_eq!(splitter.to_string(), expected.join("\n") + "\n");
+}
@@ -0,0 +1,23 @@
+#![cfg(feature = "wasm")]
+
+use plane_split::wasm::WasmSplitter;
+
+#[test]
+fn wasm_sort() {
+    let front = [0.0, 0.0, 1.0, 1.0, 0.0, 1.0, 1.0, 1.0, 1.0, 0.0, 1.0, 1.0];
+    let back = [0.0, 0.0, 2.0, 1.0, 0.0, 2.0, 1.0, 1.0, 2.0, 0.0, 1.0, 2.0];
+    let mut splitter = WasmSplitter::new();
+    assert!(splitter.add(&front, 1));
+    assert!(splitter.add(&back, 2));
+    assert!(!splitter.add(&[0.0; 9], 3));
+
+    let coordinates = splitter.sort(0.0, 0.0, -1.0);
+    assert_eq!(splitter.point_counts(), vec![4, 4]);
+    assert_eq!(splitter.anchors(), vec![2, 1]);
+    assert_eq!(&coordinates[..12], &back[..]);
+    assert_eq!(&coordinates[12..], &front[..]);
+
+    splitter.reset();
+    assert!(splitter.sort(0.0, 0.0, -1.0).is_empty());
+    assert!(splitter.anchors().is_empty());
+}